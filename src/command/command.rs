@@ -1,3 +1,5 @@
+use clap::Parser;
+
 use crate::{
     config,
     error::{self, Error},
@@ -12,8 +14,30 @@ pub fn commands() -> Vec<shell::command::Command> {
     ];
     let vars_commands = super::vars::commands();
     let show_commands = super::show::commands();
+    let ws_commands = super::ws::commands();
+    let host_commands = super::host::commands();
+    let cred_commands = super::cred::commands();
+    let import_commands = super::import::commands();
+    let web_commands = super::web::commands();
+    let note_commands = super::note::commands();
+    let timeline_commands = super::timeline::commands();
+    let report_commands = super::report::commands();
+    let finding_commands = super::finding::commands();
+    let flag_commands = super::flag::commands();
+    let loot_commands = super::loot::commands();
     commands.extend(vars_commands);
     commands.extend(show_commands);
+    commands.extend(ws_commands);
+    commands.extend(host_commands);
+    commands.extend(cred_commands);
+    commands.extend(import_commands);
+    commands.extend(web_commands);
+    commands.extend(note_commands);
+    commands.extend(timeline_commands);
+    commands.extend(report_commands);
+    commands.extend(finding_commands);
+    commands.extend(flag_commands);
+    commands.extend(loot_commands);
     commands
 }
 
@@ -30,7 +54,7 @@ pub async fn start_shell(app_conf: &mut config::AppConfig) {
 // カレントディレクトリをワークスペースとして初期化するコマンド関数
 fn init(_args: String, app_conf: &mut crate::config::AppConfig) -> bool {
     // AppConfigに用意された専用の関数を使う
-    let _ = match app_conf.init_current_directory_as_workspace() {
+    match app_conf.init_current_directory_as_workspace() {
         Ok(_) => (),
         Err(e) => {
             println!(
@@ -45,10 +69,26 @@ fn init(_args: String, app_conf: &mut crate::config::AppConfig) -> bool {
     false
 }
 
+// use --dry-run では、必要なマイグレーションを表示するだけでワークスペースは切り替えない
+#[derive(Parser, Debug)]
+struct UseArgs {
+    #[arg(long)]
+    dry_run: bool,
+}
+
 // ワークスペースの場所をカレントディレクトリに変更するコマンド関数
-fn use_(_args: String, app_conf: &mut crate::config::AppConfig) -> bool {
+fn use_(args_: String, app_conf: &mut crate::config::AppConfig) -> bool {
+    let mut args_iter = vec![""];
+    args_iter.extend(&args_.split_whitespace().collect::<Vec<&str>>());
+    let args = match UseArgs::try_parse_from(&args_iter) {
+        Ok(a) => a,
+        Err(e) => {
+            println!("{}", e);
+            return true;
+        }
+    };
     // AppConfigに用意された専用の関数を使う
-    let _ = match app_conf.use_current_dir_as_workspace() {
+    match app_conf.use_current_dir_as_workspace(args.dry_run) {
         Ok(_) => (),
         Err(e) => {
             println!(
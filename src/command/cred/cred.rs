@@ -0,0 +1,289 @@
+use chrono::Utc;
+
+use crate::{
+    config::AppConfig,
+    error::{self, CredsError, Error, HostsError},
+    output::{blue, error_prefix, green, log_prefix, red},
+    store::{
+        creds::{self, CredKind, Credential, Try, TryResult},
+        document,
+        findings::FindingBook,
+        hosts,
+    },
+};
+
+// 一覧で値の代わりに表示する文字列
+const MASK: &str = "********";
+
+fn err_msg() {
+    println!("{} cred command error", error_prefix());
+}
+
+pub fn add(
+    app_conf: &AppConfig,
+    user: String,
+    kind: CredKind,
+    plaintext: String,
+    domain: Option<String>,
+    source: Option<String>,
+    plaintext_ok: bool,
+) -> bool {
+    let secret_key = app_conf.secret_key.as_ref();
+    // 鍵がないと平文で保存することになるので、明示的に指定されたときだけ許す
+    if secret_key.is_none() && !plaintext_ok {
+        err_msg();
+        error::print_error(Error::CredsError(CredsError::Locked));
+        return true;
+    }
+    let secret = match creds::seal(secret_key, &plaintext) {
+        Ok(s) => s,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::CredsError(CredsError::SecretError(e)));
+            return true;
+        }
+    };
+    let result = creds::update(app_conf, |vault| {
+        // 暗号化された値は、unlockされていなければ比べられないので重複とはみなさない
+        if let Some(c) = vault.creds.iter().find(|c| {
+            c.user == user
+                && c.domain == domain
+                && c.kind == kind
+                && c.reveal(secret_key).is_ok_and(|p| p == plaintext)
+        }) {
+            return Err(CredsError::CredAlreadyExists(c.id));
+        }
+        let id = vault.take_id();
+        vault.creds.push(Credential {
+            id,
+            user: user.clone(),
+            domain: domain.clone(),
+            kind,
+            secret,
+            source,
+            tries: vec![],
+            added_at: Utc::now(),
+        });
+        Ok(id)
+    });
+    match result {
+        Ok(id) => {
+            let account = match &domain {
+                Some(d) => format!("{}\\{}", d, user),
+                None => user,
+            };
+            println!(
+                "{} added credential {} ({} {})",
+                log_prefix(),
+                id,
+                account,
+                kind
+            );
+            if secret_key.is_none() {
+                println!("{} the value is stored in plaintext", log_prefix());
+            }
+        }
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::CredsError(e));
+            return true;
+        }
+    };
+    false
+}
+
+pub fn list(app_conf: &AppConfig, user: Option<String>, show: bool) -> bool {
+    let creds = match creds::load(app_conf) {
+        Ok(c) => c,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::CredsError(e));
+            return true;
+        }
+    };
+    // 途中で復号に失敗したときに一部だけ表示されないように、先に行を作っておく
+    let mut rows = vec![];
+    for c in &creds {
+        if user.as_ref().is_some_and(|u| u != &c.user) {
+            continue;
+        }
+        let value = if show {
+            match c.reveal(app_conf.secret_key.as_ref()) {
+                Ok(v) => v,
+                Err(e) => {
+                    err_msg();
+                    error::print_error(Error::CredsError(CredsError::SecretError(e)));
+                    return true;
+                }
+            }
+        } else {
+            MASK.to_string()
+        };
+        let ok = c
+            .tries
+            .iter()
+            .filter(|t| t.result == TryResult::Success)
+            .count();
+        rows.push(format!(
+            "{}\t{}\t{}\t{}\t{}\t{} ok / {} tried",
+            blue(&c.id.to_string()),
+            c.account(),
+            c.kind,
+            value,
+            c.source.as_deref().unwrap_or("-"),
+            ok,
+            c.tries.len()
+        ));
+    }
+    for r in &rows {
+        println!("{}", r);
+    }
+    if rows.is_empty() {
+        println!("{} no credential is registered", log_prefix());
+    }
+    false
+}
+
+pub fn try_result(
+    app_conf: &AppConfig,
+    id: u32,
+    host: &str,
+    result: TryResult,
+    service: Option<String>,
+) -> bool {
+    let hosts = match hosts::load(app_conf) {
+        Ok(h) => h,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::CredsError(CredsError::HostsError(e)));
+            return true;
+        }
+    };
+    // 同じホストが別の名前で記録されないように、ホストの名前にそろえる
+    let name = match hosts::resolve(&hosts, host) {
+        Some(n) => n,
+        None => {
+            err_msg();
+            error::print_error(Error::CredsError(CredsError::HostsError(
+                HostsError::HostNotFound(host.to_string()),
+            )));
+            return true;
+        }
+    };
+    let service = match service.map(|s| hosts::parse_port(&s)) {
+        Some(Ok((port, protocol))) => Some(format!("{}/{}", port, protocol)),
+        Some(Err(e)) => {
+            err_msg();
+            error::print_error(Error::CredsError(CredsError::HostsError(e)));
+            return true;
+        }
+        None => None,
+    };
+    let result_ = creds::update(app_conf, |vault| {
+        let c = creds::find_mut(&mut vault.creds, id)?;
+        c.record_try(Try {
+            host: name.clone(),
+            service: service.clone(),
+            result,
+            tried_at: Utc::now(),
+        });
+        Ok(c.account())
+    });
+    match result_ {
+        Ok(account) => {
+            let target = match &service {
+                Some(s) => format!("{} {}", name, s),
+                None => name,
+            };
+            let result = match result {
+                TryResult::Success => green("success"),
+                TryResult::Failure => red("failure"),
+            };
+            println!("{} {} on {}: {}", log_prefix(), account, target, result);
+        }
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::CredsError(e));
+            return true;
+        }
+    };
+    false
+}
+
+// 認証情報を行、試したホストを列にした表
+//  id  account        dc01  web01
+//  1   CORP\svc_bak   ok    x
+//  2   admin          -     ok
+pub fn matrix(app_conf: &AppConfig) -> bool {
+    let creds = match creds::load(app_conf) {
+        Ok(c) => c,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::CredsError(e));
+            return true;
+        }
+    };
+    if creds.is_empty() {
+        println!("{} no credential is registered", log_prefix());
+        return false;
+    }
+    let mut hosts = creds
+        .iter()
+        .flat_map(|c| c.tries.iter().map(|t| t.host.clone()))
+        .collect::<Vec<String>>();
+    hosts.sort();
+    hosts.dedup();
+    if hosts.is_empty() {
+        println!(
+            "{} no result is recorded. use `cred try-result` first",
+            log_prefix()
+        );
+        return false;
+    }
+    println!("id\taccount\t{}", hosts.join("\t"));
+    for c in &creds {
+        let cells = hosts
+            .iter()
+            .map(|h| match c.result_on(h) {
+                Some(TryResult::Success) => green("ok"),
+                Some(TryResult::Failure) => red("x"),
+                None => "-".to_string(),
+            })
+            .collect::<Vec<String>>();
+        println!("{}\t{}\t{}", c.id, c.account(), cells.join("\t"));
+    }
+    false
+}
+
+pub fn rm(app_conf: &AppConfig, id: u32) -> bool {
+    let findings_path = app_conf.dyn_conf.to_workspace().findings_path;
+    let result = creds::update(app_conf, |vault| {
+        if !vault.creds.iter().any(|c| c.id == id) {
+            return Err(CredsError::CredNotFound(id));
+        }
+        // 指摘事項から参照されている認証情報を消すと、報告書で別のものを指してしまうので消さない
+        let findings = match document::read::<FindingBook>(&findings_path) {
+            Ok(b) => b.findings,
+            Err(e) => return Err(CredsError::ReadFindingsError(e)),
+        };
+        let used_by = findings
+            .iter()
+            .filter(|f| f.creds.contains(&id))
+            .map(|f| f.id.to_string())
+            .collect::<Vec<String>>();
+        if !used_by.is_empty() {
+            return Err(CredsError::CredInUse(id, used_by.join(", ")));
+        }
+        vault.creds.retain(|c| c.id != id);
+        Ok(())
+    });
+    match result {
+        Ok(_) => println!("{} removed credential {}", log_prefix(), id),
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::CredsError(e));
+            return true;
+        }
+    };
+    false
+}
@@ -0,0 +1,81 @@
+use std::{fs, path::PathBuf};
+
+use super::ExportFormat;
+use crate::{
+    config::AppConfig,
+    error::{self, CredsError, Error},
+    output::{error_prefix, log_prefix},
+    store::creds::{self, CredKind},
+};
+
+// スプレーなどのツールに渡せるように、1行に1つ user:pass または user:hash の形で書き出す
+// 暗号化された値を書き出すにはunlockされている必要がある
+pub fn export(
+    app_conf: &AppConfig,
+    format: ExportFormat,
+    out: Option<PathBuf>,
+    with_domain: bool,
+) -> bool {
+    let err_msg = || {
+        println!("{} cred export error", error_prefix());
+    };
+    let creds = match creds::load(app_conf) {
+        Ok(c) => c,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::CredsError(e));
+            return true;
+        }
+    };
+    let kind = match format {
+        ExportFormat::Pass => CredKind::Password,
+        ExportFormat::Hash => CredKind::Hash,
+    };
+    let mut lines = vec![];
+    for c in creds.iter().filter(|c| c.kind == kind) {
+        let value = match c.reveal(app_conf.secret_key.as_ref()) {
+            Ok(v) => v,
+            Err(e) => {
+                err_msg();
+                error::print_error(Error::CredsError(CredsError::SecretError(e)));
+                return true;
+            }
+        };
+        let user = if with_domain {
+            c.account()
+        } else {
+            c.user.clone()
+        };
+        let line = format!("{}:{}", user, value);
+        if !lines.contains(&line) {
+            lines.push(line);
+        }
+    }
+    match out {
+        Some(path) => {
+            let content = lines.iter().map(|l| format!("{}\n", l)).collect::<String>();
+            match fs::write(&path, content) {
+                Ok(_) => println!(
+                    "{} exported {} credentials to {}",
+                    log_prefix(),
+                    lines.len(),
+                    path.display()
+                ),
+                Err(e) => {
+                    err_msg();
+                    error::print_error(Error::CredsError(CredsError::WriteError(e)));
+                    return true;
+                }
+            };
+        }
+        None => {
+            for l in &lines {
+                println!("{}", l);
+            }
+            if lines.is_empty() {
+                println!("{} no {} is registered", log_prefix(), kind);
+            }
+        }
+    }
+    false
+}
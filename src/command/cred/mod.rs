@@ -0,0 +1,145 @@
+use std::path::PathBuf;
+
+use clap::{ArgGroup, Parser, Subcommand, ValueEnum};
+
+use crate::{
+    json::json::{split_quoted_args, unquote},
+    store::creds::{CredKind, TryResult},
+};
+
+#[allow(clippy::module_inception)]
+pub mod cred;
+pub mod export;
+
+pub fn commands() -> Vec<crate::shell::command::Command> {
+    vec![crate::shell::command::Command::new("cred", Box::new(cred_))]
+}
+
+// cred はワークスペースで見つけた認証情報を扱うコマンド
+//  cred add --user U (--pass P | --hash H | --key K) [--domain D] [--source S] [--plaintext]
+//                                                                  認証情報を登録する。unlockしていなければ --plaintext が必要
+//  cred list [--user U] [--show]                                   一覧を表示する。値は --show のときだけ表示する
+//  cred try-result <id> <host> <success|failure> [--service 445/tcp]   どこで試して使えたかを記録する
+//  cred matrix                                                     認証情報ごとに、どのホストで使えたかを表で表示する
+//  cred export --format pass|hash [--out FILE] [--with-domain]     user:pass, user:hash の形で書き出す
+//  cred rm <id>                                                    認証情報を削除する
+// 空白や引用符を含む値はシェルのように '...' や "..." で囲んで書く (json::split_quoted_argsを参照)
+#[derive(Parser, Debug)]
+struct CredArgs {
+    #[command(subcommand)]
+    command: CredCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum CredCommand {
+    /// register a credential
+    #[command(group(ArgGroup::new("secret").required(true).args(["pass", "hash", "key"])))]
+    Add {
+        #[arg(long)]
+        user: String,
+        #[arg(long)]
+        pass: Option<String>,
+        /// ntlm hash or other hash usable for pass-the-hash
+        #[arg(long)]
+        hash: Option<String>,
+        /// private key or the path to it
+        #[arg(long)]
+        key: Option<String>,
+        #[arg(long)]
+        domain: Option<String>,
+        /// where the credential was found
+        #[arg(long)]
+        source: Option<String>,
+        /// store the value in plaintext when the secret key is not unlocked
+        #[arg(long)]
+        plaintext: bool,
+    },
+    /// list credentials. the secret values are masked unless --show is given
+    List {
+        /// only list credentials of this user
+        #[arg(long)]
+        user: Option<String>,
+        #[arg(long)]
+        show: bool,
+    },
+    /// record whether a credential worked on a host or service
+    TryResult {
+        id: u32,
+        /// name, address or hostname of the host
+        host: String,
+        #[arg(value_enum)]
+        result: TryResult,
+        /// port like 445 or 161/udp
+        #[arg(long)]
+        service: Option<String>,
+    },
+    /// show which credential worked on which host
+    Matrix,
+    /// export credentials for spraying tools
+    Export {
+        #[arg(long, value_enum)]
+        format: ExportFormat,
+        /// write to this file instead of printing
+        #[arg(long)]
+        out: Option<PathBuf>,
+        /// prefix users with DOMAIN\
+        #[arg(long)]
+        with_domain: bool,
+    },
+    /// remove a credential
+    Rm { id: u32 },
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum ExportFormat {
+    /// user:pass lines from passwords
+    Pass,
+    /// user:hash lines from hashes
+    Hash,
+}
+
+fn cred_(args_: String, app_conf: &mut crate::config::AppConfig) -> bool {
+    let mut args_iter = vec!["cred".to_string()];
+    args_iter.extend(split_quoted_args(&args_).into_iter().map(unquote));
+    let args = match CredArgs::try_parse_from(&args_iter) {
+        Ok(a) => a,
+        Err(e) => {
+            println!("{}", e);
+            return true;
+        }
+    };
+    match args.command {
+        CredCommand::Add {
+            user,
+            pass,
+            hash,
+            key,
+            domain,
+            source,
+            plaintext,
+        } => {
+            // clapのArgGroupでどれか1つだけが指定されている
+            let (kind, value) = match (pass, hash, key) {
+                (Some(p), _, _) => (CredKind::Password, p),
+                (_, Some(h), _) => (CredKind::Hash, h),
+                (_, _, Some(k)) => (CredKind::Key, k),
+                _ => return true,
+            };
+            cred::add(app_conf, user, kind, value, domain, source, plaintext)
+        }
+        CredCommand::List { user, show } => cred::list(app_conf, user, show),
+        CredCommand::TryResult {
+            id,
+            host,
+            result,
+            service,
+        } => cred::try_result(app_conf, id, &host, result, service),
+        CredCommand::Matrix => cred::matrix(app_conf),
+        CredCommand::Export {
+            format,
+            out,
+            with_domain,
+        } => export::export(app_conf, format, out, with_domain),
+        CredCommand::Rm { id } => cred::rm(app_conf, id),
+    }
+}
@@ -0,0 +1,52 @@
+use std::path::PathBuf;
+
+use chrono::Utc;
+
+use crate::{
+    config::AppConfig,
+    error::{self, Error},
+    output::{error_prefix, green, log_prefix},
+    store::findings,
+};
+
+// 証拠のファイルをワークスペースにコピーして、SHA-256と一緒に指摘事項に記録する
+// ファイルのコピーはfindings.jsonの排他ロックを取った状態で行う
+pub fn attach(app_conf: &AppConfig, id: u32, files: Vec<PathBuf>) -> bool {
+    let evidence_path = app_conf.dyn_conf.to_workspace().evidence_path;
+    let result = findings::update(app_conf, |book| {
+        let f = findings::find_mut(&mut book.findings, id)?;
+        let mut report = vec![];
+        for file in &files {
+            let (evidence, copied) = findings::copy_evidence(&evidence_path, f, file)?;
+            if copied {
+                report.push(green(&format!(
+                    "+ {}\tsha256:{}",
+                    evidence.file, evidence.sha256
+                )));
+                f.evidence.push(evidence);
+                f.updated_at = Utc::now();
+            } else {
+                report.push(format!(
+                    "{} {} is already attached as {}",
+                    log_prefix(),
+                    file.display(),
+                    evidence.file
+                ));
+            }
+        }
+        Ok(report)
+    });
+    match result {
+        Ok(report) => {
+            for line in report {
+                println!("{}", line);
+            }
+        }
+        Err(e) => {
+            println!("{} finding evidence error", error_prefix());
+            error::print_error(Error::FindingsError(e));
+            return true;
+        }
+    };
+    false
+}
@@ -0,0 +1,413 @@
+use std::fs;
+
+use chrono::{Local, Utc};
+
+use super::SortKey;
+use crate::{
+    command::vars::edit::open_editor,
+    config::AppConfig,
+    error::{self, CredsError, Error, FindingsError, HostsError},
+    output::{blue, error_prefix, log_prefix, red},
+    store::{
+        creds,
+        findings::{self, Finding, FindingStatus, Severity},
+        hosts, lock,
+    },
+};
+
+fn err_msg() {
+    println!("{} finding command error", error_prefix());
+}
+
+pub struct NewFinding {
+    pub title: String,
+    pub severity: Severity,
+    pub cvss: Option<f32>,
+    pub hosts: Vec<String>,
+    pub creds: Vec<u32>,
+    pub status: FindingStatus,
+    pub description: String,
+}
+
+// 指定されたホストをhosts.jsonの名前に、認証情報はcreds.jsonにあるかを確かめる
+fn resolve(
+    app_conf: &AppConfig,
+    host_args: Vec<String>,
+    cred_ids: &[u32],
+) -> Result<Vec<String>, FindingsError> {
+    let mut names = vec![];
+    if !host_args.is_empty() {
+        let hosts = match hosts::load(app_conf) {
+            Ok(h) => h,
+            Err(e) => return Err(FindingsError::HostsError(e)),
+        };
+        for h in host_args {
+            match hosts::resolve(&hosts, &h) {
+                Some(n) => names.push(n),
+                None => {
+                    return Err(FindingsError::HostsError(HostsError::HostNotFound(h)));
+                }
+            }
+        }
+    }
+    if !cred_ids.is_empty() {
+        let creds = match creds::load(app_conf) {
+            Ok(c) => c,
+            Err(e) => return Err(FindingsError::CredsError(e)),
+        };
+        for id in cred_ids {
+            if !creds.iter().any(|c| c.id == *id) {
+                return Err(FindingsError::CredsError(CredsError::CredNotFound(*id)));
+            }
+        }
+    }
+    Ok(names)
+}
+
+pub fn add(app_conf: &AppConfig, new: NewFinding) -> bool {
+    let result = resolve(app_conf, new.hosts, &new.creds).and_then(|hosts| {
+        if let Some(c) = new.cvss {
+            findings::validate_cvss(c)?;
+        }
+        let evidence_path = app_conf.dyn_conf.to_workspace().evidence_path;
+        findings::update(app_conf, |book| {
+            // findings.jsonを手で編集した場合などに備えて、証拠のディレクトリが残っているidは使わない
+            let id = loop {
+                let id = book.take_id();
+                if !evidence_path.join(id.to_string()).exists() {
+                    break id;
+                }
+            };
+            let mut creds = vec![];
+            for c in &new.creds {
+                if !creds.contains(c) {
+                    creds.push(*c);
+                }
+            }
+            let mut host_names = vec![];
+            hosts::add_unique(&mut host_names, hosts);
+            book.findings.push(Finding {
+                id,
+                title: new.title.clone(),
+                severity: new.severity,
+                cvss: new.cvss,
+                status: new.status,
+                hosts: host_names,
+                creds,
+                description: new.description,
+                evidence: vec![],
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+            });
+            Ok(id)
+        })
+    });
+    match result {
+        Ok(id) => println!(
+            "{} added finding {} ({}) {}",
+            log_prefix(),
+            id,
+            new.severity,
+            new.title
+        ),
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::FindingsError(e));
+            return true;
+        }
+    };
+    false
+}
+
+pub fn list(
+    app_conf: &AppConfig,
+    status: Option<FindingStatus>,
+    severity: Option<Severity>,
+    host: Option<String>,
+    sort: SortKey,
+) -> bool {
+    let result = findings::load(app_conf).and_then(|findings| {
+        // ホストはアドレスやホスト名でも指定できるので、名前にそろえてから比べる
+        let host = match host {
+            Some(h) => resolve(app_conf, vec![h], &[])?.pop(),
+            None => None,
+        };
+        Ok((findings, host))
+    });
+    let (mut findings, host) = match result {
+        Ok(r) => r,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::FindingsError(e));
+            return true;
+        }
+    };
+    findings.retain(|f| {
+        status.is_none_or(|s| f.status == s)
+            && severity.is_none_or(|s| f.severity == s)
+            && host.as_ref().is_none_or(|h| f.hosts.contains(h))
+    });
+    match sort {
+        SortKey::Severity => findings.sort_by_key(|f| (f.severity, f.id)),
+        // cvssは高い順。cvssがないものは最後
+        SortKey::Cvss => findings.sort_by(|a, b| {
+            b.cvss
+                .unwrap_or(-1.0)
+                .total_cmp(&a.cvss.unwrap_or(-1.0))
+                .then(a.id.cmp(&b.id))
+        }),
+        SortKey::Status => findings.sort_by_key(|f| (f.status, f.severity, f.id)),
+        SortKey::Id => findings.sort_by_key(|f| f.id),
+    }
+    for f in &findings {
+        println!(
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            blue(&f.id.to_string()),
+            severity_label(f.severity),
+            f.cvss
+                .map(|c| format!("{:.1}", c))
+                .unwrap_or("-".to_string()),
+            f.status,
+            if f.hosts.is_empty() {
+                "-".to_string()
+            } else {
+                f.hosts.join(",")
+            },
+            f.title
+        );
+    }
+    if findings.is_empty() {
+        println!("{} no finding is registered", log_prefix());
+    }
+    false
+}
+
+// criticalとhighは目立つようにする
+fn severity_label(severity: Severity) -> String {
+    match severity {
+        Severity::Critical | Severity::High => red(&severity.to_string()),
+        s => s.to_string(),
+    }
+}
+
+pub fn show(app_conf: &AppConfig, id: u32) -> bool {
+    let result = findings::load(app_conf).and_then(|findings| {
+        match findings.into_iter().find(|f| f.id == id) {
+            Some(f) => Ok(f),
+            None => Err(FindingsError::FindingNotFound(id)),
+        }
+    });
+    let f = match result {
+        Ok(f) => f,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::FindingsError(e));
+            return true;
+        }
+    };
+    let time = |t: &chrono::DateTime<Utc>| {
+        t.with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string()
+    };
+    println!("{} {}", blue(&f.id.to_string()), f.title);
+    println!("\tseverity\t{}", severity_label(f.severity));
+    println!(
+        "\tcvss\t\t{}",
+        f.cvss
+            .map(|c| format!("{:.1}", c))
+            .unwrap_or("-".to_string())
+    );
+    println!("\tstatus\t\t{}", f.status);
+    println!("\thosts\t\t{}", f.hosts.join(", "));
+    println!(
+        "\tcreds\t\t{}",
+        f.creds
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<String>>()
+            .join(", ")
+    );
+    println!("\tcreated\t\t{}", time(&f.created_at));
+    println!("\tupdated\t\t{}", time(&f.updated_at));
+    println!("\tevidence");
+    for e in &f.evidence {
+        println!("\t\t{}\tsha256:{}", e.file, e.sha256);
+    }
+    if !f.description.is_empty() {
+        println!();
+        println!("{}", f.description);
+    }
+    false
+}
+
+pub fn edit(app_conf: &AppConfig, id: u32) -> bool {
+    let current = match findings::load(app_conf).and_then(|findings| {
+        match findings.into_iter().find(|f| f.id == id) {
+            Some(f) => Ok(f.description),
+            None => Err(FindingsError::FindingNotFound(id)),
+        }
+    }) {
+        Ok(d) => d,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::FindingsError(e));
+            return true;
+        }
+    };
+    let description = match edit_description(id, &current) {
+        Ok(d) => d,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::FindingsError(e));
+            return true;
+        }
+    };
+    if description == current {
+        println!("{} nothing changed", log_prefix());
+        return false;
+    }
+    let result = findings::update(app_conf, |book| {
+        let f = findings::find_mut(&mut book.findings, id)?;
+        f.description = description;
+        f.updated_at = Utc::now();
+        Ok(())
+    });
+    match result {
+        Ok(_) => println!("{} updated the description of finding {}", log_prefix(), id),
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::FindingsError(e));
+            return true;
+        }
+    };
+    false
+}
+
+// 説明を一時ファイルに書き出してエディタで開き、保存された内容を返す
+fn edit_description(id: u32, current: &str) -> Result<String, FindingsError> {
+    // 説明には認証情報を書くこともあるので、ほかのユーザーから読めないディレクトリに置く
+    let dir = match lock::create_private_temp_dir("pwnrail-finding") {
+        Ok(d) => d,
+        Err(e) => {
+            return Err(FindingsError::EditorError(format!(
+                "failed to create a temporary directory: {}",
+                e
+            )))
+        }
+    };
+    let path = dir.join(format!("finding-{}.md", id));
+    let result = fs::write(&path, format!("{}\n", current))
+        .map_err(|e| FindingsError::EditorError(format!("failed to write a temporary file: {}", e)))
+        .and_then(|_| open_editor(&path).map_err(FindingsError::EditorError))
+        .and_then(|_| {
+            fs::read_to_string(&path).map_err(|e| {
+                FindingsError::EditorError(format!("failed to read the temporary file: {}", e))
+            })
+        });
+    let _ = fs::remove_dir_all(&dir);
+    result.map(|d| d.trim_end().to_string())
+}
+
+pub fn status(app_conf: &AppConfig, id: u32, status: FindingStatus) -> bool {
+    let result = findings::update(app_conf, |book| {
+        let f = findings::find_mut(&mut book.findings, id)?;
+        let old = f.status;
+        f.status = status;
+        f.updated_at = Utc::now();
+        Ok(old)
+    });
+    match result {
+        Ok(old) => println!(
+            "{} status of finding {}: {} -> {}",
+            log_prefix(),
+            id,
+            old,
+            status
+        ),
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::FindingsError(e));
+            return true;
+        }
+    };
+    false
+}
+
+pub fn link(app_conf: &AppConfig, id: u32, hosts: Vec<String>, creds: Vec<u32>, rm: bool) -> bool {
+    let result = resolve(app_conf, hosts, &creds).and_then(|host_names| {
+        findings::update(app_conf, |book| {
+            let f = findings::find_mut(&mut book.findings, id)?;
+            if rm {
+                f.hosts.retain(|h| !host_names.contains(h));
+                f.creds.retain(|c| !creds.contains(c));
+            } else {
+                hosts::add_unique(&mut f.hosts, host_names);
+                for c in creds {
+                    if !f.creds.contains(&c) {
+                        f.creds.push(c);
+                    }
+                }
+            }
+            f.updated_at = Utc::now();
+            Ok((f.hosts.clone(), f.creds.clone()))
+        })
+    });
+    match result {
+        Ok((hosts, creds)) => println!(
+            "{} finding {}: hosts [{}], creds [{}]",
+            log_prefix(),
+            id,
+            hosts.join(", "),
+            creds
+                .iter()
+                .map(|c| c.to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::FindingsError(e));
+            return true;
+        }
+    };
+    false
+}
+
+pub fn rm(app_conf: &AppConfig, id: u32) -> bool {
+    let result = findings::update(app_conf, |book| {
+        let before = book.findings.len();
+        book.findings.retain(|f| f.id != id);
+        if book.findings.len() == before {
+            return Err(FindingsError::FindingNotFound(id));
+        }
+        Ok(())
+    });
+    match result {
+        Ok(_) => (),
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::FindingsError(e));
+            return true;
+        }
+    };
+    let dir = app_conf
+        .dyn_conf
+        .to_workspace()
+        .evidence_path
+        .join(id.to_string());
+    if dir.exists() {
+        match fs::remove_dir_all(&dir) {
+            Ok(_) => (),
+            Err(e) => {
+                err_msg();
+                error::print_error(Error::FindingsError(FindingsError::WriteEvidenceError(
+                    dir, e,
+                )));
+                return true;
+            }
+        };
+    }
+    println!("{} removed finding {}", log_prefix(), id);
+    false
+}
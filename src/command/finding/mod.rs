@@ -0,0 +1,154 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand, ValueEnum};
+
+use crate::store::findings::{FindingStatus, Severity};
+
+pub mod evidence;
+#[allow(clippy::module_inception)]
+pub mod finding;
+
+pub fn commands() -> Vec<crate::shell::command::Command> {
+    vec![crate::shell::command::Command::new(
+        "finding",
+        Box::new(finding_),
+    )]
+}
+
+// finding はワークスペースの指摘事項を扱うコマンド
+//  finding add --title T... --severity S [--cvss N] [--host H]... [--cred ID]... [--description D...]
+//  finding list [--status S] [--severity S] [--host H] [--sort severity|cvss|status|id]
+//  finding show <id>                       説明と証拠を含めて表示する
+//  finding edit <id>                       Markdownの説明を$EDITORで編集する
+//  finding status <id> <open|confirmed|reported>
+//  finding link <id> [--host H]... [--cred ID]... [--rm]   ホストや認証情報を関連付ける・外す
+//  finding evidence <id> <file>...         証拠のファイルを .prail/evidence/ にコピーして添付する
+//  finding rm <id>                         指摘事項と証拠のファイルを削除する
+#[derive(Parser, Debug)]
+struct FindingArgs {
+    #[command(subcommand)]
+    command: FindingCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum FindingCommand {
+    /// register a finding
+    Add {
+        #[arg(long, num_args = 1.., required = true)]
+        title: Vec<String>,
+        #[arg(long, value_enum)]
+        severity: Severity,
+        #[arg(long)]
+        cvss: Option<f32>,
+        /// name, address or hostname of an affected host. can be given multiple times
+        #[arg(long = "host")]
+        hosts: Vec<String>,
+        /// id of a credential used for or found by this finding. can be given multiple times
+        #[arg(long = "cred")]
+        creds: Vec<u32>,
+        #[arg(long, value_enum, default_value = "open")]
+        status: FindingStatus,
+        /// markdown description. use `finding edit` to write a longer one
+        #[arg(long, num_args = 1..)]
+        description: Vec<String>,
+    },
+    /// list findings
+    List {
+        #[arg(long, value_enum)]
+        status: Option<FindingStatus>,
+        #[arg(long, value_enum)]
+        severity: Option<Severity>,
+        /// only list findings on this host
+        #[arg(long)]
+        host: Option<String>,
+        #[arg(long, value_enum, default_value = "severity")]
+        sort: SortKey,
+    },
+    /// show a finding with its description and evidence
+    Show { id: u32 },
+    /// edit the description of a finding in $EDITOR
+    Edit { id: u32 },
+    /// change the status of a finding
+    Status {
+        id: u32,
+        #[arg(value_enum)]
+        status: FindingStatus,
+    },
+    /// link hosts and credentials to a finding, or unlink them with --rm
+    Link {
+        id: u32,
+        #[arg(long = "host")]
+        hosts: Vec<String>,
+        #[arg(long = "cred")]
+        creds: Vec<u32>,
+        #[arg(long)]
+        rm: bool,
+    },
+    /// copy files into the workspace and attach them to a finding
+    Evidence {
+        id: u32,
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// remove a finding and its evidence files
+    Rm { id: u32 },
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum SortKey {
+    Severity,
+    Cvss,
+    Status,
+    Id,
+}
+
+fn finding_(args_: String, app_conf: &mut crate::config::AppConfig) -> bool {
+    let mut args_iter = vec!["finding"];
+    args_iter.extend(&args_.split_whitespace().collect::<Vec<&str>>());
+    let args = match FindingArgs::try_parse_from(&args_iter) {
+        Ok(a) => a,
+        Err(e) => {
+            println!("{}", e);
+            return true;
+        }
+    };
+    match args.command {
+        FindingCommand::Add {
+            title,
+            severity,
+            cvss,
+            hosts,
+            creds,
+            status,
+            description,
+        } => finding::add(
+            app_conf,
+            finding::NewFinding {
+                title: title.join(" "),
+                severity,
+                cvss,
+                hosts,
+                creds,
+                status,
+                description: description.join(" "),
+            },
+        ),
+        FindingCommand::List {
+            status,
+            severity,
+            host,
+            sort,
+        } => finding::list(app_conf, status, severity, host, sort),
+        FindingCommand::Show { id } => finding::show(app_conf, id),
+        FindingCommand::Edit { id } => finding::edit(app_conf, id),
+        FindingCommand::Status { id, status } => finding::status(app_conf, id, status),
+        FindingCommand::Link {
+            id,
+            hosts,
+            creds,
+            rm,
+        } => finding::link(app_conf, id, hosts, creds, rm),
+        FindingCommand::Evidence { id, files } => evidence::attach(app_conf, id, files),
+        FindingCommand::Rm { id } => finding::rm(app_conf, id),
+    }
+}
@@ -0,0 +1,200 @@
+use chrono::{Local, Utc};
+
+use crate::{
+    config::AppConfig,
+    error::{self, Error, FlagsError},
+    output::{blue, error_prefix, green, log_prefix},
+    store::{
+        flags::{self, Flag, FlagKind},
+        hosts,
+    },
+};
+
+fn err_msg() {
+    println!("{} flag command error", error_prefix());
+}
+
+pub fn add(
+    app_conf: &AppConfig,
+    value: String,
+    host: Option<String>,
+    kind: FlagKind,
+    source: Option<String>,
+    force: bool,
+) -> bool {
+    let value = value.trim().to_string();
+    // CTFではホストを登録していないこともあるので、見つからなければ指定された名前のまま記録する
+    let host = match host {
+        Some(h) => match hosts::load(app_conf) {
+            Ok(hosts) => Some(hosts::resolve(&hosts, &h).unwrap_or(h)),
+            Err(e) => {
+                err_msg();
+                error::print_error(Error::FlagsError(FlagsError::HostsError(e)));
+                return true;
+            }
+        },
+        None => None,
+    };
+    let result = flags::update(app_conf, |board| {
+        if let Some(f) = board.flags.iter().find(|f| f.value == value) {
+            let time = f
+                .captured_at
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M:%S");
+            return Err(FlagsError::FlagAlreadyCaptured(time.to_string()));
+        }
+        if let (Some(platform), false) = (&board.platform, force) {
+            let formats = flags::formats(&app_conf.app_conf_path)?;
+            flags::validate(&formats, platform, &value)?;
+        }
+        board.flags.push(Flag {
+            value: value.clone(),
+            kind,
+            host,
+            source,
+            captured_at: Utc::now(),
+        });
+        Ok(flags::progress(board))
+    });
+    match result {
+        Ok(progress) => println!(
+            "{} captured {} flag ({})",
+            log_prefix(),
+            kind,
+            progress.unwrap_or_default()
+        ),
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::FlagsError(e));
+            return true;
+        }
+    };
+    false
+}
+
+pub fn list(app_conf: &AppConfig) -> bool {
+    let board = match flags::load(app_conf) {
+        Ok(b) => b,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::FlagsError(e));
+            return true;
+        }
+    };
+    for f in &board.flags {
+        println!(
+            "{}\t{}\t{}\t{}\t{}",
+            blue(&f.kind.to_string()),
+            f.value,
+            f.host.as_deref().unwrap_or("-"),
+            f.source.as_deref().unwrap_or("-"),
+            f.captured_at
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M:%S")
+        );
+    }
+    let count = |kind: FlagKind| board.flags.iter().filter(|f| f.kind == kind).count();
+    let captured = match board.expected {
+        Some(n) => format!("{}/{}", board.flags.len(), n),
+        None => board.flags.len().to_string(),
+    };
+    let summary = format!(
+        "{} flags captured (user {}, root {}, other {}), platform: {}",
+        captured,
+        count(FlagKind::User),
+        count(FlagKind::Root),
+        count(FlagKind::Other),
+        board.platform.as_deref().unwrap_or("-")
+    );
+    if board
+        .expected
+        .is_some_and(|n| board.flags.len() as u32 >= n)
+    {
+        println!("{} {}", log_prefix(), green(&summary));
+    } else {
+        println!("{} {}", log_prefix(), summary);
+    }
+    false
+}
+
+pub fn set(app_conf: &AppConfig, platform: Option<String>, expected: Option<u32>) -> bool {
+    let result = flags::update(app_conf, |board| {
+        if let Some(p) = platform {
+            if p == "none" {
+                board.platform = None;
+            } else {
+                let formats = flags::formats(&app_conf.app_conf_path)?;
+                if !formats.contains_key(&p) {
+                    return Err(FlagsError::UnknownPlatform(p));
+                }
+                board.platform = Some(p);
+            }
+        }
+        if let Some(n) = expected {
+            board.expected = if n == 0 { None } else { Some(n) };
+        }
+        Ok((board.platform.clone(), board.expected))
+    });
+    match result {
+        Ok((platform, expected)) => println!(
+            "{} platform: {}, expected flags: {}",
+            log_prefix(),
+            platform.as_deref().unwrap_or("-"),
+            expected.map(|n| n.to_string()).unwrap_or("-".to_string())
+        ),
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::FlagsError(e));
+            return true;
+        }
+    };
+    false
+}
+
+pub fn formats(app_conf: &AppConfig) -> bool {
+    let formats = match flags::formats(&app_conf.app_conf_path) {
+        Ok(f) => f,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::FlagsError(e));
+            return true;
+        }
+    };
+    // ワークスペースを使っていなければ、現在のプラットフォームの印は付けない
+    let current = flags::load(app_conf).ok().and_then(|b| b.platform);
+    for (name, format) in &formats {
+        let mark = if current.as_ref() == Some(name) {
+            "*"
+        } else {
+            " "
+        };
+        println!(
+            "{} {}\t{}{}",
+            mark,
+            blue(name),
+            format.regex,
+            if format.custom { "\t(custom)" } else { "" }
+        );
+    }
+    false
+}
+
+pub fn rm(app_conf: &AppConfig, value: String) -> bool {
+    let result = flags::update(app_conf, |board| {
+        let before = board.flags.len();
+        board.flags.retain(|f| f.value != value);
+        if board.flags.len() == before {
+            return Err(FlagsError::FlagNotFound(value.clone()));
+        }
+        Ok(())
+    });
+    match result {
+        Ok(_) => println!("{} removed flag {}", log_prefix(), value),
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::FlagsError(e));
+            return true;
+        }
+    };
+    false
+}
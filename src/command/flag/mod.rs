@@ -0,0 +1,82 @@
+use clap::{Parser, Subcommand};
+
+use crate::store::flags::FlagKind;
+
+#[allow(clippy::module_inception)]
+pub mod flag;
+
+pub fn commands() -> Vec<crate::shell::command::Command> {
+    vec![crate::shell::command::Command::new("flag", Box::new(flag_))]
+}
+
+// flag はCTFのフラグを記録するコマンド
+//  flag add <value> [--host H] [--kind user|root|other] [--source S] [--force]
+//                                  フラグを記録する。platformが設定されていれば形式を確かめる
+//  flag ls                         取ったフラグと、取った数/取るべき数を表示する
+//  flag set [--platform P] [--expected N]  このワークスペースのプラットフォームと取るべきフラグの数を設定する
+//  flag formats                    プラットフォームごとのフラグの形式を表示する
+//  flag rm <value>                 間違えて記録したフラグを消す
+#[derive(Parser, Debug)]
+struct FlagArgs {
+    #[command(subcommand)]
+    command: FlagCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum FlagCommand {
+    /// record a captured flag
+    Add {
+        value: String,
+        /// name, address or hostname of the host where the flag was found
+        #[arg(long)]
+        host: Option<String>,
+        #[arg(long, value_enum, default_value = "other")]
+        kind: FlagKind,
+        /// where the flag was found (e.g. /root/root.txt)
+        #[arg(long)]
+        source: Option<String>,
+        /// add the flag even if it does not match the format of the platform
+        #[arg(long)]
+        force: bool,
+    },
+    /// list captured flags
+    Ls,
+    /// set the platform and the number of flags to capture in this workspace
+    Set {
+        /// platform whose format flags are checked against. `none` to stop checking
+        #[arg(long)]
+        platform: Option<String>,
+        /// number of flags to capture. 0 to hide the count from the prompt
+        #[arg(long)]
+        expected: Option<u32>,
+    },
+    /// list flag formats of the platforms
+    Formats,
+    /// remove a recorded flag
+    Rm { value: String },
+}
+
+fn flag_(args_: String, app_conf: &mut crate::config::AppConfig) -> bool {
+    let mut args_iter = vec!["flag"];
+    args_iter.extend(&args_.split_whitespace().collect::<Vec<&str>>());
+    let args = match FlagArgs::try_parse_from(&args_iter) {
+        Ok(a) => a,
+        Err(e) => {
+            println!("{}", e);
+            return true;
+        }
+    };
+    match args.command {
+        FlagCommand::Add {
+            value,
+            host,
+            kind,
+            source,
+            force,
+        } => flag::add(app_conf, value, host, kind, source, force),
+        FlagCommand::Ls => flag::list(app_conf),
+        FlagCommand::Set { platform, expected } => flag::set(app_conf, platform, expected),
+        FlagCommand::Formats => flag::formats(app_conf),
+        FlagCommand::Rm { value } => flag::rm(app_conf, value),
+    }
+}
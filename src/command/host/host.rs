@@ -0,0 +1,175 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    config::AppConfig,
+    error::{self, Error, HostsError},
+    output::{blue, error_prefix, log_prefix},
+    store::hosts::{self, Host},
+};
+
+fn err_msg() {
+    println!("{} host command error", error_prefix());
+}
+
+pub fn add(
+    app_conf: &AppConfig,
+    name: String,
+    addresses: Vec<String>,
+    hostnames: Vec<String>,
+    os: Option<String>,
+    tags: Vec<String>,
+) -> bool {
+    let result = hosts::update(app_conf, |hosts| {
+        if hosts.contains_key(&name) {
+            return Err(HostsError::HostAlreadyExists(name.clone()));
+        }
+        for a in &addresses {
+            hosts::validate_address(a)?;
+        }
+        let mut host = Host {
+            os,
+            ..Default::default()
+        };
+        hosts::add_unique(&mut host.addresses, addresses);
+        hosts::add_unique(&mut host.hostnames, hostnames);
+        hosts::add_unique(&mut host.tags, tags);
+        hosts.insert(name.clone(), host);
+        Ok(())
+    });
+    match result {
+        Ok(_) => println!("{} added host {}", log_prefix(), name),
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::HostsError(e));
+            return true;
+        }
+    };
+    false
+}
+
+pub fn list(app_conf: &AppConfig, tag: Option<String>) -> bool {
+    let hosts = match hosts::load(app_conf) {
+        Ok(h) => h,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::HostsError(e));
+            return true;
+        }
+    };
+    let mut count = 0;
+    for (name, host) in &hosts {
+        if let Some(t) = &tag {
+            if !host.tags.contains(t) {
+                continue;
+            }
+        }
+        println!(
+            "{}\t{}\t{}\t{}\t{}\t{} services",
+            blue(name),
+            or_dash(host.addresses.join(",")),
+            or_dash(host.hostnames.join(",")),
+            host.os.as_deref().unwrap_or("-"),
+            or_dash(host.tags.join(",")),
+            host.services.len()
+        );
+        count += 1;
+    }
+    if count == 0 {
+        println!("{} no host is registered", log_prefix());
+    }
+    false
+}
+
+pub fn show(app_conf: &AppConfig, name: &str) -> bool {
+    let hosts = match hosts::load(app_conf) {
+        Ok(h) => h,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::HostsError(e));
+            return true;
+        }
+    };
+    let host = match hosts.get(name) {
+        Some(h) => h,
+        None => {
+            err_msg();
+            error::print_error(Error::HostsError(HostsError::HostNotFound(
+                name.to_string(),
+            )));
+            return true;
+        }
+    };
+    println!("{}", blue(name));
+    println!("\taddresses\t{}", host.addresses.join(", "));
+    println!("\thostnames\t{}", host.hostnames.join(", "));
+    println!("\tos\t\t{}", host.os.as_deref().unwrap_or("-"));
+    println!("\ttags\t\t{}", host.tags.join(", "));
+    println!("\tservices");
+    for s in &host.services {
+        println!("\t\t{}", super::service::describe(s));
+        print_scripts(&s.scripts, "\t\t\t");
+    }
+    if !host.scripts.is_empty() {
+        println!("\tscripts");
+        print_scripts(&host.scripts, "\t\t");
+    }
+    false
+}
+
+pub fn tag(app_conf: &AppConfig, name: &str, tags: Vec<String>, rm: bool) -> bool {
+    let result = hosts::update(app_conf, |hosts| match hosts.get_mut(name) {
+        Some(host) if rm => {
+            host.tags.retain(|t| !tags.contains(t));
+            Ok(host.tags.clone())
+        }
+        Some(host) => {
+            hosts::add_unique(&mut host.tags, tags);
+            Ok(host.tags.clone())
+        }
+        None => Err(HostsError::HostNotFound(name.to_string())),
+    });
+    match result {
+        Ok(tags) => println!("{} tags of {}: {}", log_prefix(), name, tags.join(", ")),
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::HostsError(e));
+            return true;
+        }
+    };
+    false
+}
+
+pub fn rm(app_conf: &AppConfig, name: &str) -> bool {
+    let result = hosts::update(app_conf, |hosts| match hosts.remove(name) {
+        Some(_) => Ok(()),
+        None => Err(HostsError::HostNotFound(name.to_string())),
+    });
+    match result {
+        Ok(_) => println!("{} removed host {}", log_prefix(), name),
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::HostsError(e));
+            return true;
+        }
+    };
+    false
+}
+
+// nmapのスクリプトの出力は複数行のことが多いので、インデントを揃えて表示する
+fn print_scripts(scripts: &BTreeMap<String, String>, indent: &str) {
+    for (id, output) in scripts {
+        println!("{}{}:", indent, id);
+        for line in output.lines() {
+            println!("{}  {}", indent, line.trim_end());
+        }
+    }
+}
+
+// 一覧で空の欄が詰まって見えないようにする
+fn or_dash(s: String) -> String {
+    if s.is_empty() {
+        "-".to_string()
+    } else {
+        s
+    }
+}
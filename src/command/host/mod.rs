@@ -0,0 +1,138 @@
+use clap::{Parser, Subcommand};
+
+#[allow(clippy::module_inception)]
+pub mod host;
+pub mod service;
+
+pub fn commands() -> Vec<crate::shell::command::Command> {
+    vec![
+        crate::shell::command::Command::new("host", Box::new(host_)),
+        crate::shell::command::Command::new("svc", Box::new(svc)),
+    ]
+}
+
+// host はワークスペースのホストを扱うコマンド
+//  host add <name> [--ip A] [--hostname H] [--os OS] [--tag T]    ホストを登録する
+//  host list [--tag T]                                             ホストの一覧を表示する
+//  host show <name>                                                ホストの詳細とサービスを表示する
+//  host tag <name> <tag>... [--rm]                                 タグを付ける・外す
+//  host rm <name>                                                  ホストを削除する
+#[derive(Parser, Debug)]
+struct HostArgs {
+    #[command(subcommand)]
+    command: HostCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum HostCommand {
+    /// register a host
+    Add {
+        name: String,
+        /// ip address of the host. can be given multiple times
+        #[arg(long = "ip")]
+        addresses: Vec<String>,
+        #[arg(long = "hostname")]
+        hostnames: Vec<String>,
+        #[arg(long)]
+        os: Option<String>,
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+    },
+    /// list hosts
+    List {
+        /// only list hosts with this tag
+        #[arg(long)]
+        tag: Option<String>,
+    },
+    /// show a host and its services
+    Show { name: String },
+    /// add tags to a host, or remove them with --rm
+    Tag {
+        name: String,
+        #[arg(required = true)]
+        tags: Vec<String>,
+        #[arg(long)]
+        rm: bool,
+    },
+    /// remove a host and its services
+    Rm { name: String },
+}
+
+fn host_(args_: String, app_conf: &mut crate::config::AppConfig) -> bool {
+    let mut args_iter = vec!["host"];
+    args_iter.extend(&args_.split_whitespace().collect::<Vec<&str>>());
+    let args = match HostArgs::try_parse_from(&args_iter) {
+        Ok(a) => a,
+        Err(e) => {
+            println!("{}", e);
+            return true;
+        }
+    };
+    match args.command {
+        HostCommand::Add {
+            name,
+            addresses,
+            hostnames,
+            os,
+            tags,
+        } => host::add(app_conf, name, addresses, hostnames, os, tags),
+        HostCommand::List { tag } => host::list(app_conf, tag),
+        HostCommand::Show { name } => host::show(app_conf, &name),
+        HostCommand::Tag { name, tags, rm } => host::tag(app_conf, &name, tags, rm),
+        HostCommand::Rm { name } => host::rm(app_conf, &name),
+    }
+}
+
+// svc はホストのサービスを扱うコマンド
+//  svc add <host> <port[/proto]> [--state S] [--product P] [--version V]   サービスを登録・更新する
+//  svc list [host] [--port N]                                              サービスの一覧を表示する
+#[derive(Parser, Debug)]
+struct SvcArgs {
+    #[command(subcommand)]
+    command: SvcCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum SvcCommand {
+    /// add a service to a host, or update it if the port is already registered
+    Add {
+        host: String,
+        /// port number like 80, 80/tcp or 53/udp
+        port: String,
+        #[arg(long, default_value = "open")]
+        state: String,
+        #[arg(long)]
+        product: Option<String>,
+        #[arg(long)]
+        version: Option<String>,
+    },
+    /// list services of all hosts or of a host
+    List {
+        host: Option<String>,
+        /// only list services on this port
+        #[arg(long)]
+        port: Option<u16>,
+    },
+}
+
+fn svc(args_: String, app_conf: &mut crate::config::AppConfig) -> bool {
+    let mut args_iter = vec!["svc"];
+    args_iter.extend(&args_.split_whitespace().collect::<Vec<&str>>());
+    let args = match SvcArgs::try_parse_from(&args_iter) {
+        Ok(a) => a,
+        Err(e) => {
+            println!("{}", e);
+            return true;
+        }
+    };
+    match args.command {
+        SvcCommand::Add {
+            host,
+            port,
+            state,
+            product,
+            version,
+        } => service::add(app_conf, &host, &port, state, product, version),
+        SvcCommand::List { host, port } => service::list(app_conf, host, port),
+    }
+}
@@ -0,0 +1,96 @@
+use crate::{
+    config::AppConfig,
+    error::{self, Error, HostsError},
+    output::{blue, error_prefix, log_prefix},
+    store::hosts::{self, Service},
+};
+
+fn err_msg() {
+    println!("{} svc command error", error_prefix());
+}
+
+pub fn add(
+    app_conf: &AppConfig,
+    host: &str,
+    port: &str,
+    state: String,
+    product: Option<String>,
+    version: Option<String>,
+) -> bool {
+    let result = hosts::update(app_conf, |hosts| {
+        let (port, protocol) = hosts::parse_port(port)?;
+        let service = Service {
+            port,
+            protocol,
+            state,
+            name: None,
+            product,
+            version,
+            scripts: Default::default(),
+            paths: vec![],
+        };
+        match hosts.get_mut(host) {
+            Some(h) => {
+                h.upsert_service(service.clone());
+                Ok(service)
+            }
+            None => Err(HostsError::HostNotFound(host.to_string())),
+        }
+    });
+    match result {
+        Ok(s) => println!("{} {}: {}", log_prefix(), host, describe(&s)),
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::HostsError(e));
+            return true;
+        }
+    };
+    false
+}
+
+pub fn list(app_conf: &AppConfig, host: Option<String>, port: Option<u16>) -> bool {
+    let hosts = match hosts::load(app_conf) {
+        Ok(h) => h,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::HostsError(e));
+            return true;
+        }
+    };
+    if let Some(h) = &host {
+        if !hosts.contains_key(h) {
+            err_msg();
+            error::print_error(Error::HostsError(HostsError::HostNotFound(h.clone())));
+            return true;
+        }
+    }
+    let mut count = 0;
+    for (name, h) in &hosts {
+        if host.as_ref().is_some_and(|target| target != name) {
+            continue;
+        }
+        for s in &h.services {
+            if port.is_some_and(|p| p != s.port) {
+                continue;
+            }
+            println!("{}\t{}", blue(name), describe(s));
+            count += 1;
+        }
+    }
+    if count == 0 {
+        println!("{} no service is registered", log_prefix());
+    }
+    false
+}
+
+// 445/tcp  open  microsoft-ds  Windows Server 2019  10.0 のような1行の説明
+pub fn describe(service: &Service) -> String {
+    let mut line = format!("{}/{}\t{}", service.port, service.protocol, service.state);
+    for s in [&service.name, &service.product, &service.version]
+        .into_iter()
+        .flatten()
+    {
+        line = format!("{}\t{}", line, s);
+    }
+    line
+}
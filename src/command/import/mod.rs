@@ -0,0 +1,63 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+
+pub mod nmap;
+pub mod web;
+
+pub fn commands() -> Vec<crate::shell::command::Command> {
+    vec![crate::shell::command::Command::new(
+        "import",
+        Box::new(import),
+    )]
+}
+
+// import はツールの出力をワークスペースに取り込むコマンド
+//  import nmap <scan.xml>                  nmap -oX の結果をホストとサービスの一覧に取り込む
+//  import ffuf <results.json>              ffuf -of json の結果をWebのパスとして取り込む
+//  import feroxbuster <results.json>       feroxbuster --json の結果をWebのパスとして取り込む
+//  import gobuster <output.txt> [--url U]  gobuster dir の出力をWebのパスとして取り込む
+#[derive(Parser, Debug)]
+struct ImportArgs {
+    #[command(subcommand)]
+    command: ImportCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum ImportCommand {
+    /// import hosts, services, script output and os guesses from nmap xml (-oX) into hosts.json. see them with `host list`
+    Nmap { file: PathBuf },
+    /// import discovered urls from ffuf json output (-of json)
+    Ffuf { file: PathBuf },
+    /// import discovered urls from feroxbuster json output (--json)
+    Feroxbuster { file: PathBuf },
+    /// import discovered paths from gobuster dir text output
+    Gobuster {
+        file: PathBuf,
+        /// base url the paths were found under. not needed if gobuster was run with -e
+        #[arg(long)]
+        url: Option<String>,
+    },
+}
+
+fn import(args_: String, app_conf: &mut crate::config::AppConfig) -> bool {
+    let mut args_iter = vec!["import"];
+    args_iter.extend(&args_.split_whitespace().collect::<Vec<&str>>());
+    let args = match ImportArgs::try_parse_from(&args_iter) {
+        Ok(a) => a,
+        Err(e) => {
+            println!("{}", e);
+            return true;
+        }
+    };
+    match args.command {
+        ImportCommand::Nmap { file } => nmap::import(app_conf, &file),
+        ImportCommand::Ffuf { file } => web::import(app_conf, web::Tool::Ffuf, &file, None),
+        ImportCommand::Feroxbuster { file } => {
+            web::import(app_conf, web::Tool::Feroxbuster, &file, None)
+        }
+        ImportCommand::Gobuster { file, url } => {
+            web::import(app_conf, web::Tool::Gobuster, &file, url)
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+use roxmltree::{Document, Node, ParsingOptions};
+
+use crate::{
+    command::host::service::describe,
+    config::AppConfig,
+    error::{self, Error, ImportError},
+    output::{blue, error_prefix, green, log_prefix},
+    store::hosts::{self, Protocol, Service},
+};
+
+// import nmap <scan.xml> では nmap -oX の結果をワークスペースのホスト一覧 (store::hosts) に取り込む
+// 取り込むもの: アドレス、ホスト名、ポートとサービス・バージョン、NSEスクリプトの出力、OSの推定
+// すでに同じアドレスのホストがあればそのホストを更新し、なければアドレスを名前にしてホストを作る
+// 前回までに取り込んだ内容と比べて、新しいホスト・サービスと変わったサービスを表示する
+// 取り込んだホストはvrや {{query}} から hosts.<名前> で参照できる。varsファイルのhostsとはマージされる (store::viewを参照)
+//
+// 依頼ではvars.jsonのhostsに書き込むことになっていたが、hosts.json (store::hosts) に書き込んでいる
+// host/svcコマンドが扱うホスト一覧はhosts.jsonなので、vars.jsonにも書くと同じホストが2か所にできて、どちらが正しいか分からなくなる
+// vrや {{hosts["10.10.10.5"].ip}} からはvars.jsonにある場合と同じように参照できるが、
+// vl・vs・vars export/mergeはvarsファイルだけを見るので、取り込んだホストはhost listやhost showで確認する
+
+// XMLから読み取った1つのホスト
+pub(crate) struct ScannedHost {
+    address: String,
+    hostnames: Vec<String>,
+    os: Option<String>,
+    services: Vec<Service>,
+    scripts: BTreeMap<String, String>,
+}
+
+pub fn import(app_conf: &AppConfig, file: &PathBuf) -> bool {
+    let err_msg = || {
+        println!("{} nmap import error", error_prefix());
+    };
+    let content = match fs::read_to_string(file) {
+        Ok(c) => c,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::ImportError(ImportError::ReadError(e)));
+            return true;
+        }
+    };
+    let scanned = match parse(&content) {
+        Ok(s) => s,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::ImportError(e));
+            return true;
+        }
+    };
+    let result = hosts::update(app_conf, |hosts| {
+        let mut report = vec![];
+        for s in scanned {
+            report.extend(apply(hosts, s));
+        }
+        Ok(report)
+    });
+    let report = match result {
+        Ok(r) => r,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::ImportError(ImportError::HostsError(e)));
+            return true;
+        }
+    };
+    for line in &report {
+        println!("{}", line);
+    }
+    if report.is_empty() {
+        println!("{} nothing changed since the last import", log_prefix());
+    }
+    false
+}
+
+// ホスト一覧に取り込み、変わったところを表示用の行にして返す
+pub(crate) fn apply(hosts: &mut hosts::Hosts, scanned: ScannedHost) -> Vec<String> {
+    let mut report = vec![];
+    let name = match hosts::find_by_address(hosts, &scanned.address) {
+        Some(n) => n,
+        None => {
+            report.push(green(&format!("+ host {}", scanned.address)));
+            scanned.address.clone()
+        }
+    };
+    let host = hosts.entry(name.clone()).or_default();
+    hosts::add_unique(&mut host.addresses, vec![scanned.address]);
+    hosts::add_unique(&mut host.hostnames, scanned.hostnames);
+    if let Some(os) = scanned.os {
+        if host.os.as_ref() != Some(&os) {
+            report.push(blue(&format!(
+                "~ {} os: {} -> {}",
+                name,
+                host.os.as_deref().unwrap_or("-"),
+                os
+            )));
+            host.os = Some(os);
+        }
+    }
+    host.scripts.extend(scanned.scripts);
+    for service in scanned.services {
+        let (port, protocol) = (service.port, service.protocol);
+        let old = host.upsert_service(service);
+        let new = match host
+            .services
+            .iter()
+            .find(|s| s.port == port && s.protocol == protocol)
+        {
+            Some(s) => describe(s),
+            None => continue,
+        };
+        match old {
+            None => report.push(green(&format!("+ {} {}", name, new))),
+            Some(old) if describe(&old) != new => {
+                report.push(blue(&format!("~ {} {} -> {}", name, describe(&old), new)))
+            }
+            Some(_) => (),
+        }
+    }
+    report
+}
+
+pub(crate) fn parse(content: &str) -> Result<Vec<ScannedHost>, ImportError> {
+    // nmapの出力には <!DOCTYPE nmaprun> が付いている
+    let options = ParsingOptions {
+        allow_dtd: true,
+        ..Default::default()
+    };
+    let doc = match Document::parse_with_options(content, options) {
+        Ok(d) => d,
+        Err(e) => return Err(ImportError::ParseXmlError(e)),
+    };
+    let root = doc.root_element();
+    if !root.has_tag_name("nmaprun") {
+        return Err(ImportError::NotNmapXml);
+    }
+    let mut scanned = vec![];
+    for host in root.children().filter(|n| n.has_tag_name("host")) {
+        // 落ちているホストは取り込まない
+        let up = child(host, "status").and_then(|s| s.attribute("state")) == Some("up");
+        let address = host
+            .children()
+            .filter(|n| n.has_tag_name("address"))
+            .find(|n| matches!(n.attribute("addrtype"), Some("ipv4") | Some("ipv6")))
+            .and_then(|n| n.attribute("addr"));
+        let address = match (up, address) {
+            (true, Some(a)) => a.to_string(),
+            _ => continue,
+        };
+        let hostnames = match child(host, "hostnames") {
+            Some(h) => h
+                .children()
+                .filter(|n| n.has_tag_name("hostname"))
+                .filter_map(|n| n.attribute("name"))
+                .map(|n| n.to_string())
+                .collect(),
+            None => vec![],
+        };
+        // 一番確度の高いOSの推定を使う。osmatchは確度の高い順に並んでいる
+        let os = child(host, "os")
+            .and_then(|o| child(o, "osmatch"))
+            .and_then(|m| m.attribute("name"))
+            .map(|n| n.to_string());
+        let services = match child(host, "ports") {
+            Some(p) => p
+                .children()
+                .filter(|n| n.has_tag_name("port"))
+                .filter_map(parse_port)
+                .collect(),
+            None => vec![],
+        };
+        let scripts = match child(host, "hostscript") {
+            Some(s) => scripts(s),
+            None => BTreeMap::new(),
+        };
+        scanned.push(ScannedHost {
+            address,
+            hostnames,
+            os,
+            services,
+            scripts,
+        });
+    }
+    Ok(scanned)
+}
+
+fn parse_port(port: Node) -> Option<Service> {
+    let protocol = match port.attribute("protocol")? {
+        "tcp" => Protocol::Tcp,
+        "udp" => Protocol::Udp,
+        _ => return None,
+    };
+    let number = port.attribute("portid")?.parse::<u16>().ok()?;
+    let state = child(port, "state")?.attribute("state")?.to_string();
+    let service = child(port, "service");
+    let attr = |name: &str| {
+        service
+            .and_then(|s| s.attribute(name))
+            .map(|v| v.to_string())
+    };
+    // extrainfoはバージョンの後ろに括弧で付ける (nmapの表示と同じ)
+    let version = match (attr("version"), attr("extrainfo")) {
+        (Some(v), Some(e)) => Some(format!("{} ({})", v, e)),
+        (Some(v), None) => Some(v),
+        (None, Some(e)) => Some(format!("({})", e)),
+        (None, None) => None,
+    };
+    Some(Service {
+        port: number,
+        protocol,
+        state,
+        name: attr("name"),
+        product: attr("product"),
+        version,
+        scripts: scripts(port),
+        paths: vec![],
+    })
+}
+
+fn scripts(node: Node) -> BTreeMap<String, String> {
+    node.children()
+        .filter(|n| n.has_tag_name("script"))
+        .filter_map(|n| {
+            Some((
+                n.attribute("id")?.to_string(),
+                n.attribute("output")?.trim().to_string(),
+            ))
+        })
+        .collect()
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.has_tag_name(name))
+}
@@ -0,0 +1,281 @@
+use std::{fs, path::PathBuf};
+
+use regex::Regex;
+use serde_json::Value;
+use url::Url;
+
+use crate::{
+    config::AppConfig,
+    error::{self, Error, ImportError},
+    output::{blue, error_prefix, green, log_prefix},
+    store::hosts::{self, Host, Protocol, Service, WebPath},
+};
+
+// import ffuf / feroxbuster / gobuster ではWebのファザーの結果をホスト一覧に取り込む
+// 見つけたURLは、URLのホスト (アドレスかホスト名) とポートに対応するサービスの paths に記録する
+// ホストやサービスがまだなければ作る。同じURLは何度取り込んでも1つにまとめる
+
+pub enum Tool {
+    Ffuf,
+    Feroxbuster,
+    Gobuster,
+}
+
+// 結果のファイルから読み取った1つのURL
+pub(crate) struct Hit {
+    pub(crate) url: Url,
+    pub(crate) status: u16,
+    pub(crate) size: Option<u64>,
+    pub(crate) words: Option<u64>,
+}
+
+pub fn import(app_conf: &AppConfig, tool: Tool, file: &PathBuf, base: Option<String>) -> bool {
+    let err_msg = || {
+        println!("{} web import error", error_prefix());
+    };
+    let content = match fs::read_to_string(file) {
+        Ok(c) => c,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::ImportError(ImportError::ReadError(e)));
+            return true;
+        }
+    };
+    let parsed = match tool {
+        Tool::Ffuf => parse_ffuf(&content),
+        Tool::Feroxbuster => parse_feroxbuster(&content),
+        Tool::Gobuster => parse_gobuster(&content, base),
+    };
+    let hits = match parsed {
+        Ok(h) => h,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::ImportError(e));
+            return true;
+        }
+    };
+    let count = hits.len();
+    let result = hosts::update(app_conf, |hosts| {
+        let mut report = vec![];
+        for h in hits {
+            report.extend(apply(hosts, h));
+        }
+        Ok(report)
+    });
+    let report = match result {
+        Ok(r) => r,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::ImportError(ImportError::HostsError(e)));
+            return true;
+        }
+    };
+    for line in &report {
+        println!("{}", line);
+    }
+    if report.is_empty() {
+        println!("{} nothing changed since the last import", log_prefix());
+    }
+    println!("{} read {} urls", log_prefix(), count);
+    false
+}
+
+// ホスト一覧に取り込み、変わったところを表示用の行にして返す
+pub(crate) fn apply(hosts: &mut hosts::Hosts, hit: Hit) -> Vec<String> {
+    let mut report = vec![];
+    let host_str = match hit.url.host_str() {
+        Some(h) => h.trim_start_matches('[').trim_end_matches(']').to_string(),
+        None => return report,
+    };
+    let is_address = hosts::validate_address(&host_str).is_ok();
+    // URLのホストはホストの名前でもよい。すでにあるホストを空のホストで上書きしないように、見つからなければ作るだけにする
+    let name = match hosts::resolve(hosts, &host_str) {
+        Some(n) => n,
+        None => {
+            report.push(green(&format!("+ host {}", host_str)));
+            host_str.clone()
+        }
+    };
+    let host = hosts.entry(name.clone()).or_insert_with(|| {
+        let mut host = Host::default();
+        if is_address {
+            host.addresses.push(host_str.clone());
+        } else {
+            host.hostnames.push(host_str.clone());
+        }
+        host
+    });
+    let port = match hit.url.port_or_known_default() {
+        Some(p) => p,
+        None => return report,
+    };
+    let service = match host
+        .services
+        .iter()
+        .position(|s| s.port == port && s.protocol == Protocol::Tcp)
+    {
+        Some(i) => &mut host.services[i],
+        None => {
+            let service = Service {
+                port,
+                protocol: Protocol::Tcp,
+                state: "open".to_string(),
+                name: Some(hit.url.scheme().to_string()),
+                product: None,
+                version: None,
+                scripts: Default::default(),
+                paths: vec![],
+            };
+            report.push(green(&format!(
+                "+ {} {}",
+                name,
+                crate::command::host::service::describe(&service)
+            )));
+            host.upsert_service(service);
+            // upsert_serviceはポート順に並べ替えるので、もう一度探す
+            match host
+                .services
+                .iter_mut()
+                .find(|s| s.port == port && s.protocol == Protocol::Tcp)
+            {
+                Some(s) => s,
+                None => return report,
+            }
+        }
+    };
+    let path = WebPath {
+        url: hit.url.to_string(),
+        status: hit.status,
+        size: hit.size,
+        words: hit.words,
+    };
+    let new = describe(&path);
+    match service.upsert_path(path.clone()) {
+        None => report.push(green(&format!("+ {} {}", name, new))),
+        Some(old) if old != path => {
+            report.push(blue(&format!("~ {} {} -> {}", name, describe(&old), new)))
+        }
+        Some(_) => (),
+    }
+    report
+}
+
+// http://10.0.0.10/admin  301  178 bytes  12 words のような1行の説明
+pub fn describe(path: &WebPath) -> String {
+    let mut line = format!("{}\t{}", path.url, path.status);
+    if let Some(s) = path.size {
+        line = format!("{}\t{} bytes", line, s);
+    }
+    if let Some(w) = path.words {
+        line = format!("{}\t{} words", line, w);
+    }
+    line
+}
+
+// ffuf -of json の出力
+//  {"results": [{"url": "http://...", "status": 200, "length": 1234, "words": 56, ...}], ...}
+pub(crate) fn parse_ffuf(content: &str) -> Result<Vec<Hit>, ImportError> {
+    let value: Value = match serde_json::from_str(content) {
+        Ok(v) => v,
+        Err(e) => return Err(ImportError::ParseJsonError(e)),
+    };
+    let results = match value.get("results").and_then(|r| r.as_array()) {
+        Some(r) => r,
+        None => return Err(ImportError::NotFfufJson),
+    };
+    let mut hits = vec![];
+    for r in results {
+        if let Some(h) = to_hit(r, "length", "words")? {
+            hits.push(h);
+        }
+    }
+    Ok(hits)
+}
+
+// feroxbuster --json の出力。1行に1つのJSONで、type が response の行だけを読む
+//  {"type": "response", "url": "http://...", "status": 200, "content_length": 1234, "word_count": 56, ...}
+pub(crate) fn parse_feroxbuster(content: &str) -> Result<Vec<Hit>, ImportError> {
+    let mut hits = vec![];
+    for (i, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let value: Value = match serde_json::from_str(line) {
+            Ok(v) => v,
+            Err(e) => return Err(ImportError::ParseJsonLineError(i + 1, e)),
+        };
+        if value.get("type").and_then(|t| t.as_str()) != Some("response") {
+            continue;
+        }
+        if let Some(h) = to_hit(&value, "content_length", "word_count")? {
+            hits.push(h);
+        }
+    }
+    Ok(hits)
+}
+
+fn to_hit(value: &Value, size_key: &str, words_key: &str) -> Result<Option<Hit>, ImportError> {
+    let (url, status) = match (
+        value.get("url").and_then(|u| u.as_str()),
+        value.get("status").and_then(|s| s.as_u64()),
+    ) {
+        (Some(u), Some(s)) => (u, s),
+        _ => return Ok(None),
+    };
+    let url = match Url::parse(url) {
+        Ok(u) => u,
+        Err(e) => return Err(ImportError::InvalidUrl(url.to_string(), e)),
+    };
+    Ok(Some(Hit {
+        url,
+        status: status as u16,
+        size: value.get(size_key).and_then(|s| s.as_u64()),
+        words: value.get(words_key).and_then(|w| w.as_u64()),
+    }))
+}
+
+// gobuster dir のテキストの出力
+//  /admin                (Status: 301) [Size: 178] [--> http://10.0.0.10/admin/]
+// -e を付けた場合はパスではなくURLが出力される。パスの場合は --url でベースのURLを指定する
+pub(crate) fn parse_gobuster(content: &str, base: Option<String>) -> Result<Vec<Hit>, ImportError> {
+    let base = match base {
+        Some(b) => match Url::parse(&b) {
+            // パスはベースのURLの下に付けるので、最後を / にしておく
+            Ok(mut u) if !u.path().ends_with('/') => {
+                u.set_path(&format!("{}/", u.path()));
+                Some(u)
+            }
+            Ok(u) => Some(u),
+            Err(e) => return Err(ImportError::InvalidUrl(b, e)),
+        },
+        None => None,
+    };
+    let re = Regex::new(r"^(\S+)\s+\(Status:\s*(\d+)\)(?:\s*\[Size:\s*(\d+)\])?").unwrap();
+    let mut hits = vec![];
+    for line in content.lines() {
+        let caps = match re.captures(line.trim()) {
+            Some(c) => c,
+            None => continue,
+        };
+        let target = &caps[1];
+        let url = if target.starts_with('/') {
+            match &base {
+                Some(b) => b.join(target.trim_start_matches('/')),
+                None => return Err(ImportError::BaseUrlRequired),
+            }
+        } else {
+            Url::parse(target)
+        };
+        let url = match url {
+            Ok(u) => u,
+            Err(e) => return Err(ImportError::InvalidUrl(target.to_string(), e)),
+        };
+        hits.push(Hit {
+            url,
+            status: caps[2].parse().unwrap_or_default(),
+            size: caps.get(3).and_then(|s| s.as_str().parse().ok()),
+            words: None,
+        });
+    }
+    Ok(hits)
+}
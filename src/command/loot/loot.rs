@@ -0,0 +1,203 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use chrono::Local;
+
+use crate::{
+    config::AppConfig,
+    error::{self, Error, LootError},
+    output::{blue, error_prefix, green, log_prefix, red},
+    store::{
+        hosts, lock,
+        loot::{self, Loot},
+    },
+};
+
+fn err_msg() {
+    println!("{} loot command error", error_prefix());
+}
+
+// ホストの名前にそろえる。登録されていないホストから持ってくることもあるので、見つからなければそのまま使う
+fn resolve_host(app_conf: &AppConfig, host: Option<String>) -> Result<Option<String>, LootError> {
+    match host {
+        Some(h) => match hosts::load(app_conf) {
+            Ok(hosts) => Ok(Some(hosts::resolve(&hosts, &h).unwrap_or(h))),
+            Err(e) => Err(LootError::HostsError(e)),
+        },
+        None => Ok(None),
+    }
+}
+
+fn find(index: Vec<Loot>, id: u32) -> Result<Loot, LootError> {
+    match index.into_iter().find(|l| l.id == id) {
+        Some(l) => Ok(l),
+        None => Err(LootError::LootNotFound(id)),
+    }
+}
+
+// lootのホストの一覧 (重複なし)
+fn hosts_of(l: &Loot) -> Vec<String> {
+    let mut hosts = vec![];
+    for h in l.sources.iter().filter_map(|s| s.host.clone()) {
+        if !hosts.contains(&h) {
+            hosts.push(h);
+        }
+    }
+    hosts
+}
+
+pub fn add(app_conf: &AppConfig, file: &Path, host: Option<String>, desc: Vec<String>) -> bool {
+    let loot_path = app_conf.dyn_conf.to_workspace().loot_path;
+    let desc = if desc.is_empty() {
+        None
+    } else {
+        Some(desc.join(" "))
+    };
+    // ファイルのコピーはloot.jsonの排他ロックを取った状態で行う
+    let result = resolve_host(app_conf, host).and_then(|host| {
+        loot::update(app_conf, |index| {
+            loot::store(&loot_path, index, file, host, desc)
+        })
+    });
+    match result {
+        Ok((l, true)) => println!(
+            "{} stored {} as loot {} ({} bytes, {}, sha256:{})",
+            log_prefix(),
+            file.display(),
+            l.id,
+            l.size,
+            l.mime,
+            l.sha256
+        ),
+        Ok((l, false)) => println!(
+            "{} {} has the same content as loot {} ({}). recorded the source without copying",
+            log_prefix(),
+            file.display(),
+            l.id,
+            l.name
+        ),
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::LootError(e));
+            return true;
+        }
+    };
+    false
+}
+
+pub fn list(app_conf: &AppConfig, host: Option<String>) -> bool {
+    let result = resolve_host(app_conf, host)
+        .and_then(|host| loot::load(app_conf).map(|index| (index, host)));
+    let (index, host) = match result {
+        Ok(r) => r,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::LootError(e));
+            return true;
+        }
+    };
+    let mut count = 0;
+    for l in &index {
+        let hosts = hosts_of(l);
+        if host.as_ref().is_some_and(|h| !hosts.contains(h)) {
+            continue;
+        }
+        count += 1;
+        println!(
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            blue(&l.id.to_string()),
+            l.name,
+            l.size,
+            l.mime,
+            if hosts.is_empty() {
+                "-".to_string()
+            } else {
+                hosts.join(",")
+            },
+            l.sha256.get(..12).unwrap_or(&l.sha256)
+        );
+    }
+    if count == 0 {
+        println!("{} no loot is stored", log_prefix());
+    }
+    false
+}
+
+pub fn show(app_conf: &AppConfig, id: u32) -> bool {
+    let loot_path = app_conf.dyn_conf.to_workspace().loot_path;
+    let l = match loot::load(app_conf).and_then(|index| find(index, id)) {
+        Ok(l) => l,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::LootError(e));
+            return true;
+        }
+    };
+    // 記録したハッシュと今のコピーを比べて、変わっていないことを確かめる
+    let integrity = match fs::read(loot_path.join(&l.file)) {
+        Ok(c) if loot::sha256(&c) == l.sha256 => green("ok"),
+        Ok(_) => red("modified"),
+        Err(_) => red("missing"),
+    };
+    println!("{} {}", blue(&l.id.to_string()), l.name);
+    println!("\tfile\t\tloot/{}", l.file);
+    println!("\tsha256\t\t{} ({})", l.sha256, integrity);
+    println!("\tsize\t\t{}", l.size);
+    println!("\tmime\t\t{}", l.mime);
+    println!(
+        "\tadded\t\t{}",
+        l.added_at.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S")
+    );
+    println!("\tsources");
+    for s in &l.sources {
+        println!(
+            "\t\t{}\t{}\t{}\t{}",
+            s.added_at.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S"),
+            s.host.as_deref().unwrap_or("-"),
+            s.path,
+            s.desc.as_deref().unwrap_or("")
+        );
+    }
+    false
+}
+
+pub fn extract(app_conf: &AppConfig, id: u32, out: Option<PathBuf>, force: bool) -> bool {
+    let loot_path = app_conf.dyn_conf.to_workspace().loot_path;
+    let result = loot::load(app_conf)
+        .and_then(|index| find(index, id))
+        .and_then(|l| {
+            let stored = loot_path.join(&l.file);
+            let content = match fs::read(&stored) {
+                Ok(c) => c,
+                Err(e) => return Err(LootError::ReadError(stored, e)),
+            };
+            // 取り出したファイルを証拠として使えるように、記録したときと同じ内容であることを確かめる
+            if loot::sha256(&content) != l.sha256 {
+                return Err(LootError::HashMismatch(stored));
+            }
+            let out = match out {
+                Some(o) if o.is_dir() => o.join(&l.name),
+                Some(o) => o,
+                None => env::current_dir().unwrap_or_default().join(&l.name),
+            };
+            if out.exists() && !force {
+                return Err(LootError::OutputExists(out));
+            }
+            // 取り出したファイルも loot/ のコピーと同じく他のユーザーから読めないようにする
+            match lock::write_atomic_private(&out, &content) {
+                Ok(_) => Ok(out),
+                Err(e) => Err(LootError::WriteError(out, e)),
+            }
+        });
+    match result {
+        Ok(out) => println!("{} wrote {}", log_prefix(), out.display()),
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::LootError(e));
+            return true;
+        }
+    };
+    false
+}
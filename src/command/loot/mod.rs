@@ -0,0 +1,70 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+
+#[allow(clippy::module_inception)]
+pub mod loot;
+
+pub fn commands() -> Vec<crate::shell::command::Command> {
+    vec![crate::shell::command::Command::new("loot", Box::new(loot_))]
+}
+
+// loot はホストから持ち出したファイルを扱うコマンド
+//  loot add <file> [--host H] [--desc D...]    ファイルを .prail/loot/ にコピーして、ハッシュと入手元を記録する
+//  loot ls [--host H]                          持ち出したファイルの一覧
+//  loot show <id>                              入手元の一覧と、コピーが変わっていないかを表示する
+//  loot extract <id> [--out PATH] [--force]    コピーを元の名前で取り出す
+#[derive(Parser, Debug)]
+struct LootArgs {
+    #[command(subcommand)]
+    command: LootCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum LootCommand {
+    /// copy an exfiltrated file into the workspace and record where it came from
+    Add {
+        file: PathBuf,
+        /// name, address or hostname of the host the file came from
+        #[arg(long)]
+        host: Option<String>,
+        /// where the file was on the host, how it was taken, etc.
+        #[arg(long, num_args = 1..)]
+        desc: Vec<String>,
+    },
+    /// list stored files
+    Ls {
+        /// only list files taken from this host
+        #[arg(long)]
+        host: Option<String>,
+    },
+    /// show a stored file with its sources and check its hash
+    Show { id: u32 },
+    /// copy a stored file out of the workspace
+    Extract {
+        id: u32,
+        /// file or directory to write to. defaults to the original name in the current directory
+        #[arg(long)]
+        out: Option<PathBuf>,
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+fn loot_(args_: String, app_conf: &mut crate::config::AppConfig) -> bool {
+    let mut args_iter = vec!["loot"];
+    args_iter.extend(&args_.split_whitespace().collect::<Vec<&str>>());
+    let args = match LootArgs::try_parse_from(&args_iter) {
+        Ok(a) => a,
+        Err(e) => {
+            println!("{}", e);
+            return true;
+        }
+    };
+    match args.command {
+        LootCommand::Add { file, host, desc } => loot::add(app_conf, &file, host, desc),
+        LootCommand::Ls { host } => loot::list(app_conf, host),
+        LootCommand::Show { id } => loot::show(app_conf, id),
+        LootCommand::Extract { id, out, force } => loot::extract(app_conf, id, out, force),
+    }
+}
@@ -1,3 +1,18 @@
+#[allow(clippy::module_inception)]
 pub mod command;
-pub mod vars;
+pub mod cred;
+pub mod finding;
+pub mod flag;
+pub mod host;
+pub mod import;
+pub mod loot;
+pub mod note;
+pub mod report;
 pub mod show;
+pub mod timeline;
+pub mod vars;
+pub mod web;
+pub mod ws;
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,80 @@
+use clap::{Parser, Subcommand};
+
+#[allow(clippy::module_inception)]
+pub mod note;
+
+pub fn commands() -> Vec<crate::shell::command::Command> {
+    vec![crate::shell::command::Command::new("note", Box::new(note_))]
+}
+
+// note はワークスペースのMarkdownのメモを扱うコマンド
+//  note ws                                         ワークスペース全体のメモを$EDITORで開く
+//  note host <host>                                ホストのメモを開く。なければ作る
+//  note svc <host> <port[/proto]>                  サービスのメモを開く。なければ作る
+//  note append [--host H] [--port P] <text>...     時刻を付けた1行をメモに足す。省略した場合はワークスペースのメモ
+//  note ls                                         メモの一覧を表示する
+//  note grep <pattern>... [--regex]                メモから行を探す
+#[derive(Parser, Debug)]
+struct NoteArgs {
+    #[command(subcommand)]
+    command: NoteCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum NoteCommand {
+    /// open the note of the workspace
+    Ws,
+    /// open the note of a host
+    Host {
+        /// name, address or hostname of the host
+        host: String,
+    },
+    /// open the note of a service
+    Svc {
+        host: String,
+        /// port like 445 or 161/udp
+        port: String,
+    },
+    /// append a timestamped line to a note
+    Append {
+        /// append to the note of this host instead of the workspace
+        #[arg(long)]
+        host: Option<String>,
+        /// append to the note of this service of the host
+        #[arg(long, requires = "host")]
+        port: Option<String>,
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        text: Vec<String>,
+    },
+    /// list notes
+    Ls,
+    /// search lines in notes
+    Grep {
+        #[arg(required = true)]
+        pattern: Vec<String>,
+        #[arg(long)]
+        regex: bool,
+    },
+}
+
+fn note_(args_: String, app_conf: &mut crate::config::AppConfig) -> bool {
+    let mut args_iter = vec!["note"];
+    args_iter.extend(&args_.split_whitespace().collect::<Vec<&str>>());
+    let args = match NoteArgs::try_parse_from(&args_iter) {
+        Ok(a) => a,
+        Err(e) => {
+            println!("{}", e);
+            return true;
+        }
+    };
+    match args.command {
+        NoteCommand::Ws => note::open(app_conf, None, None),
+        NoteCommand::Host { host } => note::open(app_conf, Some(host), None),
+        NoteCommand::Svc { host, port } => note::open(app_conf, Some(host), Some(port)),
+        NoteCommand::Append { host, port, text } => {
+            note::append(app_conf, host, port, &text.join(" "))
+        }
+        NoteCommand::Ls => note::ls(app_conf),
+        NoteCommand::Grep { pattern, regex } => note::grep(app_conf, &pattern.join(" "), regex),
+    }
+}
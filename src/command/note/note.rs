@@ -0,0 +1,187 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use regex::Regex;
+
+use crate::{
+    command::vars::{edit::open_editor, search::highlight},
+    config::AppConfig,
+    error::{self, Error, HostsError, NotesError},
+    output::{blue, error_prefix, log_prefix},
+    store::{
+        hosts,
+        notes::{self, NoteTarget},
+    },
+};
+
+fn err_msg() {
+    println!("{} note command error", error_prefix());
+}
+
+fn notes_path(app_conf: &AppConfig) -> Result<PathBuf, NotesError> {
+    if app_conf.dyn_conf.current_workspace.is_empty() {
+        return Err(NotesError::NoCurrentWorkspace);
+    }
+    Ok(app_conf.dyn_conf.to_workspace().notes_path)
+}
+
+// ホストは名前、アドレス、ホスト名のどれで指定してもホストの名前のメモにする
+fn target(
+    app_conf: &AppConfig,
+    host: Option<String>,
+    port: Option<String>,
+) -> Result<NoteTarget, NotesError> {
+    let host = match host {
+        Some(h) => h,
+        None => return Ok(NoteTarget::Workspace),
+    };
+    let hosts = match hosts::load(app_conf) {
+        Ok(h) => h,
+        Err(e) => return Err(NotesError::HostsError(e)),
+    };
+    let name = match hosts::resolve(&hosts, &host) {
+        Some(n) => n,
+        None => return Err(NotesError::HostsError(HostsError::HostNotFound(host))),
+    };
+    match port.map(|p| hosts::parse_port(&p)) {
+        Some(Ok((port, protocol))) => Ok(NoteTarget::Service(name, port, protocol)),
+        Some(Err(e)) => Err(NotesError::HostsError(e)),
+        None => Ok(NoteTarget::Host(name)),
+    }
+}
+
+pub fn open(app_conf: &AppConfig, host: Option<String>, port: Option<String>) -> bool {
+    let result = notes_path(app_conf).and_then(|notes_path| {
+        let target = target(app_conf, host, port)?;
+        if let Err(e) = notes::ensure(&notes_path, &target) {
+            return Err(NotesError::WriteError(e));
+        }
+        let path = target.path(&notes_path);
+        match open_editor(&path) {
+            Ok(_) => Ok(path),
+            Err(e) => Err(NotesError::EditorError(e)),
+        }
+    });
+    match result {
+        Ok(path) => println!("{} saved {}", log_prefix(), path.display()),
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::NotesError(e));
+            return true;
+        }
+    };
+    false
+}
+
+pub fn append(
+    app_conf: &AppConfig,
+    host: Option<String>,
+    port: Option<String>,
+    text: &str,
+) -> bool {
+    let result = notes_path(app_conf).and_then(|notes_path| {
+        let target = target(app_conf, host, port)?;
+        match notes::append(&notes_path, &target, text) {
+            Ok(p) => Ok(p),
+            Err(e) => Err(NotesError::WriteError(e)),
+        }
+    });
+    match result {
+        Ok(path) => println!("{} appended to {}", log_prefix(), path.display()),
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::NotesError(e));
+            return true;
+        }
+    };
+    false
+}
+
+pub fn ls(app_conf: &AppConfig) -> bool {
+    let result = notes_path(app_conf).and_then(|notes_path| match notes::list(&notes_path) {
+        Ok(n) => Ok((notes_path, n)),
+        Err(e) => Err(NotesError::ReadError(e)),
+    });
+    let (notes_path, notes) = match result {
+        Ok(r) => r,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::NotesError(e));
+            return true;
+        }
+    };
+    for path in &notes {
+        let lines = fs::read_to_string(path)
+            .map(|c| c.lines().count())
+            .unwrap_or(0);
+        println!("{}\t{} lines", blue(&relative(&notes_path, path)), lines);
+    }
+    if notes.is_empty() {
+        println!("{} no note is written", log_prefix());
+    }
+    false
+}
+
+// vsと同じく、パターンは既定では文字列としてそのまま探し、--regexを付けると正規表現として扱う
+pub fn grep(app_conf: &AppConfig, pattern: &str, regex: bool) -> bool {
+    let pattern = if regex {
+        pattern.to_string()
+    } else {
+        regex::escape(pattern)
+    };
+    let re = match Regex::new(&pattern) {
+        Ok(r) => r,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::NotesError(NotesError::InvalidPattern(e)));
+            return true;
+        }
+    };
+    let result = notes_path(app_conf).and_then(|notes_path| match notes::list(&notes_path) {
+        Ok(n) => Ok((notes_path, n)),
+        Err(e) => Err(NotesError::ReadError(e)),
+    });
+    let (notes_path, notes) = match result {
+        Ok(r) => r,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::NotesError(e));
+            return true;
+        }
+    };
+    let mut count = 0;
+    for path in &notes {
+        let content = match fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(e) => {
+                err_msg();
+                error::print_error(Error::NotesError(NotesError::ReadError(e)));
+                return true;
+            }
+        };
+        for (i, line) in content.lines().enumerate() {
+            if re.is_match(line) {
+                println!(
+                    "{}:{}: {}",
+                    blue(&relative(&notes_path, path)),
+                    i + 1,
+                    highlight(&re, line)
+                );
+                count += 1;
+            }
+        }
+    }
+    if count == 0 {
+        println!("{} no match", log_prefix());
+    }
+    false
+}
+
+fn relative(notes_path: &Path, path: &Path) -> String {
+    path.strip_prefix(notes_path)
+        .unwrap_or(path)
+        .display()
+        .to_string()
+}
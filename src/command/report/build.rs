@@ -0,0 +1,267 @@
+use std::{fs, path::PathBuf};
+
+use chrono::Local;
+use pulldown_cmark::{html, Event, Parser};
+use serde_json::{json, Value};
+use tera::{Context, Tera};
+
+use super::{template, ReportFormat};
+use crate::{
+    config::AppConfig,
+    error::{self, Error, ReportError},
+    output::{error_prefix, log_prefix},
+    store::{self, creds, findings, flags, hosts, notes, secret},
+};
+
+// 報告書では認証情報の値の代わりにこれを表示する
+const MASK: &str = "********";
+
+// report build ではワークスペースのデータをテンプレートに渡して報告書を作る
+// テンプレートに渡す値
+//  workspace       name, path
+//  generated_at    作った時刻 (ローカル時刻)
+//  hosts           ホストの一覧。nameとipを足したもの (store::hostsを参照)
+//  creds           認証情報の一覧。値は隠し、accountと使えたホストの一覧 (worked_on) を足したもの
+//  findings        指摘事項を重い順に並べたもの。descriptionをHTMLにしたもの (description_html) を足す
+//  flags           flag addで記録したフラグ (name, value, host, source, captured_at) と、varsのflagsの値を並べたもの
+//  notes           .prail/notesの下のMarkdownファイル。path, content (見出しを2段下げたもの), html
+//  vars, vars_json varsファイルの値。秘密の値と、credsや秘密情報らしいキー (pass, hash など) の値は <secret> にする
+// description_htmlとnotesのhtmlはMarkdownに書かれたHTMLをエスケープしてあるので、テンプレートで | safe を付けてよい
+pub fn build(app_conf: &mut AppConfig, format: ReportFormat, out: Option<PathBuf>) -> bool {
+    let err_msg = || {
+        println!("{} report build error", error_prefix());
+    };
+    let result = context(app_conf).and_then(|context| {
+        let template = template::load(app_conf, format)?;
+        render(format, &template, &context)
+    });
+    let report = match result {
+        Ok(r) => r,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::ReportError(e));
+            return true;
+        }
+    };
+    let root = PathBuf::from(&app_conf.dyn_conf.current_workspace);
+    let out = out.unwrap_or_else(|| match format {
+        ReportFormat::Md => root.join("report.md"),
+        ReportFormat::Html => root.join("report.html"),
+    });
+    match fs::write(&out, report) {
+        Ok(_) => println!("{} wrote {}", log_prefix(), out.display()),
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::ReportError(ReportError::WriteError(out, e)));
+            return true;
+        }
+    };
+    false
+}
+
+fn render(format: ReportFormat, template: &str, context: &Value) -> Result<String, ReportError> {
+    // Teraはテンプレートの名前の拡張子で自動エスケープを決めるので、htmlの場合だけ .html にする
+    let name = match format {
+        ReportFormat::Md => "report.md",
+        ReportFormat::Html => "report.html",
+    };
+    let mut tera = Tera::default();
+    match tera.add_raw_template(name, template) {
+        Ok(_) => (),
+        Err(e) => return Err(ReportError::TemplateError(e)),
+    };
+    let context = match Context::from_value(context.clone()) {
+        Ok(c) => c,
+        Err(e) => return Err(ReportError::TemplateError(e)),
+    };
+    match tera.render(name, &context) {
+        Ok(r) => Ok(r),
+        Err(e) => Err(ReportError::TemplateError(e)),
+    }
+}
+
+fn context(app_conf: &mut AppConfig) -> Result<Value, ReportError> {
+    if app_conf.dyn_conf.current_workspace.is_empty() {
+        return Err(ReportError::NoCurrentWorkspace);
+    }
+    let root = PathBuf::from(&app_conf.dyn_conf.current_workspace);
+    let workspace = app_conf.dyn_conf.to_workspace();
+
+    let mut vars = match store::vars::load(app_conf) {
+        Ok(s) => s.json.data,
+        Err(e) => return Err(ReportError::VarsStoreError(e)),
+    };
+    secret::mask(&mut vars);
+    // 報告書は外に渡すものなので、varsに書いた認証情報も出さない
+    if let Some(c) = vars.get_mut("creds") {
+        secret::replace_all(c, secret::MASKED_VALUE);
+    }
+    secret::strip_secret_keys(&mut vars, secret::MASKED_VALUE);
+
+    let hosts = match hosts::load(app_conf) {
+        Ok(h) => h,
+        Err(e) => return Err(ReportError::HostsError(e)),
+    };
+    let hosts = match hosts::to_value(&hosts) {
+        Value::Object(map) => map
+            .into_iter()
+            .map(|(name, mut h)| {
+                h["name"] = Value::String(name);
+                h
+            })
+            .collect(),
+        _ => vec![],
+    };
+
+    let creds = match creds::load(app_conf) {
+        Ok(c) => c,
+        Err(e) => return Err(ReportError::CredsError(e)),
+    };
+    let creds = creds
+        .iter()
+        .map(|c| {
+            let mut worked_on = c
+                .tries
+                .iter()
+                .filter(|t| t.result == creds::TryResult::Success)
+                .map(|t| match &t.service {
+                    Some(s) => format!("{} {}", t.host, s),
+                    None => t.host.clone(),
+                })
+                .collect::<Vec<String>>();
+            worked_on.dedup();
+            let mut v = json!(c);
+            v["secret"] = Value::String(MASK.to_string());
+            v["account"] = Value::String(c.account());
+            v["worked_on"] = json!(worked_on);
+            v
+        })
+        .collect::<Vec<Value>>();
+
+    let mut findings = match findings::load(app_conf) {
+        Ok(f) => f,
+        Err(e) => return Err(ReportError::FindingsError(e)),
+    };
+    findings.sort_by_key(|f| (f.severity, f.id));
+    let findings = findings
+        .iter()
+        .map(|f| {
+            let html = markdown_to_html(&demote_headings(&f.description, 3));
+            let mut v = json!(f);
+            v["description"] = Value::String(demote_headings(&f.description, 3));
+            v["description_html"] = Value::String(html);
+            // f32のままだと 5.300000190734863 のように出るので、文字列にしておく
+            if let Some(c) = f.cvss {
+                v["cvss"] = Value::String(format!("{:.1}", c));
+            }
+            v
+        })
+        .collect::<Vec<Value>>();
+
+    let board = match flags::load(app_conf) {
+        Ok(b) => b,
+        Err(e) => return Err(ReportError::FlagsError(e)),
+    };
+    let mut flags = board
+        .flags
+        .iter()
+        .map(|f| {
+            let mut v = json!(f);
+            v["name"] = Value::String(f.kind.to_string());
+            v["captured_at"] = Value::String(
+                f.captured_at
+                    .with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string(),
+            );
+            v
+        })
+        .collect::<Vec<Value>>();
+    // flag addができる前はvarsのflagsに書いていたので、それも報告書に入れる
+    match vars.get("flags") {
+        Some(Value::Object(map)) => flags.extend(
+            map.iter()
+                .map(|(k, v)| json!({"name": k, "value": text(v)})),
+        ),
+        Some(Value::Array(arr)) => flags.extend(
+            arr.iter()
+                .enumerate()
+                .map(|(i, v)| json!({"name": i.to_string(), "value": text(v)})),
+        ),
+        _ => (),
+    };
+
+    let mut notes = vec![];
+    // notesの下だけを見る。.prailのほかのファイル (履歴やlootなど) は報告書に入れない
+    let paths = match notes::list(&workspace.notes_path) {
+        Ok(p) => p,
+        Err(e) => return Err(ReportError::ReadNoteError(workspace.notes_path, e)),
+    };
+    for path in paths {
+        let content = match fs::read_to_string(&path) {
+            Ok(c) => demote_headings(&c, 2),
+            Err(e) => return Err(ReportError::ReadNoteError(path, e)),
+        };
+        let html = markdown_to_html(&content);
+        let relative = path.strip_prefix(&workspace.mgr_path).unwrap_or(&path);
+        notes.push(json!({
+            "path": relative.display().to_string(),
+            "content": content,
+            "html": html,
+        }));
+    }
+
+    let name = root
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "workspace".to_string());
+    Ok(json!({
+        "workspace": {"name": name, "path": root.display().to_string()},
+        "generated_at": Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        "hosts": hosts,
+        "creds": creds,
+        "findings": findings,
+        "flags": flags,
+        "flags_captured": board.flags.len(),
+        "flags_expected": board.expected,
+        "notes": notes,
+        "vars_json": serde_json::to_string_pretty(&vars).unwrap_or_default(),
+        "vars": vars,
+    }))
+}
+
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        v => v.to_string(),
+    }
+}
+
+// メモの見出しが報告書の見出しより上にならないように、# の数を増やす
+// コードブロックの中の行はそのままにする
+// MarkdownをHTMLにする。Markdownの中に書かれたHTMLはそのまま出さずに、文字列としてエスケープする
+pub(crate) fn markdown_to_html(content: &str) -> String {
+    let parser = Parser::new(content).map(|event| match event {
+        Event::Html(s) | Event::InlineHtml(s) => Event::Text(s),
+        e => e,
+    });
+    let mut html = String::new();
+    html::push_html(&mut html, parser);
+    html
+}
+
+fn demote_headings(content: &str, levels: usize) -> String {
+    let mut in_code = false;
+    let mut lines = vec![];
+    for line in content.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+        }
+        if !in_code && line.starts_with('#') {
+            lines.push(format!("{}{}", "#".repeat(levels), line));
+        } else {
+            lines.push(line.to_string());
+        }
+    }
+    lines.join("\n")
+}
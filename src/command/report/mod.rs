@@ -0,0 +1,58 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand, ValueEnum};
+
+pub mod build;
+pub mod template;
+
+pub fn commands() -> Vec<crate::shell::command::Command> {
+    vec![crate::shell::command::Command::new(
+        "report",
+        Box::new(report),
+    )]
+}
+
+// report はワークスペースのデータから報告書を作るコマンド
+//  report build [--format md|html] [--out FILE]    報告書を作る。既定ではワークスペースのルートの report.md に書く
+//  report templates                                組み込みのテンプレートを書き出して、編集できるようにする
+#[derive(Parser, Debug)]
+struct ReportArgs {
+    #[command(subcommand)]
+    command: ReportCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum ReportCommand {
+    /// render a report from findings, hosts, credentials, flags, notes and vars
+    Build {
+        #[arg(long, value_enum, default_value = "md")]
+        format: ReportFormat,
+        /// write the report to this file. defaults to report.md or report.html in the workspace
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// write the built-in templates to the config directory to customize them
+    Templates,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum ReportFormat {
+    Md,
+    Html,
+}
+
+fn report(args_: String, app_conf: &mut crate::config::AppConfig) -> bool {
+    let mut args_iter = vec!["report"];
+    args_iter.extend(&args_.split_whitespace().collect::<Vec<&str>>());
+    let args = match ReportArgs::try_parse_from(&args_iter) {
+        Ok(a) => a,
+        Err(e) => {
+            println!("{}", e);
+            return true;
+        }
+    };
+    match args.command {
+        ReportCommand::Build { format, out } => build::build(app_conf, format, out),
+        ReportCommand::Templates => template::init(app_conf),
+    }
+}
@@ -0,0 +1,84 @@
+use std::{fs, path::PathBuf};
+
+use super::ReportFormat;
+use crate::{
+    config::{is_entry_exist, AppConfig},
+    error::{self, Error, ReportError},
+    output::{error_prefix, log_prefix},
+};
+
+// 報告書のテンプレート (Tera)
+// app configディレクトリの templates/report/ に同じ名前のファイルがあればそれを使い、なければ組み込みのものを使う
+//  report.md.tera      report build --format md
+//  report.html.tera    report build --format html
+const TEMPLATE_DIR: &str = "templates/report";
+const MARKDOWN: &str = include_str!("templates/report.md.tera");
+const HTML: &str = include_str!("templates/report.html.tera");
+
+fn file_name(format: ReportFormat) -> &'static str {
+    match format {
+        ReportFormat::Md => "report.md.tera",
+        ReportFormat::Html => "report.html.tera",
+    }
+}
+
+fn builtin(format: ReportFormat) -> &'static str {
+    match format {
+        ReportFormat::Md => MARKDOWN,
+        ReportFormat::Html => HTML,
+    }
+}
+
+pub fn template_dir(app_conf: &AppConfig) -> PathBuf {
+    app_conf.app_conf_path.join(TEMPLATE_DIR)
+}
+
+// 上書き用のテンプレートがあれば読み込む
+pub fn load(app_conf: &AppConfig, format: ReportFormat) -> Result<String, ReportError> {
+    let path = template_dir(app_conf).join(file_name(format));
+    match fs::read_to_string(&path) {
+        Ok(t) => Ok(t),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(builtin(format).to_string()),
+        Err(e) => Err(ReportError::ReadTemplateError(path, e)),
+    }
+}
+
+// 組み込みのテンプレートを書き出して、編集できるようにする。すでにあるファイルは上書きしない
+pub fn init(app_conf: &AppConfig) -> bool {
+    let err_msg = || {
+        println!("{} report template error", error_prefix());
+    };
+    let dir = template_dir(app_conf);
+    match fs::create_dir_all(&dir) {
+        Ok(_) => (),
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::ReportError(ReportError::WriteError(dir, e)));
+            return true;
+        }
+    };
+    for format in [ReportFormat::Md, ReportFormat::Html] {
+        let path = dir.join(file_name(format));
+        match is_entry_exist(&path) {
+            Ok(true) => {
+                println!("{} {} already exists", log_prefix(), path.display());
+                continue;
+            }
+            Ok(false) => (),
+            Err(e) => {
+                err_msg();
+                error::print_error(Error::ReportError(ReportError::ReadTemplateError(path, e)));
+                return true;
+            }
+        };
+        match fs::write(&path, builtin(format)) {
+            Ok(_) => println!("{} wrote {}", log_prefix(), path.display()),
+            Err(e) => {
+                err_msg();
+                error::print_error(Error::ReportError(ReportError::WriteError(path, e)));
+                return true;
+            }
+        };
+    }
+    false
+}
@@ -0,0 +1,338 @@
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, fs, path::PathBuf, process};
+
+    use serde_json::json;
+
+    use super::super::{
+        import::{nmap, web},
+        report::build::markdown_to_html,
+        vars::{
+            env::{flatten, parse_dotenv, render, to_query, EnvFormat},
+            search::Target,
+        },
+        ws::{import, ArchiveManifest, MANIFEST_FILE_NAME},
+    };
+    use crate::{
+        error::{VarsEnvError, WsImportError},
+        json::json::{split_args, Json},
+        store::hosts::{Host, Hosts, Protocol},
+    };
+
+    // テストごとに別の一時ディレクトリを作る
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("pwnrail-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn ws_import_rejects_unsafe_workspace_name() {
+        let dir = temp_dir("ws-import");
+        for name in ["../../x", "/tmp/x", "a/b", "..", "."] {
+            let manifest = ArchiveManifest {
+                format_version: 1,
+                pwnrail_version: "0.0.0".to_string(),
+                exported_at: chrono::Utc::now(),
+                workspace_name: name.to_string(),
+                secrets_stripped: false,
+                files: Default::default(),
+            };
+            let content = serde_json::to_vec(&manifest).unwrap();
+            let file = dir.join("evil.tar.zst");
+            let encoder = zstd::Encoder::new(fs::File::create(&file).unwrap(), 0).unwrap();
+            let mut builder = tar::Builder::new(encoder.auto_finish());
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o600);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, MANIFEST_FILE_NAME, content.as_slice())
+                .unwrap();
+            builder.into_inner().unwrap();
+            assert!(
+                matches!(
+                    import::verify(&file),
+                    Err(WsImportError::UnsafeWorkspaceName(_))
+                ),
+                "{}",
+                name
+            );
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn web_parse_saved_outputs() {
+        let ffuf = web::parse_ffuf(include_str!("import/testdata/ffuf.json")).unwrap();
+        assert_eq!(ffuf.len(), 3);
+        assert_eq!(ffuf[0].url.as_str(), "http://dc01/admin");
+        assert_eq!(
+            (ffuf[0].status, ffuf[0].size, ffuf[0].words),
+            (301, Some(178), Some(6))
+        );
+
+        let ferox =
+            web::parse_feroxbuster(include_str!("import/testdata/feroxbuster.jsonl")).unwrap();
+        // configurationやstatisticsの行は読まない
+        assert_eq!(ferox.len(), 2);
+        assert_eq!(ferox[1].url.as_str(), "https://10.10.10.20:8443/api");
+        assert_eq!(
+            (ferox[1].status, ferox[1].size, ferox[1].words),
+            (401, Some(31), Some(3))
+        );
+
+        let gobuster = include_str!("import/testdata/gobuster.txt");
+        assert!(web::parse_gobuster(gobuster, None).is_err());
+        let hits = web::parse_gobuster(gobuster, Some("http://10.10.10.30".to_string())).unwrap();
+        assert_eq!(hits.len(), 3);
+        assert_eq!(hits[1].url.as_str(), "http://10.10.10.30/uploads");
+        assert_eq!((hits[2].status, hits[2].size), (200, Some(10918)));
+    }
+
+    #[test]
+    fn web_reimport_is_deduplicated() {
+        let mut hosts = Hosts::new();
+        let content = include_str!("import/testdata/feroxbuster.jsonl");
+        for h in web::parse_feroxbuster(content).unwrap() {
+            web::apply(&mut hosts, h);
+        }
+        let mut report = vec![];
+        for h in web::parse_feroxbuster(content).unwrap() {
+            report.extend(web::apply(&mut hosts, h));
+        }
+        assert!(report.is_empty());
+        assert_eq!(hosts.len(), 1);
+        let service = &hosts["10.10.10.20"].services[0];
+        assert_eq!((service.port, service.protocol), (8443, Protocol::Tcp));
+        assert_eq!(service.paths.len(), 2);
+    }
+
+    #[test]
+    fn web_import_keeps_existing_host() {
+        let mut hosts = Hosts::new();
+        hosts.insert(
+            "dc01".to_string(),
+            Host {
+                addresses: vec!["10.0.0.5".to_string()],
+                tags: vec!["ad".to_string()],
+                ..Default::default()
+            },
+        );
+        for h in web::parse_ffuf(include_str!("import/testdata/ffuf.json")).unwrap() {
+            web::apply(&mut hosts, h);
+        }
+        assert_eq!(hosts.len(), 1);
+        let host = &hosts["dc01"];
+        assert_eq!(host.addresses, vec!["10.0.0.5"]);
+        assert_eq!(host.tags, vec!["ad"]);
+        assert_eq!(host.services[0].paths.len(), 3);
+    }
+
+    #[test]
+    fn report_markdown_escapes_raw_html() {
+        let html =
+            markdown_to_html("# t\n\n<script>alert(1)</script>\n\nok <img src=x onerror=alert(1)>");
+        assert!(!html.contains("<script>"));
+        assert!(!html.contains("<img"));
+        assert!(html.contains("&lt;script&gt;"));
+        assert!(html.contains("<h1>t</h1>"));
+    }
+
+    #[test]
+    fn nmap_import_reports_new_and_changed_services() {
+        let scanned = nmap::parse(include_str!("import/testdata/nmap.xml")).unwrap();
+        // 落ちているホストは取り込まない
+        assert_eq!(scanned.len(), 1);
+        let mut hosts = Hosts::new();
+        let mut report = vec![];
+        for s in scanned {
+            report.extend(nmap::apply(&mut hosts, s));
+        }
+        assert_eq!(report.len(), 4);
+        assert!(report[0].contains("+ host 10.10.10.5"));
+        assert!(report[1].contains("~ 10.10.10.5 os: - -> Linux 5.0 - 5.14"));
+        assert!(report[2].contains("+ 10.10.10.5 22/tcp"));
+        assert!(
+            report[3].contains("+ 10.10.10.5 80/tcp\topen\thttp\tApache httpd\t2.4.52 ((Ubuntu))")
+        );
+        let host = &hosts["10.10.10.5"];
+        assert_eq!(host.hostnames, vec!["web01.corp.local"]);
+        assert_eq!(host.os.as_deref(), Some("Linux 5.0 - 5.14"));
+        assert_eq!(host.services[1].scripts["http-title"], "Corp Intranet");
+
+        // 同じ結果を取り込み直しても何も変わらない
+        for s in nmap::parse(include_str!("import/testdata/nmap.xml")).unwrap() {
+            assert!(nmap::apply(&mut hosts, s).is_empty());
+        }
+
+        let mut report = vec![];
+        for s in nmap::parse(include_str!("import/testdata/nmap-rescan.xml")).unwrap() {
+            report.extend(nmap::apply(&mut hosts, s));
+        }
+        assert_eq!(report.len(), 2);
+        assert!(report[0].contains("~ 10.10.10.5 80/tcp"));
+        assert!(report[0].contains("-> 80/tcp\topen\thttp\tnginx\t1.18.0 (Ubuntu)"));
+        assert!(report[1].contains("+ 10.10.10.5 8080/tcp"));
+        assert_eq!(hosts["10.10.10.5"].services.len(), 3);
+    }
+
+    #[test]
+    fn search_paths_are_valid_queries() {
+        let data = serde_json::json!({
+            "ip": "10.0.0.5",
+            "hosts": {
+                "dc01.corp.local": {"ip": "10.0.0.10", "ports": [[88, 389], [445]]},
+                "web 01": {"users": [{"name": "alice"}]},
+                "a[0]": "bracket",
+                "": "empty"
+            }
+        });
+        let target = Target {
+            re: regex::Regex::new(".*").unwrap(),
+            keys: true,
+            values: true,
+        };
+        let mut matches = vec![];
+        target.walk(&data, Some(""), &mut matches);
+        assert_eq!(matches.len(), 18);
+        let mut j = Json { data: data.clone() };
+        for (path, _) in &matches {
+            // ハイライトの色を外したものがvrに渡すクエリになる
+            let query = path.replace("\x1b[31m", "").replace("\x1b[0m", "");
+            let args = split_args(&query);
+            assert_eq!(args.len(), 1, "{}", query);
+            assert!(j.get(&args[0].to_string()).is_ok(), "{}", query);
+        }
+        let paths = matches
+            .iter()
+            .map(|(p, _)| p.replace("\x1b[31m", "").replace("\x1b[0m", ""))
+            .collect::<Vec<String>>();
+        assert!(paths.contains(&r#"hosts["dc01.corp.local"].ports[0][1]"#.to_string()));
+        assert!(paths.contains(&r#"hosts["web 01"].users[0].name"#.to_string()));
+        assert!(paths.contains(&r#"hosts["a[0]"]"#.to_string()));
+
+        // ルートが配列なら要素を指すクエリは書けないので、全体を指す . にする
+        let mut matches = vec![];
+        target.walk(
+            &serde_json::json!(["x", {"k": "y"}]),
+            Some(""),
+            &mut matches,
+        );
+        assert!(matches.iter().all(|(p, _)| p == "."));
+        assert_eq!(matches.len(), 3);
+    }
+
+    // クォートやバックスラッシュ、改行などを含む、書き出しで壊れやすい値
+    fn tricky_vars() -> BTreeMap<String, String> {
+        let value = json!({
+            "creds": [{"user": "admin", "pass": "p'a\"s$s `id` \\ #1"}],
+            "note": "line1\nline2",
+            "port": 445,
+            "empty": null,
+        });
+        let mut vars = BTreeMap::new();
+        flatten(&value, "", &mut vars).unwrap();
+        vars
+    }
+
+    #[test]
+    fn env_flatten_names() {
+        let vars = tricky_vars();
+        let names = vars.keys().cloned().collect::<Vec<String>>();
+        assert_eq!(
+            names,
+            vec!["CREDS_0_PASS", "CREDS_0_USER", "EMPTY", "NOTE", "PORT"]
+        );
+        assert_eq!(vars["PORT"], "445");
+        assert_eq!(vars["EMPTY"], "");
+    }
+
+    #[test]
+    fn env_flatten_reports_name_collisions() {
+        for value in [
+            json!({"a": {"b": 1}, "a_b": 2}),
+            json!({"Key": 1, "KEY": 2}),
+            json!({"a-b": 1, "a.b": 2}),
+        ] {
+            let mut vars = BTreeMap::new();
+            match flatten(&value, "", &mut vars) {
+                Err(VarsEnvError::NameCollision(name)) => assert!(name == "A_B" || name == "KEY"),
+                r => panic!("collision is not reported: {:?}", r),
+            }
+        }
+    }
+
+    #[test]
+    fn env_dotenv_round_trip() {
+        let vars = tricky_vars();
+        let content = vars
+            .iter()
+            .map(|(n, v)| render(EnvFormat::Dotenv, n, v))
+            .collect::<Vec<String>>()
+            .join("\n");
+        let parsed = parse_dotenv(&content).unwrap();
+        assert_eq!(parsed.into_iter().collect::<BTreeMap<_, _>>(), vars);
+    }
+
+    #[test]
+    fn env_sh_round_trip() {
+        let vars = tricky_vars();
+        for (name, value) in &vars {
+            let script = format!(
+                "{}\nprintf '%s' \"${}\"",
+                render(EnvFormat::Sh, name, value),
+                name
+            );
+            let out = process::Command::new("sh")
+                .args(["-c", &script])
+                .output()
+                .unwrap();
+            assert_eq!(String::from_utf8(out.stdout).unwrap(), *value);
+        }
+    }
+
+    #[test]
+    fn env_parse_dotenv_rejects_duplicate_names() {
+        let content = "A=1\n# comment\nexport B='x'\nA=\"2\"\n";
+        match parse_dotenv(content) {
+            Err(VarsEnvError::DuplicateDotenvName(name, line)) => {
+                assert_eq!(name, "A");
+                assert_eq!(line, 4);
+            }
+            r => panic!("duplicate is not reported: {:?}", r),
+        }
+        let pairs = parse_dotenv("export A=1 # c\nB=\"x\\\"y\"").unwrap();
+        assert_eq!(
+            pairs,
+            vec![
+                ("A".to_string(), "1".to_string()),
+                ("B".to_string(), "x\"y".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn env_dotenv_import_round_trip_utf8() {
+        let value = json!({"user": "josé", "note": "日本語 é", "tags": ["ü", "[é]"]});
+        let mut vars = BTreeMap::new();
+        flatten(&value, "", &mut vars).unwrap();
+        let content = vars
+            .iter()
+            .map(|(n, v)| render(EnvFormat::Dotenv, n, v))
+            .collect::<Vec<String>>()
+            .join("\n");
+        // vars importと同じように、名前をクエリにしてmodifyで書き込む
+        let mut j = Json::new("{}").unwrap();
+        for (name, v) in parse_dotenv(&content).unwrap() {
+            let query = to_query(&name, true).unwrap();
+            j.modify(&query, &v).unwrap();
+        }
+        assert_eq!(
+            j.data,
+            json!({"user": "josé", "note": "日本語 é", "tags": ["ü", ["é"]]})
+        );
+    }
+}
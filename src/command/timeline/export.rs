@@ -0,0 +1,120 @@
+use std::{fs, path::PathBuf};
+
+use chrono::SecondsFormat;
+
+use super::{
+    timeline::{self, Filter},
+    ExportFormat,
+};
+use crate::{
+    config::AppConfig,
+    error::{self, Error, TimelineError},
+    output::{error_prefix, log_prefix},
+    store::timeline::Entry,
+};
+
+// 報告書に載せるために記録を書き出す。時刻は正確に出せるようにUTCのRFC 3339で書く
+//  jsonl   timeline.jsonlと同じ形式
+//  csv     time,type,command,args,duration_ms,ok,exit_code,rev
+//  md      Markdownの表
+pub fn export(app_conf: &AppConfig, filter: Filter, file: &PathBuf, format: ExportFormat) -> bool {
+    let err_msg = || {
+        println!("{} timeline export error", error_prefix());
+    };
+    let entries = match timeline::load(app_conf, filter) {
+        Ok(e) => e,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::TimelineError(e));
+            return true;
+        }
+    };
+    let content = match format {
+        ExportFormat::Jsonl => {
+            let mut lines = String::new();
+            for e in &entries {
+                match serde_json::to_string(e) {
+                    Ok(l) => lines.push_str(&format!("{}\n", l)),
+                    Err(e) => {
+                        err_msg();
+                        error::print_error(Error::TimelineError(TimelineError::SerializeError(e)));
+                        return true;
+                    }
+                }
+            }
+            lines
+        }
+        ExportFormat::Csv => to_csv(&entries),
+        ExportFormat::Md => to_markdown(&entries),
+    };
+    match fs::write(file, content) {
+        Ok(_) => println!(
+            "{} exported {} entries to {}",
+            log_prefix(),
+            entries.len(),
+            file.display()
+        ),
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::TimelineError(TimelineError::WriteError(e)));
+            return true;
+        }
+    };
+    false
+}
+
+fn fields(e: &Entry) -> [String; 8] {
+    let opt = |v: Option<String>| v.unwrap_or_default();
+    [
+        e.time.to_rfc3339_opts(SecondsFormat::Millis, true),
+        e.kind.to_string(),
+        e.command.clone(),
+        opt(e.args.clone()),
+        opt(e.duration_ms.map(|d| d.to_string())),
+        opt(e.ok.map(|o| o.to_string())),
+        opt(e.exit_code.map(|c| c.to_string())),
+        opt(e.rev.map(|r| r.to_string())),
+    ]
+}
+
+const HEADER: [&str; 8] = [
+    "time",
+    "type",
+    "command",
+    "args",
+    "duration_ms",
+    "ok",
+    "exit_code",
+    "rev",
+];
+
+fn to_csv(entries: &[Entry]) -> String {
+    // カンマや引用符、改行を含む値は引用符で囲み、引用符は2つにする
+    let quote = |s: &str| {
+        if s.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", s.replace('"', "\"\""))
+        } else {
+            s.to_string()
+        }
+    };
+    let mut csv = format!("{}\n", HEADER.join(","));
+    for e in entries {
+        let row = fields(e).iter().map(|f| quote(f)).collect::<Vec<String>>();
+        csv.push_str(&format!("{}\n", row.join(",")));
+    }
+    csv
+}
+
+fn to_markdown(entries: &[Entry]) -> String {
+    let cell = |s: &str| s.replace('|', "\\|").replace('\n', " ");
+    let mut md = format!(
+        "| {} |\n|{}\n",
+        HEADER.join(" | "),
+        "---|".repeat(HEADER.len())
+    );
+    for e in entries {
+        let row = fields(e).iter().map(|f| cell(f)).collect::<Vec<String>>();
+        md.push_str(&format!("| {} |\n", row.join(" | ")));
+    }
+    md
+}
@@ -0,0 +1,70 @@
+use std::path::PathBuf;
+
+use clap::{Parser, ValueEnum};
+
+use crate::store::timeline::EntryKind;
+
+pub mod export;
+#[allow(clippy::module_inception)]
+pub mod timeline;
+
+pub fn commands() -> Vec<crate::shell::command::Command> {
+    vec![crate::shell::command::Command::new(
+        "timeline",
+        Box::new(timeline_),
+    )]
+}
+
+// timeline はワークスペースで実行したコマンドとvarsの変更の記録を表示するコマンド
+//  timeline [--since T] [--until T] [--type K]... [--limit N]     記録を古い順に表示する
+//  timeline ... --export FILE [--format jsonl|csv|md]              条件に合う記録をファイルに書き出す
+// 時刻は 2h, 30m, 1d のような今からの長さか、2024-05-01, 2024-05-01T13:00 (ローカル時刻) で指定する
+#[derive(Parser, Debug)]
+struct TimelineArgs {
+    /// only show entries after this time
+    #[arg(long)]
+    since: Option<String>,
+    /// only show entries before this time
+    #[arg(long)]
+    until: Option<String>,
+    /// only show entries of this type. can be given multiple times
+    #[arg(long = "type", value_enum)]
+    kinds: Vec<EntryKind>,
+    /// only show the last N entries
+    #[arg(long)]
+    limit: Option<usize>,
+    /// write the entries to this file instead of printing
+    #[arg(long)]
+    export: Option<PathBuf>,
+    #[arg(long, value_enum, default_value = "jsonl", requires = "export")]
+    format: ExportFormat,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum ExportFormat {
+    Jsonl,
+    Csv,
+    Md,
+}
+
+fn timeline_(args_: String, app_conf: &mut crate::config::AppConfig) -> bool {
+    let mut args_iter = vec![""];
+    args_iter.extend(&args_.split_whitespace().collect::<Vec<&str>>());
+    let args = match TimelineArgs::try_parse_from(&args_iter) {
+        Ok(a) => a,
+        Err(e) => {
+            println!("{}", e);
+            return true;
+        }
+    };
+    let filter = timeline::Filter {
+        since: args.since,
+        until: args.until,
+        kinds: args.kinds,
+        limit: args.limit,
+    };
+    match args.export {
+        Some(file) => export::export(app_conf, filter, &file, args.format),
+        None => timeline::list(app_conf, filter),
+    }
+}
@@ -0,0 +1,112 @@
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+
+use crate::{
+    config::AppConfig,
+    error::{self, Error, TimelineError},
+    output::{error_prefix, green, log_prefix, red},
+    store::timeline::{self, Entry, EntryKind},
+};
+
+pub struct Filter {
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub kinds: Vec<EntryKind>,
+    pub limit: Option<usize>,
+}
+
+pub fn list(app_conf: &AppConfig, filter: Filter) -> bool {
+    let entries = match load(app_conf, filter) {
+        Ok(e) => e,
+        Err(e) => {
+            println!("{} timeline error", error_prefix());
+            error::print_error(Error::TimelineError(e));
+            return true;
+        }
+    };
+    for e in &entries {
+        println!("{}", describe(e));
+    }
+    if entries.is_empty() {
+        println!("{} no entry is recorded", log_prefix());
+    }
+    false
+}
+
+// 2024-05-01 13:00:05  command  vr ip  12ms  ok のような1行の説明。時刻はローカル時刻
+fn describe(e: &Entry) -> String {
+    let mut line = format!(
+        "{}\t{}\t{}",
+        e.time.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S"),
+        e.kind,
+        e.command
+    );
+    if let Some(a) = &e.args {
+        line = format!("{} {}", line, a);
+    }
+    if let Some(rev) = e.rev {
+        line = format!("{}\t(rev {})", line, rev);
+    }
+    if let Some(d) = e.duration_ms {
+        line = format!("{}\t{}ms", line, d);
+    }
+    match (e.ok, e.exit_code) {
+        (Some(true), _) => line = format!("{}\t{}", line, green("ok")),
+        (Some(false), Some(code)) => line = format!("{}\t{}", line, red(&format!("exit {}", code))),
+        (Some(false), None) => line = format!("{}\t{}", line, red("error")),
+        (None, _) => (),
+    }
+    line
+}
+
+// 条件に合う記録を古い順に返す
+pub fn load(app_conf: &AppConfig, filter: Filter) -> Result<Vec<Entry>, TimelineError> {
+    if app_conf.dyn_conf.current_workspace.is_empty() {
+        return Err(TimelineError::NoCurrentWorkspace);
+    }
+    let since = filter.since.as_deref().map(parse_time).transpose()?;
+    let until = filter.until.as_deref().map(parse_time).transpose()?;
+    let path = app_conf.dyn_conf.to_workspace().timeline_path;
+    let mut entries = timeline::read(&path)?
+        .into_iter()
+        .filter(|e| since.is_none_or(|s| e.time >= s))
+        .filter(|e| until.is_none_or(|u| e.time <= u))
+        .filter(|e| filter.kinds.is_empty() || filter.kinds.contains(&e.kind))
+        .collect::<Vec<Entry>>();
+    entries.sort_by_key(|e| e.time);
+    if let Some(n) = filter.limit {
+        let skip = entries.len().saturating_sub(n);
+        entries.drain(..skip);
+    }
+    Ok(entries)
+}
+
+// 30m, 2h, 1d のような今からの長さ、またはローカル時刻の日付・日時、RFC 3339の時刻
+fn parse_time(s: &str) -> Result<DateTime<Utc>, TimelineError> {
+    let invalid = || TimelineError::InvalidTime(s.to_string());
+    if let Some((n, unit)) = s.split_at_checked(s.len().saturating_sub(1)) {
+        if let Ok(n) = n.parse::<i64>() {
+            let duration = match unit {
+                "m" => Duration::minutes(n),
+                "h" => Duration::hours(n),
+                "d" => Duration::days(n),
+                _ => return Err(invalid()),
+            };
+            return Ok(Utc::now() - duration);
+        }
+    }
+    if let Ok(t) = DateTime::parse_from_rfc3339(s) {
+        return Ok(t.with_timezone(&Utc));
+    }
+    let naive = ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"]
+        .iter()
+        .find_map(|f| NaiveDateTime::parse_from_str(s, f).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                .ok()
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+        });
+    match naive.and_then(|n| Local.from_local_datetime(&n).earliest()) {
+        Some(t) => Ok(t.with_timezone(&Utc)),
+        None => Err(invalid()),
+    }
+}
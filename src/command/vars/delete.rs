@@ -0,0 +1,47 @@
+use clap::Parser;
+
+use crate::{
+    error::{self, Error},
+    json::json::split_args,
+    output::error_prefix,
+    store,
+};
+
+// vd (delete)ではjsonクエリで指定した場所の値を削除する
+#[derive(Parser, Debug)]
+struct DelArgs {
+    path: String,
+}
+
+pub fn delete(args_: String, app_conf: &mut crate::config::AppConfig) -> bool {
+    let err_msg = || {
+        println!("{} vars deletion error", error_prefix());
+    };
+    let mut args_iter = vec![""];
+    args_iter.extend(split_args(&args_));
+    let args = match DelArgs::try_parse_from(&args_iter) {
+        Ok(a) => a,
+        Err(e) => {
+            println!("{}", e);
+            return true;
+        }
+    };
+    let mut snapshot = match store::vars::load(app_conf) {
+        Ok(s) => s,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::VarsStoreError(e));
+            return true;
+        }
+    };
+    match snapshot.json.delete(&args.path) {
+        Ok(_) => (),
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::JsonQueryError(e));
+            return true;
+        }
+    };
+    let summary = format!("vd {}", args_);
+    super::modify::save(app_conf, snapshot, &summary, err_msg)
+}
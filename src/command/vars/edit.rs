@@ -0,0 +1,186 @@
+use std::{env, fs, path::PathBuf, process};
+
+use clap::Parser;
+use serde_json::Value;
+
+use crate::{
+    error::{self, Error},
+    json::{diff, error::JsonQueryError, json::split_args},
+    output::{error_prefix, log_prefix},
+    store::{self, format::VarsFormat, lock},
+};
+
+// ve (edit)ではjsonクエリで指定した場所(省略した場合はvars全体)を$EDITORで編集する
+// 保存された内容をパースできない場合は、エラーを先頭に注釈として書き込んでからエディタを開き直す
+// 内容を空にして保存すると編集を取り消す
+#[derive(Parser, Debug)]
+struct EditArgs {
+    query: Option<String>,
+}
+
+pub fn edit(args_: String, app_conf: &mut crate::config::AppConfig) -> bool {
+    let err_msg = || {
+        println!("{} vars edit error", error_prefix());
+    };
+    let mut args_iter = vec![""];
+    args_iter.extend(split_args(&args_));
+    let args = match EditArgs::try_parse_from(&args_iter) {
+        Ok(a) => a,
+        Err(e) => {
+            println!("{}", e);
+            return true;
+        }
+    };
+    let mut snapshot = match store::vars::load(app_conf) {
+        Ok(s) => s,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::VarsStoreError(e));
+            return true;
+        }
+    };
+    let query = args.query.unwrap_or(".".to_string());
+    // まだ存在しない場所を指定した場合はnullから編集を始める
+    let old = match snapshot.json.get(&query) {
+        Ok(v) => v,
+        Err(JsonQueryError::NotFound) => Value::Null,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::JsonQueryError(e));
+            return true;
+        }
+    };
+    let format = match store::vars::workspace(app_conf) {
+        Ok(w) => w.vars_format,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::VarsStoreError(e));
+            return true;
+        }
+    };
+    let new = match edit_in_editor(&old, format) {
+        Ok(Some(v)) => v,
+        Ok(None) => {
+            println!("{} the edit was cancelled", log_prefix());
+            return false;
+        }
+        Err(e) => {
+            err_msg();
+            println!("{} {}", error_prefix(), e);
+            return true;
+        }
+    };
+
+    let before = snapshot.json.data.clone();
+    match snapshot.json.set(&query, new) {
+        Ok(_) => (),
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::JsonQueryError(e));
+            return true;
+        }
+    };
+    let changes = diff::diff(&before, &snapshot.json.data);
+    if changes.is_empty() {
+        println!("{} nothing changed", log_prefix());
+        return false;
+    }
+    for change in &changes {
+        super::history::print_change(change);
+    }
+    let summary = format!("ve {}", query);
+    super::modify::save(app_conf, snapshot, &summary, err_msg)
+}
+
+// 値を一時ファイルに書き出してエディタで開き、保存された内容をパースして返す
+// ワークスペースのvarsファイルと同じ形式で編集する。ただしtomlはテーブル以外を書けないので、そのときはjsonにする
+// 内容が空にされた場合はNoneを返す
+fn edit_in_editor(value: &Value, format: VarsFormat) -> Result<Option<Value>, String> {
+    let format = match format {
+        VarsFormat::Toml if !value.is_object() => VarsFormat::Json,
+        f => f,
+    };
+    // 秘密の値も書き出すので、ほかのユーザーから読めないディレクトリに置く
+    let dir = match lock::create_private_temp_dir("pwnrail-ve") {
+        Ok(d) => d,
+        Err(e) => return Err(format!("failed to create a temporary directory: {}", e)),
+    };
+    let path = dir.join(format!("vars.{}", format.extension()));
+    let result = edit_file(&path, value, format);
+    let _ = fs::remove_dir_all(&dir);
+    result
+}
+
+fn edit_file(path: &PathBuf, value: &Value, format: VarsFormat) -> Result<Option<Value>, String> {
+    let mut content = match format.serialize(value) {
+        Ok(c) => String::from_utf8_lossy(&c).to_string(),
+        Err(e) => return Err(e.to_string()),
+    };
+    let prefix = annotation_prefix(format);
+    let mut annotation = String::new();
+    loop {
+        match fs::write(path, format!("{}{}\n", annotation, content.trim_end())) {
+            Ok(_) => (),
+            Err(e) => return Err(format!("failed to write a temporary file: {}", e)),
+        };
+        match open_editor(path) {
+            Ok(_) => (),
+            Err(e) => return Err(e),
+        };
+        let saved = match fs::read_to_string(path) {
+            Ok(s) => s,
+            Err(e) => return Err(format!("failed to read the temporary file: {}", e)),
+        };
+        // 注釈の行は空行にしてからパースするので、エラーの行番号はエディタで見える行番号と一致する
+        let annotation_lines = saved
+            .lines()
+            .take_while(|l| l.trim_start().starts_with(prefix))
+            .count();
+        content = saved
+            .lines()
+            .skip(annotation_lines)
+            .collect::<Vec<&str>>()
+            .join("\n");
+        if content.trim().is_empty() {
+            return Ok(None);
+        }
+        match format.parse(&format!("{}{}", "\n".repeat(annotation_lines), content)) {
+            Ok(v) => return Ok(Some(v)),
+            Err(e) => {
+                annotation = format!(
+                    "{p} error: {}\n{p} fix the content below and save it again, or empty the file to cancel\n",
+                    e.to_string().replace('\n', " "),
+                    p = prefix
+                );
+            }
+        }
+    }
+}
+
+// エディタで保存された内容の先頭にある、この接頭辞で始まる行は注釈として読み飛ばす
+// yamlとtomlではそのままコメントになる
+fn annotation_prefix(format: VarsFormat) -> &'static str {
+    match format {
+        VarsFormat::Json => "//",
+        VarsFormat::Yaml | VarsFormat::Toml => "#",
+    }
+}
+
+// $EDITORを開いて閉じられるまで待つ。"code -w"のように引数を含んでいてもよい
+pub fn open_editor(path: &PathBuf) -> Result<(), String> {
+    let editor = env::var("EDITOR").unwrap_or("vi".to_string());
+    let mut words = editor.split_whitespace();
+    let program = match words.next() {
+        Some(p) => p,
+        None => return Err("$EDITOR is empty".to_string()),
+    };
+    match process::Command::new(program)
+        .args(words)
+        .arg(path)
+        .status()
+    {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(format!("the editor exited with {}", status)),
+        Err(e) => Err(format!("failed to launch the editor `{}`: {}", editor, e)),
+    }
+}
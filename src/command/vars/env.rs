@@ -0,0 +1,431 @@
+use std::{collections::BTreeMap, env, fs, path::PathBuf};
+
+use clap::ValueEnum;
+use serde_json::Value;
+
+use crate::{
+    config::AppConfig,
+    error::{self, Error, VarsEnvError},
+    json::diff,
+    output::{error_prefix, log_prefix},
+    store::{self, secret},
+};
+
+// varsを環境変数として書き出したり、環境変数や.envファイルからvarsに取り込んだりする
+// creds[0].user のようなクエリは CREDS_0_USER のような名前に平らにする
+//  vars export --format sh|fish|dotenv|powershell [--prefix P_] [-o file] [query]
+//  vars import --from-env P_ [--split] [--into query]
+//  vars import --dotenv file [--prefix P_] [--split] [--into query]
+// importでは名前を小文字にしたものをキーにする。--splitを付けると _ で区切って階層にし、数字は配列の添字にする
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum EnvFormat {
+    Sh,
+    Fish,
+    Dotenv,
+    Powershell,
+}
+
+pub fn export(
+    app_conf: &mut AppConfig,
+    format: EnvFormat,
+    prefix: String,
+    output: Option<PathBuf>,
+    query: Option<String>,
+) -> bool {
+    let err_msg = || {
+        println!("{} vars export error", error_prefix());
+    };
+    let mut snapshot = match store::vars::load(app_conf) {
+        Ok(s) => s,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::VarsStoreError(e));
+            return true;
+        }
+    };
+    let query = query.unwrap_or(".".to_string());
+    let value = match snapshot.json.get(&query) {
+        Ok(v) => v,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::JsonQueryError(e));
+            return true;
+        }
+    };
+    // クエリで指定した場所の名前も含めて平らにする (vars export creds -> CREDS_0_USER)
+    let base = if query == "." {
+        "".to_string()
+    } else {
+        env_name(&query)
+    };
+    let mut vars = BTreeMap::new();
+    let mut skipped = 0;
+    match flatten(&value, &base, &mut vars) {
+        Ok(_) => (),
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::VarsEnvError(e));
+            return true;
+        }
+    };
+
+    let mut lines = vec![];
+    for (name, v) in vars {
+        // 秘密の値はunlockされていれば復号して書き出す
+        let v = if v.starts_with(secret::SECRET_PREFIX) {
+            match &app_conf.secret_key {
+                Some(key) => match secret::decrypt(key, &v) {
+                    Ok(plain) => plain,
+                    Err(e) => {
+                        err_msg();
+                        error::print_error(Error::SecretError(e));
+                        return true;
+                    }
+                },
+                None => {
+                    skipped += 1;
+                    continue;
+                }
+            }
+        } else {
+            v
+        };
+        lines.push(render(format, &format!("{}{}", prefix, name), &v));
+    }
+    let count = lines.len();
+    // どの形式でも # から始まる行はコメントになるので、そのままevalできる
+    if skipped > 0 {
+        lines.push(format!(
+            "# {} secret values were skipped. run `unlock` to include them",
+            skipped
+        ));
+    }
+    let snippet = lines.join("\n");
+    match output {
+        Some(path) => {
+            match fs::write(&path, format!("{}\n", snippet)) {
+                Ok(_) => (),
+                Err(e) => {
+                    err_msg();
+                    error::print_error(Error::VarsEnvError(VarsEnvError::WriteError(e)));
+                    return true;
+                }
+            };
+            println!(
+                "{} wrote {} variables to {}",
+                log_prefix(),
+                count,
+                path.display()
+            );
+        }
+        None => println!("{}", snippet),
+    }
+    false
+}
+
+pub fn import(
+    app_conf: &mut AppConfig,
+    from_env: Option<String>,
+    dotenv: Option<PathBuf>,
+    prefix: Option<String>,
+    split: bool,
+    into: Option<String>,
+) -> bool {
+    let err_msg = || {
+        println!("{} vars import error", error_prefix());
+    };
+    let (source, prefix, summary) = match (from_env, dotenv) {
+        (Some(p), None) => (
+            env::vars().collect::<Vec<(String, String)>>(),
+            p.clone(),
+            format!("vars import --from-env {}", p),
+        ),
+        (None, Some(file)) => {
+            let content = match fs::read_to_string(&file) {
+                Ok(c) => c,
+                Err(e) => {
+                    err_msg();
+                    error::print_error(Error::VarsEnvError(VarsEnvError::ReadError(e)));
+                    return true;
+                }
+            };
+            let pairs = match parse_dotenv(&content) {
+                Ok(p) => p,
+                Err(e) => {
+                    err_msg();
+                    error::print_error(Error::VarsEnvError(e));
+                    return true;
+                }
+            };
+            (
+                pairs,
+                prefix.unwrap_or_default(),
+                format!("vars import --dotenv {}", file.display()),
+            )
+        }
+        _ => {
+            println!(
+                "{} give either --from-env <PREFIX> or --dotenv <file>",
+                error_prefix()
+            );
+            return true;
+        }
+    };
+
+    // 配列に要素を足していく順番になるように、数字は数として並べる
+    let mut entries = vec![];
+    for (name, value) in source {
+        let rest = match name.strip_prefix(&prefix) {
+            Some(r) if !r.is_empty() => r,
+            _ => continue,
+        };
+        let query = match to_query(rest, split) {
+            Some(q) => q,
+            None => {
+                err_msg();
+                error::print_error(Error::VarsEnvError(VarsEnvError::InvalidName(name)));
+                return true;
+            }
+        };
+        let query = match &into {
+            Some(parent) => format!("{}.{}", parent, query),
+            None => query,
+        };
+        // FOOとfooのように、別の名前が同じクエリになる場合は片方が消えてしまう
+        if entries.iter().any(|(_, q, _)| *q == query) {
+            err_msg();
+            error::print_error(Error::VarsEnvError(VarsEnvError::NameCollision(query)));
+            return true;
+        }
+        entries.push((sort_key(&query), query, value));
+    }
+    entries.sort();
+    if entries.is_empty() {
+        println!("{} no variable matched", log_prefix());
+        return false;
+    }
+
+    let mut snapshot = match store::vars::load(app_conf) {
+        Ok(s) => s,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::VarsStoreError(e));
+            return true;
+        }
+    };
+    let before = snapshot.json.data.clone();
+    for (_, query, value) in entries {
+        match snapshot.json.modify(&query, &value) {
+            Ok(_) => (),
+            Err(e) => {
+                err_msg();
+                println!("{} failed to import {}", error_prefix(), query);
+                error::print_error(Error::JsonQueryError(e));
+                return true;
+            }
+        };
+    }
+    let changes = diff::diff(&before, &snapshot.json.data);
+    if changes.is_empty() {
+        println!("{} nothing changed", log_prefix());
+        return false;
+    }
+    for change in &changes {
+        super::history::print_change(change);
+    }
+    super::modify::save(app_conf, snapshot, &summary, err_msg)
+}
+
+// 値を NAME -> 文字列 に平らにする。空のオブジェクトや配列は書き出さない
+// a.b と a_b のように、別のキーが同じ名前になる場合はNameCollisionを返す
+pub(crate) fn flatten(
+    value: &Value,
+    name: &str,
+    vars: &mut BTreeMap<String, String>,
+) -> Result<(), VarsEnvError> {
+    let join = |key: &str| {
+        if name.is_empty() {
+            env_name(key)
+        } else {
+            format!("{}_{}", name, env_name(key))
+        }
+    };
+    let leaf = match value {
+        Value::Object(map) => {
+            for (k, v) in map {
+                flatten(v, &join(k), vars)?;
+            }
+            return Ok(());
+        }
+        Value::Array(arr) => {
+            for (i, v) in arr.iter().enumerate() {
+                flatten(v, &join(&i.to_string()), vars)?;
+            }
+            return Ok(());
+        }
+        Value::String(s) => s.clone(),
+        Value::Null => "".to_string(),
+        v => v.to_string(),
+    };
+    let name = leaf_name(name);
+    if vars.contains_key(&name) {
+        return Err(VarsEnvError::NameCollision(name));
+    }
+    vars.insert(name, leaf);
+    Ok(())
+}
+
+// vars全体が文字列などの場合は名前が空になるので、VARSという名前にする
+fn leaf_name(name: &str) -> String {
+    match name {
+        "" => "VARS".to_string(),
+        n if n.starts_with(|c: char| c.is_ascii_digit()) => format!("_{}", n),
+        n => n.to_string(),
+    }
+}
+
+// 英数字以外は _ にして大文字にする。creds[0].user -> CREDS_0_USER
+fn env_name(key: &str) -> String {
+    let name = key
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    name.split('_')
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<&str>>()
+        .join("_")
+}
+
+pub(crate) fn render(format: EnvFormat, name: &str, value: &str) -> String {
+    match format {
+        EnvFormat::Sh => format!("export {}='{}'", name, value.replace('\'', "'\\''")),
+        EnvFormat::Fish => format!(
+            "set -gx {} '{}'",
+            name,
+            value.replace('\\', "\\\\").replace('\'', "\\'")
+        ),
+        EnvFormat::Dotenv => format!(
+            "{}=\"{}\"",
+            name,
+            value
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('$', "\\$")
+                .replace('\n', "\\n")
+        ),
+        EnvFormat::Powershell => format!("$env:{} = '{}'", name, value.replace('\'', "''")),
+    }
+}
+
+// 環境変数の名前をクエリにする。使えない文字を含んでいる場合はNone
+pub(crate) fn to_query(name: &str, split: bool) -> Option<String> {
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return None;
+    }
+    let name = name.to_lowercase();
+    if !split {
+        return Some(name);
+    }
+    let mut query = String::new();
+    for segment in name.split('_') {
+        if segment.is_empty() {
+            return None;
+        }
+        if segment.chars().all(|c| c.is_ascii_digit()) {
+            if query.is_empty() {
+                return None;
+            }
+            query = format!("{}[{}]", query, segment);
+        } else if query.is_empty() {
+            query = segment.to_string();
+        } else {
+            query = format!("{}.{}", query, segment);
+        }
+    }
+    Some(query)
+}
+
+fn sort_key(query: &str) -> Vec<(u64, String)> {
+    query
+        .split(['.', '[', ']'])
+        .filter(|s| !s.is_empty())
+        .map(|s| match s.parse::<u64>() {
+            Ok(n) => (n, "".to_string()),
+            Err(_) => (u64::MAX, s.to_string()),
+        })
+        .collect()
+}
+
+// KEY=VALUE の行を読む。export で始まる行、# のコメント、クォートされた値に対応する
+// 同じ名前が2回出てきた場合は、どちらを使うべきか分からないのでエラーにする
+pub(crate) fn parse_dotenv(content: &str) -> Result<Vec<(String, String)>, VarsEnvError> {
+    let mut pairs = vec![];
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (name, value) = match line.split_once('=') {
+            Some((n, v)) => (n.trim(), v.trim()),
+            None => return Err(VarsEnvError::InvalidDotenvLine(i + 1)),
+        };
+        // 閉じクォートより後ろや、クォートされていない値の " #" より後ろはコメントとして取り除く
+        let value = if let Some(quoted) = value.strip_prefix('"') {
+            match closing_quote(quoted) {
+                Some(end) => unescape(&quoted[..end]),
+                None => return Err(VarsEnvError::InvalidDotenvLine(i + 1)),
+            }
+        } else if let Some(quoted) = value.strip_prefix('\'') {
+            match quoted.find('\'') {
+                Some(end) => quoted[..end].to_string(),
+                None => return Err(VarsEnvError::InvalidDotenvLine(i + 1)),
+            }
+        } else {
+            value.split(" #").next().unwrap_or("").trim().to_string()
+        };
+        if pairs.iter().any(|(n, _)| n == name) {
+            return Err(VarsEnvError::DuplicateDotenvName(name.to_string(), i + 1));
+        }
+        pairs.push((name.to_string(), value));
+    }
+    Ok(pairs)
+}
+
+// バックスラッシュでエスケープされていない " の位置を返す
+fn closing_quote(value: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in value.char_indices() {
+        match c {
+            '\\' if !escaped => escaped = true,
+            '"' if !escaped => return Some(i),
+            _ => escaped = false,
+        }
+    }
+    None
+}
+
+fn unescape(value: &str) -> String {
+    let mut result = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some(c) => result.push(c),
+            None => result.push('\\'),
+        }
+    }
+    result
+}
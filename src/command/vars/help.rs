@@ -1,6 +1,6 @@
 use crate::output::blue;
 
-pub fn help1(args: String, app_conf: &mut crate::config::AppConfig) -> bool {
+pub fn help1(_args: String, _app_conf: &mut crate::config::AppConfig) -> bool {
     println!("{}ars", blue("V"));
     println!("\trefer or modify variables like an ip address.");
     println!("Commands:");
@@ -10,13 +10,51 @@ pub fn help1(args: String, app_conf: &mut crate::config::AppConfig) -> bool {
     println!(
         "\tvr\trefer the variables by a json query like this: \"vr ip\", \"vr creds[0].password\".",
     );
+    println!(
+        "\tvl\tlist the values with their queries: \"vl\", \"vl creds\". secret values are always shown as <secret>."
+    );
     println!(
         "\tvm\tmodify the variables by a json query. when you want to register the ip adress, you can do it with this: \"vm ip 0.0.0.0\" for example."
     );
+    println!(
+        "\t\tadd --secret to store the value encrypted: \"vm --secret creds[0].password P@ssw0rd\". it needs \"unlock\"."
+    );
+    println!(
+        "\t\ta value can refer to other values like this: \"vm url http://{{{{ip}}}}:{{{{ports.http}}}}/\". add --raw to vr to see the template."
+    );
+    println!("\tvd\tdelete the variables by a json query like this: \"vd creds[0]\".");
+    println!(
+        "\tve\tedit the variables in $EDITOR. give a json query to edit only a part of them like this: \"ve creds\"."
+    );
+    println!(
+        "\tvs\tsearch keys and values and print their queries: \"vs admin\", \"vs --regex ^10\\.\", \"vs --keys pass\"."
+    );
+    println!(
+        "\tvi\tprint a line with {{{{query}}}} replaced by the variables: \"vi nmap -sV -p {{{{ports.http}}}} {{{{ip}}}}\"."
+    );
+    println!(
+        "\tvars\tshow or revert the history of the variables: \"vars log\", \"vars diff 3\", \"vars undo\", \"vars restore 3 creds\"."
+    );
+    println!(
+        "\t\tconvert the variables from or to environment variables: \"vars export --format sh\", \"vars import --dotenv .env\"."
+    );
+    println!(
+        "\t\tstore the variables as yaml or toml instead of json: \"vars convert --to yaml\"."
+    );
+    println!(
+        "\t\tmerge the variables of another workspace or a file: \"vars merge ad-corp --at domain\", \"vars merge creds.json --strategy append\"."
+    );
+    println!(
+        "\t\tcheck the variables against .prail/vars.schema.json before saving: \"vars schema init\", \"vars schema check\"."
+    );
+    println!(
+        "\tunlock\tload the key for secret values from ~/.pwnrail/secret.key or a passphrase. vr shows secret values only while unlocked."
+    );
+    println!("\tlock\tforget the key for secret values.");
     false
 }
 
-pub fn help2(args: String, app_conf: &crate::config::AppConfig) -> bool {
+#[allow(dead_code)]
+pub fn help2(_args: String, _app_conf: &crate::config::AppConfig) -> bool {
     false
 }
-
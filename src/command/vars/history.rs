@@ -0,0 +1,183 @@
+use chrono::Local;
+use serde_json::Value;
+
+use crate::{
+    config::AppConfig,
+    error::{self, Error},
+    json::{
+        diff::{self, Change},
+        error::JsonQueryError,
+        json::Json,
+    },
+    output::{blue, error_prefix, green, log_prefix, red},
+    store::{self, history, secret},
+};
+
+// varsコマンドの変更履歴に関するサブコマンド
+// vm, vdなどで書き込むたびに .prail/history にリビジョンが記録される
+// undo, restoreも新しいリビジョンとして記録されるので、restoreで取り消しを取り消すこともできる
+// undoを続けると、1つずつ前のリビジョンに戻っていく
+
+fn history_err_msg() {
+    println!("{} vars history error", error_prefix());
+}
+
+pub fn log(app_conf: &AppConfig) -> bool {
+    let history_path = app_conf.dyn_conf.to_workspace().history_path;
+    let revisions = match history::revisions(&history_path) {
+        Ok(r) => r,
+        Err(e) => {
+            history_err_msg();
+            error::print_error(Error::VarsHistoryError(e));
+            return true;
+        }
+    };
+    if revisions.is_empty() {
+        println!("{} no revision is recorded yet", log_prefix());
+    }
+    for r in revisions.iter().rev() {
+        println!(
+            "{}\t{}\t{}",
+            blue(&r.rev.to_string()),
+            r.time.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S"),
+            r.summary
+        );
+    }
+    false
+}
+
+pub fn show_diff(app_conf: &AppConfig, rev: u64) -> bool {
+    let history_path = app_conf.dyn_conf.to_workspace().history_path;
+    let new = match history::read_revision(&history_path, rev) {
+        Ok(v) => v,
+        Err(e) => {
+            history_err_msg();
+            error::print_error(Error::VarsHistoryError(e));
+            return true;
+        }
+    };
+    // 最初のリビジョンは空のオブジェクトとの差分を表示する
+    let old = if rev == 0 {
+        Value::Object(Default::default())
+    } else {
+        match history::read_revision(&history_path, rev - 1) {
+            Ok(v) => v,
+            Err(e) => {
+                history_err_msg();
+                error::print_error(Error::VarsHistoryError(e));
+                return true;
+            }
+        }
+    };
+    let changes = diff::diff(&old, &new);
+    if changes.is_empty() {
+        println!("{} nothing changed in revision {}", log_prefix(), rev);
+    }
+    for change in &changes {
+        print_change(change);
+    }
+    false
+}
+
+// 暗号化された値は長いだけなので <secret> と表示する
+pub fn print_change(change: &Change) {
+    let masked = |v: &Value| {
+        let mut v = v.clone();
+        secret::mask(&mut v);
+        v
+    };
+    match change {
+        Change::Added(path, v) => {
+            println!("{}", green(&format!("+ {} = {}", path, masked(v))))
+        }
+        Change::Removed(path, v) => println!("{}", red(&format!("- {} = {}", path, masked(v)))),
+        Change::Modified(path, old, new) => println!(
+            "{}",
+            blue(&format!("~ {}: {} -> {}", path, masked(old), masked(new)))
+        ),
+    }
+}
+
+pub fn undo(app_conf: &mut AppConfig) -> bool {
+    let history_path = app_conf.dyn_conf.to_workspace().history_path;
+    let revisions = match history::revisions(&history_path) {
+        Ok(r) => r,
+        Err(e) => {
+            history_err_msg();
+            error::print_error(Error::VarsHistoryError(e));
+            return true;
+        }
+    };
+    let target = match history::undo_target(&revisions) {
+        Some(t) => t,
+        None => {
+            println!("{} there is nothing to undo", log_prefix());
+            return false;
+        }
+    };
+    let summary = history::undo_summary(target);
+    restore_(app_conf, target, None, &summary)
+}
+
+pub fn restore(app_conf: &mut AppConfig, rev: u64, query: Option<String>) -> bool {
+    let summary = match &query {
+        Some(q) => format!("vars restore {} {}", rev, q),
+        None => format!("vars restore {}", rev),
+    };
+    restore_(app_conf, rev, query, &summary)
+}
+
+fn restore_(app_conf: &mut AppConfig, rev: u64, query: Option<String>, summary: &str) -> bool {
+    let err_msg = || {
+        println!("{} vars restoration error", error_prefix());
+    };
+    let history_path = app_conf.dyn_conf.to_workspace().history_path;
+    let old = match history::read_revision(&history_path, rev) {
+        Ok(v) => v,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::VarsHistoryError(e));
+            return true;
+        }
+    };
+    let mut snapshot = match store::vars::load(app_conf) {
+        Ok(s) => s,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::VarsStoreError(e));
+            return true;
+        }
+    };
+    let before = snapshot.json.data.clone();
+    match &query {
+        None => snapshot.json.data = old,
+        Some(q) => {
+            // そのリビジョンに値がなかった場合は、現在の値を削除する
+            let result = match (Json { data: old }).get(q) {
+                Ok(v) => snapshot.json.set(q, v),
+                Err(JsonQueryError::NotFound) => match snapshot.json.delete(q) {
+                    Err(JsonQueryError::NotFound) => Ok(()),
+                    r => r,
+                },
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(_) => (),
+                Err(e) => {
+                    err_msg();
+                    error::print_error(Error::JsonQueryError(e));
+                    return true;
+                }
+            }
+        }
+    };
+    let changes = diff::diff(&before, &snapshot.json.data);
+    if changes.is_empty() {
+        println!("{} the vars are already in that state", log_prefix());
+        return false;
+    }
+    for change in &changes {
+        print_change(change);
+    }
+    super::modify::save(app_conf, snapshot, summary, err_msg)
+}
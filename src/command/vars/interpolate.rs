@@ -0,0 +1,59 @@
+use clap::Parser;
+use serde_json::Value;
+
+use crate::{
+    error::{self, Error},
+    output::error_prefix,
+    store::{self, secret},
+};
+
+// vi (interpolate)では文字列の中の {{query}} をvarsの値で置き換えてプリントする
+// 例: vi nmap -sV -p {{ports.http}} {{ip}}, vi smbclient -L {{hosts["dc01"].ip}}
+// 秘密の値はunlockされていれば復号して埋め込み、そうでなければ <secret> になる
+#[derive(Parser, Debug)]
+struct InterpolateArgs {
+    #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+    template: Vec<String>,
+}
+
+pub fn interpolate(args_: String, app_conf: &mut crate::config::AppConfig) -> bool {
+    let err_msg = || {
+        println!("{} vars interpolation error", error_prefix());
+    };
+    let mut args_iter = vec![""];
+    args_iter.extend(&args_.split_whitespace().collect::<Vec<&str>>());
+    let args = match InterpolateArgs::try_parse_from(&args_iter) {
+        Ok(a) => a,
+        Err(e) => {
+            println!("{}", e);
+            return true;
+        }
+    };
+    let mut snapshot = match store::vars::load(app_conf) {
+        Ok(s) => s,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::VarsStoreError(e));
+            return true;
+        }
+    };
+    match store::view::attach(app_conf, &mut snapshot.json.data) {
+        Ok(_) => (),
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::ViewError(e));
+            return true;
+        }
+    };
+    let key = app_conf.secret_key.as_ref();
+    let text = |v: &Value| secret::to_text(key, v);
+    match snapshot.json.render(&args.template.join(" "), &text) {
+        Ok(line) => println!("{}", line),
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::JsonQueryError(e));
+            return true;
+        }
+    };
+    false
+}
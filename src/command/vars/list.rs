@@ -0,0 +1,99 @@
+use clap::Parser;
+use serde_json::Value;
+
+use crate::{
+    error::{self, Error},
+    json::{error::JsonQueryError, json::split_args},
+    output::{error_prefix, log_prefix},
+    store::{self, secret},
+};
+
+use super::search::key_path;
+
+// vl (list)ではvarsの値を1つずつ、vrで使えるクエリと一緒にプリントする
+// クエリを指定するとその場所の下だけをプリントする。テンプレートは評価せずにそのまま表示する
+// 秘密の値はunlockされていても <secret> と表示する。中身を見るときはvrで1つずつ指定する
+#[derive(Parser, Debug)]
+struct ListArgs {
+    path: Option<String>,
+}
+
+pub fn list(args_: String, app_conf: &mut crate::config::AppConfig) -> bool {
+    let err_msg = || {
+        println!("{} vars list error", error_prefix());
+    };
+    let mut args_iter = vec![""];
+    args_iter.extend(split_args(&args_));
+    let args = match ListArgs::try_parse_from(&args_iter) {
+        Ok(a) => a,
+        Err(e) => {
+            println!("{}", e);
+            return true;
+        }
+    };
+    let mut snapshot = match store::vars::load(app_conf) {
+        Ok(s) => s,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::VarsStoreError(e));
+            return true;
+        }
+    };
+    let path = args.path.unwrap_or(".".to_string());
+    let value = match snapshot.json.get(&path) {
+        Ok(v) => v,
+        Err(JsonQueryError::NotFound) => {
+            println!("not found");
+            return false;
+        }
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::JsonQueryError(e));
+            return true;
+        }
+    };
+    let root = if path == "." { "" } else { path.as_str() };
+    let mut lines = vec![];
+    collect(&value, Some(root), &mut lines);
+    for (path, value) in &lines {
+        println!("{} = {}", path, value);
+    }
+    if lines.is_empty() {
+        println!("{} no value", log_prefix());
+    }
+    false
+}
+
+// 値を (vrのクエリ, 表示する値) にしてlinesに加える。空のオブジェクトや配列は表示しない
+// pathの扱いはvsと同じで、ルートの配列の中はNoneにして . と表示する (search::Target::walkを参照)
+pub(crate) fn collect(value: &Value, path: Option<&str>, lines: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(map) => {
+            for (k, v) in map {
+                let child = path.map(|p| key_path(p, k, k));
+                collect(v, child.as_deref(), lines);
+            }
+        }
+        Value::Array(arr) => {
+            for (i, v) in arr.iter().enumerate() {
+                let child = match path {
+                    Some("") | None => None,
+                    Some(p) => Some(format!("{}[{}]", p, i)),
+                };
+                collect(v, child.as_deref(), lines);
+            }
+        }
+        v => {
+            let path = match path {
+                Some("") | None => ".".to_string(),
+                Some(p) => p.to_string(),
+            };
+            let text = match v {
+                v if secret::is_secret(v) => secret::MASKED_VALUE.to_string(),
+                Value::String(s) => s.clone(),
+                v => v.to_string(),
+            };
+            lines.push((path, text));
+        }
+    }
+}
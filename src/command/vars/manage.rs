@@ -0,0 +1,170 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+
+use super::{
+    env::{self, EnvFormat},
+    history, merge, schema,
+};
+use crate::{
+    config::AppConfig,
+    error::{self, Error, VarsStoreError},
+    output::{error_prefix, log_prefix},
+    store::{self, format::VarsFormat, merge::MergeStrategy},
+};
+
+// vars はvarsファイル全体を扱うコマンド
+//  vars log                    リビジョンの一覧を表示する
+//  vars diff <rev>             そのリビジョンで何が変わったかを表示する
+//  vars undo                   最後の変更を取り消す
+//  vars restore <rev> [query]  varsファイル全体、またはクエリで指定した場所をそのリビジョンの状態に戻す
+//  vars convert --to <format>  varsファイルをyamlやtomlで保存するように変換する
+//  vars export / import        環境変数やシェルのスニペットとして書き出す・取り込む (env.rsを参照)
+//  vars merge <source>         別のワークスペースやファイルのvarsを取り込む (merge.rsを参照)
+//  vars schema init / check    vmなどで書き込む前に検査するスキーマを作る・今の値を検査する (schema.rsを参照)
+#[derive(Parser, Debug)]
+struct VarsArgs {
+    #[command(subcommand)]
+    command: VarsCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum VarsCommand {
+    /// list recorded revisions of the vars file
+    Log,
+    /// show what changed in a revision
+    Diff { rev: u64 },
+    /// revert the latest change
+    Undo,
+    /// restore the whole vars file, or only the value at a query, from a revision
+    Restore { rev: u64, query: Option<String> },
+    /// print vars as environment variables, e.g. creds[0].user -> CREDS_0_USER
+    Export {
+        #[arg(long, value_enum, default_value = "sh")]
+        format: EnvFormat,
+        /// prepended to every variable name
+        #[arg(long, default_value = "")]
+        prefix: String,
+        /// write the snippet to a file instead of printing it
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        query: Option<String>,
+    },
+    /// store the vars file in another format (vars.json, vars.yaml or vars.toml)
+    Convert {
+        #[arg(long, value_enum)]
+        to: VarsFormat,
+    },
+    /// read vars from environment variables or a dotenv file
+    Import {
+        /// import environment variables starting with this prefix
+        #[arg(long, conflicts_with = "dotenv")]
+        from_env: Option<String>,
+        #[arg(long)]
+        dotenv: Option<PathBuf>,
+        /// only import dotenv keys starting with this prefix
+        #[arg(long, requires = "dotenv")]
+        prefix: Option<String>,
+        /// split names by `_` into nested keys, numbers become array indices
+        #[arg(long)]
+        split: bool,
+        /// put the imported values under this query
+        #[arg(long)]
+        into: Option<String>,
+    },
+    /// deep-merge the vars of another workspace or a json/yaml/toml file into the current vars
+    Merge {
+        /// a workspace name or a file path
+        source: String,
+        /// merge into the value at this query instead of the whole vars
+        #[arg(long)]
+        at: Option<String>,
+        /// what to do where both sides have different values
+        #[arg(long, value_enum, default_value = "keep")]
+        strategy: MergeStrategy,
+    },
+    /// manage .prail/vars.schema.json, which vm and others check before saving
+    Schema {
+        #[command(subcommand)]
+        command: SchemaCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum SchemaCommand {
+    /// write a schema template for ip, ports and creds
+    Init,
+    /// check the current vars against the schema
+    Check,
+}
+
+pub fn vars(args_: String, app_conf: &mut AppConfig) -> bool {
+    let mut args_iter = vec!["vars"];
+    args_iter.extend(&args_.split_whitespace().collect::<Vec<&str>>());
+    let args = match VarsArgs::try_parse_from(&args_iter) {
+        Ok(a) => a,
+        Err(e) => {
+            println!("{}", e);
+            return true;
+        }
+    };
+    if app_conf.dyn_conf.current_workspace.is_empty() {
+        println!("{} vars command error", error_prefix());
+        error::print_error(Error::VarsStoreError(VarsStoreError::NoCurrentWorkspace));
+        return true;
+    }
+    match args.command {
+        VarsCommand::Log => history::log(app_conf),
+        VarsCommand::Diff { rev } => history::show_diff(app_conf, rev),
+        VarsCommand::Undo => history::undo(app_conf),
+        VarsCommand::Restore { rev, query } => history::restore(app_conf, rev, query),
+        VarsCommand::Convert { to } => convert(app_conf, to),
+        VarsCommand::Export {
+            format,
+            prefix,
+            output,
+            query,
+        } => env::export(app_conf, format, prefix, output, query),
+        VarsCommand::Import {
+            from_env,
+            dotenv,
+            prefix,
+            split,
+            into,
+        } => env::import(app_conf, from_env, dotenv, prefix, split, into),
+        VarsCommand::Merge {
+            source,
+            at,
+            strategy,
+        } => merge::merge(app_conf, source, at, strategy),
+        VarsCommand::Schema { command } => match command {
+            SchemaCommand::Init => schema::init(app_conf),
+            SchemaCommand::Check => schema::check(app_conf),
+        },
+    }
+}
+
+fn convert(app_conf: &mut AppConfig, to: VarsFormat) -> bool {
+    let (path, rev) = match store::vars::convert(app_conf, to) {
+        Ok(r) => r,
+        Err(e) => {
+            println!("{} vars conversion error", error_prefix());
+            error::print_error(Error::VarsStoreError(e));
+            return true;
+        }
+    };
+    println!(
+        "{} converted the vars file to {}",
+        log_prefix(),
+        path.display()
+    );
+    // 変換は終わっているので、変更履歴に記録できなくても知らせるだけにする
+    if let Err(e) = rev {
+        println!(
+            "{} the vars were converted, but failed to record the revision",
+            error_prefix()
+        );
+        error::print_error(Error::VarsHistoryError(e));
+    }
+    false
+}
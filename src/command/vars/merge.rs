@@ -0,0 +1,142 @@
+use std::{fs, path::PathBuf};
+
+use serde_json::Value;
+
+use crate::{
+    config::{AppConfig, Workspace},
+    error::{self, Error, VarsMergeError},
+    json::{diff, error::JsonQueryError},
+    output::{blue, error_prefix, log_prefix},
+    store::{
+        self,
+        format::VarsFormat,
+        merge::{self, MergeStrategy},
+        secret,
+    },
+};
+
+// vars merge <workspace名|ファイル> [--at query] [--strategy overwrite|keep|append]
+// 別のワークスペースのvarsや、json/yaml/tomlのファイルを今のvarsに再帰的にマージする
+// 登録されているワークスペース名を優先するので、同じ名前のファイルは ./name のように指定する
+// 両方に別の値がある場所は衝突として表示し、--strategyに従ってどちらかを残す (既定はkeep)
+pub fn merge(
+    app_conf: &mut AppConfig,
+    source: String,
+    at: Option<String>,
+    strategy: MergeStrategy,
+) -> bool {
+    let err_msg = || {
+        println!("{} vars merge error", error_prefix());
+    };
+    let theirs = match read_source(app_conf, &source) {
+        Ok(v) => v,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::VarsMergeError(e));
+            return true;
+        }
+    };
+    let mut snapshot = match store::vars::load(app_conf) {
+        Ok(s) => s,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::VarsStoreError(e));
+            return true;
+        }
+    };
+    let query = at.clone().unwrap_or(".".to_string());
+    let before = snapshot.json.data.clone();
+    // マージ先にまだ値がなければ、取り込む値をそのまま置く
+    let merged = match snapshot.json.get(&query) {
+        Ok(ours) => {
+            let mut conflicts = vec![];
+            let path = if query == "." { "" } else { &query };
+            let merged = merge::deep_merge(&ours, &theirs, strategy, path, &mut conflicts);
+            for c in &conflicts {
+                print_conflict(c, strategy);
+            }
+            merged
+        }
+        Err(JsonQueryError::NotFound) => theirs,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::JsonQueryError(e));
+            return true;
+        }
+    };
+    match snapshot.json.set(&query, merged) {
+        Ok(_) => (),
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::JsonQueryError(e));
+            return true;
+        }
+    };
+    let changes = diff::diff(&before, &snapshot.json.data);
+    if changes.is_empty() {
+        println!("{} nothing changed", log_prefix());
+        return false;
+    }
+    for change in &changes {
+        super::history::print_change(change);
+    }
+    let mut summary = format!("vars merge {}", source);
+    if let Some(at) = at {
+        summary = format!("{} --at {}", summary, at);
+    }
+    match strategy {
+        MergeStrategy::Overwrite => summary.push_str(" --strategy overwrite"),
+        MergeStrategy::Append => summary.push_str(" --strategy append"),
+        MergeStrategy::Keep => (),
+    };
+    super::modify::save(app_conf, snapshot, &summary, err_msg)
+}
+
+fn read_source(app_conf: &AppConfig, source: &str) -> Result<Value, VarsMergeError> {
+    if let Some(path) = app_conf.dyn_conf.workspaces.get(source) {
+        let workspace = Workspace::assemble_struct(&PathBuf::from(path));
+        return match store::vars::read_workspace(&workspace) {
+            Ok(v) => Ok(v),
+            Err(e) => Err(VarsMergeError::ReadWorkspaceError(
+                source.to_string(),
+                Box::new(e),
+            )),
+        };
+    }
+    let path = PathBuf::from(source);
+    if !path.is_file() {
+        return Err(VarsMergeError::SourceNotFound(source.to_string()));
+    }
+    let content = match fs::read_to_string(&path) {
+        Ok(c) => c,
+        Err(e) => return Err(VarsMergeError::ReadFileError(e)),
+    };
+    // 拡張子がわからないファイルはjsonとして読む
+    let format = VarsFormat::from_extension(&path).unwrap_or(VarsFormat::Json);
+    match format.parse(&content) {
+        Ok(v) => Ok(v),
+        Err(e) => Err(VarsMergeError::ParseFileError(e)),
+    }
+}
+
+fn print_conflict(conflict: &merge::MergeConflict, strategy: MergeStrategy) {
+    let masked = |v: &Value| {
+        let mut v = v.clone();
+        secret::mask(&mut v);
+        v
+    };
+    let resolution = match strategy {
+        MergeStrategy::Overwrite => "took the merged value",
+        MergeStrategy::Keep | MergeStrategy::Append => "kept the current value",
+    };
+    println!(
+        "{}",
+        blue(&format!(
+            "! conflict at {}: {} (current {}, merged {})",
+            conflict.path,
+            resolution,
+            masked(&conflict.ours),
+            masked(&conflict.theirs)
+        ))
+    );
+}
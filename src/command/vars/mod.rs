@@ -1,12 +1,31 @@
-pub mod refer;
+pub mod delete;
+pub mod edit;
+pub mod env;
 pub mod help;
+pub mod history;
+pub mod interpolate;
+pub mod list;
+pub mod manage;
+pub mod merge;
 pub mod modify;
+pub mod refer;
+pub mod schema;
+pub mod search;
+pub mod secret;
 
 pub fn commands() -> Vec<crate::shell::command::Command> {
     vec![
         crate::shell::command::Command::new("v", Box::new(help::help1)),
         crate::shell::command::Command::new("vh", Box::new(help::help1)),
         crate::shell::command::Command::new("vr", Box::new(refer::refer)),
+        crate::shell::command::Command::new("vl", Box::new(list::list)),
         crate::shell::command::Command::new("vm", Box::new(modify::modify)),
+        crate::shell::command::Command::new("vd", Box::new(delete::delete)),
+        crate::shell::command::Command::new("ve", Box::new(edit::edit)),
+        crate::shell::command::Command::new("vs", Box::new(search::search)),
+        crate::shell::command::Command::new("vi", Box::new(interpolate::interpolate)),
+        crate::shell::command::Command::new("vars", Box::new(manage::vars)),
+        crate::shell::command::Command::new("unlock", Box::new(secret::unlock)),
+        crate::shell::command::Command::new("lock", Box::new(secret::lock)),
     ]
 }
@@ -1,3 +1,145 @@
-pub fn modify(args: String, app_conf: &mut crate::config::AppConfig) -> bool {
+use chrono::Utc;
+use clap::Parser;
+use serde_json::Value;
+
+use crate::{
+    error::{self, Error, SecretError},
+    json::json::split_args,
+    output::{error_prefix, log_prefix},
+    store::{self, secret, timeline, vars::SaveOutcome},
+};
+
+// vm (modify)ではjsonクエリで指定した場所に値を書き込む。
+// 値はスペースを含んでいてもよく、数値・真偽値・null・[a,b]形式の配列は自動で変換される
+// --secretを付けると値を暗号化した文字列として書き込む (unlockが必要)
+#[derive(Parser, Debug)]
+struct ModArgs {
+    #[arg(long)]
+    secret: bool,
+    path: String,
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    value: Vec<String>,
+}
+
+pub fn modify(args_: String, app_conf: &mut crate::config::AppConfig) -> bool {
+    let err_msg = || {
+        println!("{} vars modification error", error_prefix());
+    };
+    let mut args_iter = vec![""];
+    args_iter.extend(split_args(&args_));
+    let args = match ModArgs::try_parse_from(&args_iter) {
+        Ok(a) => a,
+        Err(e) => {
+            println!("{}", e);
+            return true;
+        }
+    };
+    let mut snapshot = match store::vars::load(app_conf) {
+        Ok(s) => s,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::VarsStoreError(e));
+            return true;
+        }
+    };
+    let value = args.value.join(" ");
+    let result = if args.secret {
+        let key = match &app_conf.secret_key {
+            Some(k) => k,
+            None => {
+                err_msg();
+                error::print_error(Error::SecretError(SecretError::Locked));
+                return true;
+            }
+        };
+        let encrypted = match secret::encrypt(key, &value) {
+            Ok(e) => e,
+            Err(e) => {
+                err_msg();
+                error::print_error(Error::SecretError(e));
+                return true;
+            }
+        };
+        snapshot.json.set(&args.path, Value::String(encrypted))
+    } else {
+        snapshot.json.modify(&args.path, &value)
+    };
+    match result {
+        Ok(_) => (),
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::JsonQueryError(e));
+            return true;
+        }
+    };
+    // 秘密の値は変更履歴の説明に残さない
+    let summary = if args.secret {
+        format!("vm --secret {}", args.path)
+    } else {
+        format!("vm {}", args_)
+    };
+    save(app_conf, snapshot, &summary, err_msg)
+}
+
+// 変更したvarsを書き込む。vm, vdなどの変更系のコマンドで共通
+pub fn save(
+    app_conf: &mut crate::config::AppConfig,
+    snapshot: store::vars::VarsSnapshot,
+    summary: &str,
+    err_msg: impl Fn(),
+) -> bool {
+    let saved = match store::vars::save(app_conf, snapshot, summary) {
+        Ok(s) => s,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::VarsStoreError(e));
+            return true;
+        }
+    };
+    if let SaveOutcome::Merged = saved.outcome {
+        println!(
+            "{} the vars file was changed by another process. the changes were merged",
+            log_prefix()
+        );
+    }
+    // varsファイルはもう書き込まれているので、ここから先は失敗しても知らせるだけにする
+    // エラーにすると、保存できていないと思ってもう一度同じ変更をしてしまう
+    let rev = match saved.rev {
+        Ok(r) => Some(r),
+        Err(e) => {
+            println!(
+                "{} the vars were saved, but failed to record the revision",
+                error_prefix()
+            );
+            error::print_error(Error::VarsHistoryError(e));
+            None
+        }
+    };
+    record(app_conf, summary, rev);
     false
-}
\ No newline at end of file
+}
+
+// varsの変更をリビジョンと一緒にtimelineに記録する
+fn record(app_conf: &crate::config::AppConfig, summary: &str, rev: Option<u64>) {
+    let entry = timeline::Entry {
+        time: Utc::now(),
+        kind: timeline::EntryKind::Vars,
+        command: summary.to_string(),
+        args: None,
+        duration_ms: None,
+        ok: None,
+        exit_code: None,
+        rev,
+    };
+    let path = app_conf.dyn_conf.to_workspace().timeline_path;
+    match timeline::append(&path, &entry) {
+        Ok(_) => (),
+        Err(e) => {
+            println!(
+                "{} the vars were saved, but failed to record them to the timeline",
+                error_prefix()
+            );
+            error::print_error(Error::TimelineError(e));
+        }
+    };
+}
@@ -1,19 +1,24 @@
-use std::io::{self, Read, Write};
-
 use clap::Parser;
+use serde_json::Value;
 
 use crate::{
-    json::{self, error::JsonQueryError},
-    output::{blue, error_prefix, green, log_prefix},
+    error::{self, Error},
+    json::{error::JsonQueryError, json::split_args},
+    output::{error_prefix, log_prefix},
+    store::{self, secret},
 };
 
 // vr (refer)では独自のjsonクエリで場所を指定してその場所にある値をプリントする。
 // copyフラグを追加することでその値を自動でクリップボードにコピーできる
+// 値の中の {{query}} は参照先の値で置き換えて表示する。--rawを付けると置き換える前のテンプレートを表示する
+// --rawを付けない場合は hosts["dc01"].ip のようにワークスペースのホストなども参照できる (store::viewを参照)
 #[derive(Parser, Debug)]
 struct RefArgs {
     path: String,
     #[arg(short)]
     copy: bool,
+    #[arg(long)]
+    raw: bool,
 }
 
 pub fn refer(args_: String, app_conf: &mut crate::config::AppConfig) -> bool {
@@ -21,39 +26,43 @@ pub fn refer(args_: String, app_conf: &mut crate::config::AppConfig) -> bool {
         println!("{} vars reference error", error_prefix());
     };
     let mut args_iter = vec![""];
-    args_iter.extend(&args_.split_whitespace().collect::<Vec<&str>>());
+    args_iter.extend(split_args(&args_));
     let args = match RefArgs::try_parse_from(&args_iter) {
         Ok(a) => a,
         Err(e) => {
-            println!("{}", e.to_string());
+            println!("{}", e);
             return true;
         }
     };
     let json_path = args.path;
-    let path = app_conf.dyn_conf.to_workspace().vars_path;
-    let file = match std::fs::File::open(&path) {
-        Ok(f) => f,
-        Err(e) => {
-            err_msg();
-            println!("{} failed to open the file", error_prefix(),);
-            println!("{} {}", error_prefix(), e.to_string());
-            return true;
-        }
-    };
-    let mut json_buf = String::new();
-    let mut reader = io::BufReader::new(file);
-    let _ = match reader.read_to_string(&mut json_buf) {
-        Ok(_) => (),
+    let mut snapshot = match store::vars::load(app_conf) {
+        Ok(s) => s,
         Err(e) => {
             err_msg();
-            println!("{} failed to read the file", error_prefix(),);
-            println!("{} {}", error_prefix(), e.to_string());
+            error::print_error(Error::VarsStoreError(e));
             return true;
         }
     };
 
-    let mut j = json::json::Json::new(&json_buf);
-    let result = match j.refer(&json_path) {
+    // hostsなどのワークスペースのデータも参照できるようにする
+    if !args.raw {
+        match store::view::attach(app_conf, &mut snapshot.json.data) {
+            Ok(_) => (),
+            Err(e) => {
+                err_msg();
+                error::print_error(Error::ViewError(e));
+                return true;
+            }
+        };
+    }
+    let key = app_conf.secret_key.as_ref();
+    let text = |v: &Value| secret::to_text(key, v);
+    let result = if args.raw {
+        snapshot.json.get(&json_path)
+    } else {
+        snapshot.json.refer(&json_path, &text)
+    };
+    let result = match result {
         Ok(r) => r,
         Err(e) => match e {
             JsonQueryError::NotFound => {
@@ -63,11 +72,37 @@ pub fn refer(args_: String, app_conf: &mut crate::config::AppConfig) -> bool {
             _ => {
                 err_msg();
                 println!("{} failed to refer the value", error_prefix(),);
-                println!("{} {}", error_prefix(), e.to_string());
+                println!("{} {}", error_prefix(), e);
                 return true;
             }
         },
     };
+    // 秘密の値は、それだけを指定したときにunlockされていれば復号して表示する
+    // オブジェクトや配列に含まれている秘密の値は常に <secret> で隠す
+    let result = match result {
+        v if secret::is_secret(&v) => match (&app_conf.secret_key, v.as_str()) {
+            (Some(key), Some(s)) => match secret::decrypt(key, s) {
+                Ok(plain) => plain,
+                Err(e) => {
+                    err_msg();
+                    error::print_error(Error::SecretError(e));
+                    return true;
+                }
+            },
+            _ => {
+                println!(
+                    "{} the value is a secret. run `unlock` to show it",
+                    log_prefix()
+                );
+                secret::MASKED_VALUE.to_string()
+            }
+        },
+        Value::String(s) => s,
+        mut v => {
+            secret::mask(&mut v);
+            v.to_string()
+        }
+    };
     println!("{}", result);
     false
 }
@@ -0,0 +1,83 @@
+use std::fs;
+
+use crate::{
+    config::{is_entry_exist, AppConfig},
+    error::{self, Error, VarsSchemaError},
+    output::{error_prefix, log_prefix},
+    store::{self, schema},
+};
+
+// vars schema init はよく使う変数の型を決めたテンプレートを .prail/vars.schema.json に書き出す
+// 既にある場合は上書きしない。必要に応じて手で書き換えて使う
+pub fn init(app_conf: &AppConfig) -> bool {
+    let err_msg = || {
+        println!("{} vars schema error", error_prefix());
+    };
+    let schema_path = app_conf.dyn_conf.to_workspace().schema_path;
+    match is_entry_exist(&schema_path) {
+        Ok(false) => (),
+        Ok(true) => {
+            err_msg();
+            error::print_error(Error::VarsSchemaError(VarsSchemaError::AlreadyExists(
+                schema_path,
+            )));
+            return true;
+        }
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::VarsSchemaError(VarsSchemaError::ReadError(e)));
+            return true;
+        }
+    };
+    match fs::write(&schema_path, schema::TEMPLATE) {
+        Ok(_) => (),
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::VarsSchemaError(VarsSchemaError::WriteError(e)));
+            return true;
+        }
+    };
+    println!("{} wrote {}", log_prefix(), schema_path.display());
+    false
+}
+
+// スキーマを後から置いた場合など、今の値が違反していないかを確かめる
+pub fn check(app_conf: &mut AppConfig) -> bool {
+    let err_msg = || {
+        println!("{} vars schema error", error_prefix());
+    };
+    let schema_path = app_conf.dyn_conf.to_workspace().schema_path;
+    match is_entry_exist(&schema_path) {
+        Ok(true) => (),
+        Ok(false) => {
+            println!(
+                "{} this workspace has no vars.schema.json. create it with `vars schema init`",
+                log_prefix()
+            );
+            return false;
+        }
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::VarsSchemaError(VarsSchemaError::ReadError(e)));
+            return true;
+        }
+    };
+    let snapshot = match store::vars::load(app_conf) {
+        Ok(s) => s,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::VarsStoreError(e));
+            return true;
+        }
+    };
+    match schema::validate(&schema_path, &snapshot.json.data) {
+        Ok(_) => (),
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::VarsSchemaError(e));
+            return true;
+        }
+    };
+    println!("{} the vars match vars.schema.json", log_prefix());
+    false
+}
@@ -0,0 +1,184 @@
+use clap::Parser;
+use regex::Regex;
+use serde_json::Value;
+
+use crate::{
+    error::{self, Error, VarsSearchError},
+    output::{error_prefix, log_prefix, red},
+    store::{self, secret},
+};
+
+// vs (search)ではvars全体からキーや値に文字列を含む場所を探し、vrで使えるクエリと値をプリントする
+// パターンは既定では文字列としてそのまま探し、--regexを付けると正規表現として扱う
+// 秘密の値は中身を探さずに <secret> と表示する
+#[derive(Parser, Debug)]
+struct SearchArgs {
+    #[arg(required = true)]
+    pattern: Vec<String>,
+    #[arg(long)]
+    regex: bool,
+    /// only search keys
+    #[arg(long, conflicts_with = "values")]
+    keys: bool,
+    /// only search values
+    #[arg(long)]
+    values: bool,
+}
+
+pub fn search(args_: String, app_conf: &mut crate::config::AppConfig) -> bool {
+    let err_msg = || {
+        println!("{} vars search error", error_prefix());
+    };
+    let mut args_iter = vec![""];
+    args_iter.extend(&args_.split_whitespace().collect::<Vec<&str>>());
+    let args = match SearchArgs::try_parse_from(&args_iter) {
+        Ok(a) => a,
+        Err(e) => {
+            println!("{}", e);
+            return true;
+        }
+    };
+    // スペースを含むパターンも書けるように、残りの引数はつなげて1つのパターンにする
+    let pattern = args.pattern.join(" ");
+    let pattern = if args.regex {
+        pattern
+    } else {
+        regex::escape(&pattern)
+    };
+    let re = match Regex::new(&pattern) {
+        Ok(r) => r,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::VarsSearchError(VarsSearchError::InvalidPattern(e)));
+            return true;
+        }
+    };
+    let snapshot = match store::vars::load(app_conf) {
+        Ok(s) => s,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::VarsStoreError(e));
+            return true;
+        }
+    };
+    let target = Target {
+        re,
+        keys: !args.values,
+        values: !args.keys,
+    };
+    let mut matches = vec![];
+    target.walk(&snapshot.json.data, Some(""), &mut matches);
+    for (path, value) in &matches {
+        println!("{} = {}", path, value);
+    }
+    if matches.is_empty() {
+        println!("{} no match", log_prefix());
+    }
+    false
+}
+
+pub(crate) struct Target {
+    pub(crate) re: Regex,
+    pub(crate) keys: bool,
+    pub(crate) values: bool,
+}
+
+impl Target {
+    // マッチした場所を (vrのクエリ, 表示する値) にしてmatchesに加える。クエリにはハイライトを含む
+    // pathはvalueを指すクエリで、Some("")はルート。ルートが配列だと要素を指すクエリは書けないので、その中はNoneにして . と表示する
+    pub(crate) fn walk(
+        &self,
+        value: &Value,
+        path: Option<&str>,
+        matches: &mut Vec<(String, String)>,
+    ) {
+        match value {
+            Value::Object(map) => {
+                for (k, v) in map {
+                    let child = path.map(|p| key_path(p, k, k));
+                    if self.keys && self.re.is_match(k) {
+                        let highlighted = match path {
+                            Some(p) => key_path(p, k, &self.highlight(k)),
+                            None => ".".to_string(),
+                        };
+                        matches.push((highlighted, display(v)));
+                    }
+                    self.walk(v, child.as_deref(), matches);
+                }
+            }
+            Value::Array(arr) => {
+                for (i, v) in arr.iter().enumerate() {
+                    let child = match path {
+                        Some("") | None => None,
+                        Some(p) => Some(format!("{}[{}]", p, i)),
+                    };
+                    self.walk(v, child.as_deref(), matches);
+                }
+            }
+            v if secret::is_secret(v) => (),
+            v => {
+                if !self.values {
+                    return;
+                }
+                let text = match v {
+                    Value::String(s) => s.clone(),
+                    v => v.to_string(),
+                };
+                if self.re.is_match(&text) {
+                    let path = match path {
+                        Some("") | None => ".",
+                        Some(p) => p,
+                    };
+                    matches.push((path.to_string(), self.highlight(&text)));
+                }
+            }
+        }
+    }
+
+    fn highlight(&self, text: &str) -> String {
+        highlight(&self.re, text)
+    }
+}
+
+// pathの下のキーkeyを指すクエリ。shownはクエリに書くキー (ハイライトしたもの)
+// . や空白、括弧を含むキーはvrでそのまま書けないので ["..."] で書く (json::split_queryを参照)
+pub(crate) fn key_path(path: &str, key: &str, shown: &str) -> String {
+    let quote = key.is_empty() || key.contains(['.', ' ', '[', ']']);
+    if quote {
+        format!("{}[\"{}\"]", path, shown)
+    } else if path.is_empty() {
+        shown.to_string()
+    } else {
+        format!("{}.{}", path, shown)
+    }
+}
+
+// マッチした部分を赤くする
+pub fn highlight(re: &Regex, text: &str) -> String {
+    let mut result = String::new();
+    let mut last = 0;
+    for m in re.find_iter(text) {
+        // 空文字列にマッチした場所は色を付けても見えないので飛ばす
+        if m.as_str().is_empty() {
+            continue;
+        }
+        result.push_str(&text[last..m.start()]);
+        result.push_str(&red(m.as_str()));
+        last = m.end();
+    }
+    result.push_str(&text[last..]);
+    result
+}
+
+// キーにマッチしたときに表示する値。オブジェクトや配列の中の秘密の値は隠す
+fn display(value: &Value) -> String {
+    match value {
+        v if secret::is_secret(v) => secret::MASKED_VALUE.to_string(),
+        Value::String(s) => s.clone(),
+        v => {
+            let mut v = v.clone();
+            secret::mask(&mut v);
+            v.to_string()
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use crate::{
+    error::{self, Error},
+    output::{error_prefix, log_prefix},
+    store::secret,
+};
+
+// unlockは秘密の値(vm --secret)の鍵を読み込み、vrで復号して表示できるようにする
+// 鍵はこのシェルのメモリ上にだけ置かれ、lockかシェルの終了で消える
+pub fn unlock(_args: String, app_conf: &mut crate::config::AppConfig) -> bool {
+    if app_conf.secret_key.is_some() {
+        println!("{} secrets are already unlocked", log_prefix());
+        return false;
+    }
+    match secret::unlock(&app_conf.app_conf_path) {
+        Ok(key) => app_conf.secret_key = Some(key),
+        Err(e) => {
+            println!("{} failed to unlock secrets", error_prefix());
+            error::print_error(Error::SecretError(e));
+            return true;
+        }
+    };
+    println!("{} secrets are unlocked", log_prefix());
+    false
+}
+
+pub fn lock(_args: String, app_conf: &mut crate::config::AppConfig) -> bool {
+    // dropされたときに鍵はゼロで上書きされる
+    app_conf.secret_key = None;
+    println!("{} secrets are locked", log_prefix());
+    false
+}
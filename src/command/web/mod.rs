@@ -0,0 +1,41 @@
+use clap::{Parser, Subcommand};
+
+pub mod paths;
+
+pub fn commands() -> Vec<crate::shell::command::Command> {
+    vec![crate::shell::command::Command::new("web", Box::new(web))]
+}
+
+// web はホストのWebサービスで見つけたものを扱うコマンド
+//  web paths <host> [--status N]    import ffuf などで取り込んだパスの一覧を表示する
+#[derive(Parser, Debug)]
+struct WebArgs {
+    #[command(subcommand)]
+    command: WebCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum WebCommand {
+    /// list urls discovered on a host
+    Paths {
+        host: String,
+        /// only list urls which returned this status code. can be given multiple times
+        #[arg(long)]
+        status: Vec<u16>,
+    },
+}
+
+fn web(args_: String, app_conf: &mut crate::config::AppConfig) -> bool {
+    let mut args_iter = vec!["web"];
+    args_iter.extend(&args_.split_whitespace().collect::<Vec<&str>>());
+    let args = match WebArgs::try_parse_from(&args_iter) {
+        Ok(a) => a,
+        Err(e) => {
+            println!("{}", e);
+            return true;
+        }
+    };
+    match args.command {
+        WebCommand::Paths { host, status } => paths::list(app_conf, &host, status),
+    }
+}
@@ -0,0 +1,46 @@
+use crate::{
+    command::import::web::describe,
+    config::AppConfig,
+    error::{self, Error, HostsError},
+    output::{error_prefix, log_prefix},
+    store::hosts,
+};
+
+pub fn list(app_conf: &AppConfig, host: &str, status: Vec<u16>) -> bool {
+    let err_msg = || {
+        println!("{} web command error", error_prefix());
+    };
+    let hosts = match hosts::load(app_conf) {
+        Ok(h) => h,
+        Err(e) => {
+            err_msg();
+            error::print_error(Error::HostsError(e));
+            return true;
+        }
+    };
+    // ホストの名前のほかに、アドレスやホスト名でも指定できる
+    let h = match hosts::resolve(&hosts, host).and_then(|n| hosts.get(&n)) {
+        Some(h) => h,
+        None => {
+            err_msg();
+            error::print_error(Error::HostsError(HostsError::HostNotFound(
+                host.to_string(),
+            )));
+            return true;
+        }
+    };
+    let mut count = 0;
+    for service in &h.services {
+        for p in &service.paths {
+            if !status.is_empty() && !status.contains(&p.status) {
+                continue;
+            }
+            println!("{}", describe(p));
+            count += 1;
+        }
+    }
+    if count == 0 {
+        println!("{} no path is registered on {}", log_prefix(), host);
+    }
+    false
+}
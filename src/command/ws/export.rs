@@ -0,0 +1,261 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use chrono::Utc;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use super::{ArchiveManifest, ARCHIVE_FORMAT_VERSION, MANIFEST_FILE_NAME};
+use crate::{
+    config::{AppConfig, Workspace},
+    error::{self, Error, TimelineError, WsExportError},
+    output::{error_prefix, log_prefix},
+    store::{
+        secret,
+        timeline::{self, EntryKind},
+    },
+};
+
+const STRIPPED_VALUE: &str = "<stripped>";
+
+pub fn export(app_conf: &AppConfig, file: &PathBuf, with_files: bool, strip_secrets: bool) -> bool {
+    match export_(app_conf, file, with_files, strip_secrets) {
+        Ok(n) => println!(
+            "{} exported {} files to {}",
+            log_prefix(),
+            n,
+            file.display()
+        ),
+        Err(e) => {
+            println!("{} failed to export the workspace", error_prefix());
+            error::print_error(Error::WsExportError(e));
+            return true;
+        }
+    };
+    false
+}
+
+fn export_(
+    app_conf: &AppConfig,
+    file: &PathBuf,
+    with_files: bool,
+    strip_secrets: bool,
+) -> Result<usize, WsExportError> {
+    if app_conf.dyn_conf.current_workspace.is_empty() {
+        return Err(WsExportError::NoCurrentWorkspace);
+    }
+    let root = PathBuf::from(&app_conf.dyn_conf.current_workspace);
+    let workspace = Workspace::assemble_struct(&root);
+
+    // アーカイブに入れるファイル (ワークスペースのルートからの相対パス -> 内容)
+    let mut entries = BTreeMap::<String, Vec<u8>>::new();
+    match collect_dir(&root, &workspace.mgr_path, &mut entries) {
+        Ok(_) => (),
+        Err(e) => return Err(WsExportError::ReadError(e)),
+    };
+
+    let vars_key = relative_name(&root, &workspace.vars_path);
+    let vars = match entries.get(&vars_key) {
+        Some(content) => match workspace
+            .vars_format
+            .parse(&String::from_utf8_lossy(content))
+        {
+            Ok(v) => Some(v),
+            Err(e) => return Err(WsExportError::VarsFormatError(e)),
+        },
+        None => None,
+    };
+
+    if let Some(vars) = &vars {
+        // varsから参照されているワークスペース内のファイルを追加する
+        if with_files {
+            for path in referenced_files(&root, vars) {
+                let content = match fs::read(&path) {
+                    Ok(c) => c,
+                    Err(e) => return Err(WsExportError::ReadError(e)),
+                };
+                entries.insert(relative_name(&root, &path), content);
+            }
+        }
+        if strip_secrets {
+            // 変更履歴には消す前の値が残っているので、履歴ごと入れない
+            let history_key = relative_name(&root, &workspace.history_path);
+            entries.retain(|name, _| !Path::new(name).starts_with(&history_key));
+            let mut stripped = vars.clone();
+            secret::strip_secret_keys(&mut stripped, STRIPPED_VALUE);
+            let content = match workspace.vars_format.serialize(&stripped) {
+                Ok(c) => c,
+                Err(e) => return Err(WsExportError::VarsFormatError(e)),
+            };
+            entries.insert(vars_key, content);
+        }
+    }
+
+    // creds.jsonの値も同じように消す
+    let creds_key = relative_name(&root, &workspace.creds_path);
+    if strip_secrets {
+        if let Some(content) = entries.get(&creds_key) {
+            let mut creds = match serde_json::from_slice::<Value>(content) {
+                Ok(c) => c,
+                Err(e) => return Err(WsExportError::ParseCredsError(e)),
+            };
+            secret::strip_secret_keys(&mut creds, STRIPPED_VALUE);
+            let content = match serde_json::to_vec_pretty(&creds) {
+                Ok(c) => c,
+                Err(e) => return Err(WsExportError::ParseCredsError(e)),
+            };
+            entries.insert(creds_key, content);
+        }
+        // 持ち出したファイルや証拠のファイルには鍵やパスワードが含まれていることが多いので入れない
+        // 一覧 (loot.json, findings.jsonのevidence) は残す
+        let loot_key = relative_name(&root, &workspace.loot_path);
+        let evidence_key = relative_name(&root, &workspace.evidence_path);
+        entries.retain(|name, _| {
+            !Path::new(name).starts_with(&loot_key) && !Path::new(name).starts_with(&evidence_key)
+        });
+        // timelineのコマンドの引数も伏せる
+        let timeline_key = relative_name(&root, &workspace.timeline_path);
+        if let Some(content) = entries.get_mut(&timeline_key) {
+            *content = match redact_timeline(&workspace.timeline_path) {
+                Ok(c) => c,
+                Err(e) => return Err(WsExportError::TimelineError(e)),
+            };
+        }
+    }
+
+    let manifest = ArchiveManifest {
+        format_version: ARCHIVE_FORMAT_VERSION,
+        pwnrail_version: env!("CARGO_PKG_VERSION").to_string(),
+        exported_at: Utc::now(),
+        workspace_name: root
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "workspace".to_string()),
+        secrets_stripped: strip_secrets,
+        files: entries
+            .iter()
+            .map(|(name, content)| (name.clone(), hex::encode(Sha256::digest(content))))
+            .collect(),
+    };
+    let manifest = match serde_json::to_vec_pretty(&manifest) {
+        Ok(m) => m,
+        Err(e) => return Err(WsExportError::SerializeManifestError(e)),
+    };
+
+    let archive = match fs::File::create(file) {
+        Ok(f) => f,
+        Err(e) => return Err(WsExportError::CreateArchiveError(e)),
+    };
+    match write_archive(archive, &manifest, &entries) {
+        Ok(_) => (),
+        Err(e) => return Err(WsExportError::WriteArchiveError(e)),
+    };
+    Ok(entries.len())
+}
+
+// timelineの引数を伏せたものを返す
+// pwnrailのコマンドは記録するときと同じように伏せる (伏せるようになる前に記録されたものもあるので、もう一度伏せる)
+// 外部コマンドは値がどの引数にあるか分からないので、引数をすべて消す
+fn redact_timeline(path: &Path) -> Result<Vec<u8>, TimelineError> {
+    let mut content = vec![];
+    for mut entry in timeline::read(path)? {
+        entry.args = match (entry.kind, entry.args) {
+            (_, None) => None,
+            (EntryKind::External, Some(_)) => Some(STRIPPED_VALUE.to_string()),
+            (_, Some(a)) => Some(timeline::redact(&entry.command, &a)),
+        };
+        let line = match serde_json::to_string(&entry) {
+            Ok(l) => l,
+            Err(e) => return Err(TimelineError::SerializeError(e)),
+        };
+        content.extend(format!("{}\n", line).into_bytes());
+    }
+    Ok(content)
+}
+
+fn write_archive(
+    archive: fs::File,
+    manifest: &[u8],
+    entries: &BTreeMap<String, Vec<u8>>,
+) -> Result<(), std::io::Error> {
+    let encoder = zstd::Encoder::new(archive, 0)?;
+    let mut builder = tar::Builder::new(encoder);
+    let mut append = |name: &str, content: &[u8]| {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o600);
+        header.set_mtime(Utc::now().timestamp() as u64);
+        header.set_cksum();
+        builder.append_data(&mut header, name, content)
+    };
+    // マニフェストは先頭に置く
+    append(MANIFEST_FILE_NAME, manifest)?;
+    for (name, content) in entries {
+        append(name, content)?;
+    }
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
+
+fn collect_dir(
+    root: &Path,
+    dir: &Path,
+    entries: &mut BTreeMap<String, Vec<u8>>,
+) -> Result<(), std::io::Error> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            collect_dir(root, &path, entries)?;
+        } else if entry.file_type()?.is_file() && !is_transient(&path) {
+            entries.insert(relative_name(root, &path), fs::read(&path)?);
+        }
+    }
+    Ok(())
+}
+
+// ロックファイルや書き込み途中の一時ファイルはアーカイブに入れない
+fn is_transient(path: &Path) -> bool {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    name.ends_with(".lock") || name.contains(".tmp-")
+}
+
+fn relative_name(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .to_string()
+}
+
+// varsの文字列の値のうち、ワークスペース内の(管理ディレクトリ以外の)ファイルを指しているものを集める
+fn referenced_files(root: &Path, vars: &Value) -> Vec<PathBuf> {
+    let mut files = vec![];
+    let root = match root.canonicalize() {
+        Ok(r) => r,
+        Err(_) => return files,
+    };
+    let mgr = Workspace::assemble_struct(&root).mgr_path;
+    let mut stack = vec![vars];
+    while let Some(v) = stack.pop() {
+        match v {
+            Value::String(s) => {
+                if let Ok(path) = root.join(s).canonicalize() {
+                    if path.is_file()
+                        && path.starts_with(&root)
+                        && !path.starts_with(&mgr)
+                        && !files.contains(&path)
+                    {
+                        files.push(path);
+                    }
+                }
+            }
+            Value::Array(arr) => stack.extend(arr.iter()),
+            Value::Object(map) => stack.extend(map.values()),
+            _ => (),
+        }
+    }
+    files
+}
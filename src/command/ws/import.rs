@@ -0,0 +1,185 @@
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    io::{self, Read},
+    path::{Component, Path, PathBuf},
+};
+
+use sha2::{Digest, Sha256};
+
+use super::{ArchiveManifest, ARCHIVE_FORMAT_VERSION, MANIFEST_FILE_NAME};
+use crate::{
+    config::{is_entry_exist, AppConfig, Workspace},
+    error::{self, Error, WsImportError},
+    migration,
+    output::{error_prefix, log_prefix},
+};
+
+pub fn import(app_conf: &mut AppConfig, file: &PathBuf, dir: Option<PathBuf>) -> bool {
+    match import_(app_conf, file, dir) {
+        Ok((name, path)) => println!(
+            "{} imported workspace {} into {}",
+            log_prefix(),
+            name,
+            path.display()
+        ),
+        Err(e) => {
+            println!("{} failed to import the workspace", error_prefix());
+            error::print_error(Error::WsImportError(e));
+            return true;
+        }
+    };
+    false
+}
+
+fn import_(
+    app_conf: &mut AppConfig,
+    file: &PathBuf,
+    dir: Option<PathBuf>,
+) -> Result<(String, PathBuf), WsImportError> {
+    // 1回目の読み込みでマニフェストとハッシュを検証し、問題がなければ2回目の読み込みで展開する
+    let manifest = verify(file)?;
+
+    let target = match dir {
+        Some(d) => d,
+        None => match env::current_dir() {
+            Ok(c) => c.join(&manifest.workspace_name),
+            Err(e) => return Err(WsImportError::GetCurrentDirError(e)),
+        },
+    };
+    let workspace = Workspace::assemble_struct(&target);
+    if match is_entry_exist(&workspace.mgr_path) {
+        Ok(b) => b,
+        Err(e) => return Err(WsImportError::ExtractError(e)),
+    } {
+        return Err(WsImportError::TargetAlreadyExists(target));
+    }
+
+    match extract(file, &target) {
+        Ok(_) => (),
+        Err(e) => return Err(WsImportError::ExtractError(e)),
+    };
+    // 古いpwnrailでexportされたワークスペースかもしれないので、マイグレーションしておく
+    match migration::migrate(&workspace, false) {
+        Ok(_) => (),
+        Err(e) => return Err(WsImportError::MigrateWorkspaceError(e)),
+    };
+
+    let target = target.canonicalize().unwrap_or(target);
+    let name = app_conf.dyn_conf.register_workspace(&target);
+    match app_conf.update_dyn_conf_file() {
+        Ok(_) => (),
+        Err(e) => return Err(WsImportError::UpdateDynConfFileError(e)),
+    };
+    if manifest.secrets_stripped {
+        println!(
+            "{} secrets were stripped from this archive before export",
+            log_prefix()
+        );
+    }
+    Ok((name, target))
+}
+
+fn open(file: &PathBuf) -> Result<tar::Archive<impl Read>, WsImportError> {
+    let f = match fs::File::open(file) {
+        Ok(f) => f,
+        Err(e) => return Err(WsImportError::OpenArchiveError(e)),
+    };
+    match zstd::Decoder::new(f) {
+        Ok(d) => Ok(tar::Archive::new(d)),
+        Err(e) => Err(WsImportError::OpenArchiveError(e)),
+    }
+}
+
+pub(crate) fn verify(file: &PathBuf) -> Result<ArchiveManifest, WsImportError> {
+    let mut archive = open(file)?;
+    let mut manifest = None;
+    let mut hashes = BTreeMap::<String, String>::new();
+
+    let entries = match archive.entries() {
+        Ok(e) => e,
+        Err(e) => return Err(WsImportError::ReadArchiveError(e)),
+    };
+    for entry in entries {
+        let mut entry = match entry {
+            Ok(e) => e,
+            Err(e) => return Err(WsImportError::ReadArchiveError(e)),
+        };
+        let path = match entry.path() {
+            Ok(p) => p.to_path_buf(),
+            Err(e) => return Err(WsImportError::ReadArchiveError(e)),
+        };
+        if !is_safe_path(&path) {
+            return Err(WsImportError::UnsafePath(path));
+        }
+        let mut content = vec![];
+        match entry.read_to_end(&mut content) {
+            Ok(_) => (),
+            Err(e) => return Err(WsImportError::ReadArchiveError(e)),
+        };
+        let name = path.to_string_lossy().to_string();
+        if name == MANIFEST_FILE_NAME {
+            manifest = match serde_json::from_slice::<ArchiveManifest>(&content) {
+                Ok(m) => Some(m),
+                Err(e) => return Err(WsImportError::ParseManifestError(e)),
+            };
+        } else {
+            hashes.insert(name, hex::encode(Sha256::digest(&content)));
+        }
+    }
+
+    let manifest = match manifest {
+        Some(m) => m,
+        None => return Err(WsImportError::ManifestNotFound),
+    };
+    // ワークスペース名は展開先のディレクトリ名に使うので、../x や絶対パスで外に書き込めないようにする
+    if !is_safe_workspace_name(&manifest.workspace_name) {
+        return Err(WsImportError::UnsafeWorkspaceName(manifest.workspace_name));
+    }
+    if manifest.format_version > ARCHIVE_FORMAT_VERSION {
+        return Err(WsImportError::TooNewFormat(manifest.format_version));
+    }
+    for (name, hash) in &manifest.files {
+        match hashes.remove(name) {
+            Some(h) if h == *hash => (),
+            Some(_) => return Err(WsImportError::HashMismatch(name.clone())),
+            None => return Err(WsImportError::MissingFile(name.clone())),
+        }
+    }
+    // マニフェストに載っていないファイルが紛れ込んでいる場合も拒否する
+    if let Some(name) = hashes.into_keys().next() {
+        return Err(WsImportError::UnexpectedFile(name));
+    }
+    Ok(manifest)
+}
+
+fn extract(file: &PathBuf, target: &PathBuf) -> Result<(), io::Error> {
+    let mut archive = match open(file) {
+        Ok(a) => a,
+        Err(_) => return Err(io::Error::other("failed to reopen the archive")),
+    };
+    fs::create_dir_all(target)?;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if entry.path()?.as_os_str() == MANIFEST_FILE_NAME {
+            continue;
+        }
+        entry.unpack_in(target)?;
+    }
+    Ok(())
+}
+
+// 絶対パスや .. を含むパスは展開先の外に書き込めてしまうので拒否する
+fn is_safe_path(path: &Path) -> bool {
+    path.components().all(|c| matches!(c, Component::Normal(_)))
+}
+
+// 区切り文字を含まない、. でも .. でもない1つの名前だけを許す
+fn is_safe_workspace_name(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+    !name.contains(['/', '\\'])
+        && matches!(
+            (components.next(), components.next()),
+            (Some(Component::Normal(_)), None)
+        )
+}
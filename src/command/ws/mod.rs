@@ -0,0 +1,96 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+
+use crate::output::log_prefix;
+
+pub mod export;
+pub mod import;
+
+pub fn commands() -> Vec<crate::shell::command::Command> {
+    vec![crate::shell::command::Command::new("ws", Box::new(ws))]
+}
+
+// ws はワークスペース全体を扱うコマンド
+//  ws export <file.tar.zst>    現在のワークスペースの管理ディレクトリをアーカイブにまとめる
+//  ws import <file.tar.zst>    アーカイブを展開してワークスペースとして登録する
+//  ws ls                       登録されているワークスペースの一覧を表示する
+#[derive(Parser, Debug)]
+struct WsArgs {
+    #[command(subcommand)]
+    command: WsCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum WsCommand {
+    /// bundle the current workspace into a .tar.zst archive
+    Export {
+        file: PathBuf,
+        /// also bundle files in the workspace that are referenced from vars
+        #[arg(long)]
+        with_files: bool,
+        /// replace values of secret-looking keys (password, hash, token...) in vars and creds,
+        /// redact command arguments in the timeline and leave out the vars history, loot and evidence files.
+        /// notes and finding descriptions are exported as written
+        #[arg(long)]
+        strip_secrets: bool,
+    },
+    /// unpack an archive made by `ws export` and register it as a workspace
+    Import {
+        file: PathBuf,
+        /// directory to unpack into (default: ./<workspace name>)
+        #[arg(long)]
+        dir: Option<PathBuf>,
+    },
+    /// list registered workspaces
+    Ls,
+}
+
+fn ws(args_: String, app_conf: &mut crate::config::AppConfig) -> bool {
+    let mut args_iter = vec!["ws"];
+    args_iter.extend(&args_.split_whitespace().collect::<Vec<&str>>());
+    let args = match WsArgs::try_parse_from(&args_iter) {
+        Ok(a) => a,
+        Err(e) => {
+            println!("{}", e);
+            return true;
+        }
+    };
+    match args.command {
+        WsCommand::Export {
+            file,
+            with_files,
+            strip_secrets,
+        } => export::export(app_conf, &file, with_files, strip_secrets),
+        WsCommand::Import { file, dir } => import::import(app_conf, &file, dir),
+        WsCommand::Ls => {
+            if app_conf.dyn_conf.workspaces.is_empty() {
+                println!("{} no workspace is registered", log_prefix());
+            }
+            for (name, path) in &app_conf.dyn_conf.workspaces {
+                let mark = if *path == app_conf.dyn_conf.current_workspace {
+                    "*"
+                } else {
+                    " "
+                };
+                println!("{} {}\t{}", mark, name, path);
+            }
+            false
+        }
+    }
+}
+
+// アーカイブの先頭に置くマニフェスト
+// filesにはアーカイブ内の各ファイルのパスとSHA-256を記録し、import時に改ざんや破損がないか検証する
+#[derive(serde::Deserialize, serde::Serialize, Debug)]
+pub struct ArchiveManifest {
+    pub format_version: u32,
+    pub pwnrail_version: String,
+    pub exported_at: chrono::DateTime<chrono::Utc>,
+    pub workspace_name: String,
+    pub secrets_stripped: bool,
+    pub files: std::collections::BTreeMap<String, String>,
+}
+
+pub const MANIFEST_FILE_NAME: &str = "pwnrail-manifest.json";
+pub const ARCHIVE_FORMAT_VERSION: u32 = 1;
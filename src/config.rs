@@ -1,73 +1,107 @@
 use std::{
+    collections::BTreeMap,
     env, fs,
     io::{self, Read, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use crate::{
-    command::vars,
     error::{
         AppConfigError, AppInitError, CreateNewWorkspaceError, DynConfInitError,
-        InitCurrentDirAsWorkspaceError, ReadDynConfError, ShellHistInitError,
-        UpdateDynConfFileError, UseCurrentDirAsWorkspaceError,
+        InitCurrentDirAsWorkspaceError, ReadDynConfError, ReadWorkspaceMetaError,
+        ShellHistInitError, UpdateDynConfFileError, UseCurrentDirAsWorkspaceError,
+        WriteWorkspaceMetaError,
+    },
+    migration,
+    output::log_prefix,
+    store::{
+        format::{self, VarsFormat},
+        lock, schema,
+        secret::SecretKey,
     },
 };
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 const APP_CONFIG_DIR_NAME: &str = ".pwnrail";
+// XDG base directory を使う場合のディレクトリ名 ($XDG_CONFIG_HOME/pwnrail, $XDG_STATE_HOME/pwnrail)
+const XDG_APP_DIR_NAME: &str = "pwnrail";
+const APP_HOME_ENV: &str = "PWNRAIL_HOME";
+const XDG_CONFIG_HOME_ENV: &str = "XDG_CONFIG_HOME";
+const XDG_STATE_HOME_ENV: &str = "XDG_STATE_HOME";
 const DYNAMIC_CONFIG_FILE_NAME: &str = "dynamic_config.toml";
 const SHELL_HISTORY_FILE_NAME: &str = "shell_history";
 
 const WORKSPACE_DIR_NAME: &str = ".prail";
-const VARS_FILE_NAME: &str = "vars.json";
+const META_FILE_NAME: &str = "meta.toml";
+const HISTORY_DIR_NAME: &str = "history";
+const HOSTS_FILE_NAME: &str = "hosts.json";
+const CREDS_FILE_NAME: &str = "creds.json";
+const NOTES_DIR_NAME: &str = "notes";
+const TIMELINE_FILE_NAME: &str = "timeline.jsonl";
+const FINDINGS_FILE_NAME: &str = "findings.json";
+const EVIDENCE_DIR_NAME: &str = "evidence";
+const FLAGS_FILE_NAME: &str = "flags.json";
+const LOOT_INDEX_FILE_NAME: &str = "loot.json";
+const LOOT_DIR_NAME: &str = "loot";
 
 pub struct AppConfig {
     pub app_conf_path: PathBuf,
     pub dyn_conf_path: PathBuf,
     pub shell_hist_path: PathBuf,
     pub dyn_conf: DynamicConfig,
+    // このプロセスが最後に読み書きしたvarsファイルのハッシュ
+    // 他のプロセスがvarsファイルを変更したことをプロンプトで通知するために使う
+    // varsファイルの監視(store::watch)からも参照するので共有できるようにしておく
+    pub vars_fingerprint: Arc<Mutex<Option<String>>>,
+    // unlockで読み込んだ秘密の値の鍵。lockするとNoneに戻して鍵を消す
+    pub secret_key: Option<SecretKey>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
 pub struct DynamicConfig {
     pub current_workspace: String,
+    // 登録されているワークスペースの一覧 (名前 -> パス)
+    // initやuseをしたワークスペース、ws importで展開したワークスペースが登録される
+    #[serde(default)]
+    pub workspaces: BTreeMap<String, String>,
 }
 
 impl AppConfig {
-    // ~/.pwnrail
+    // ~/.pwnrail (app configディレクトリ)
     //  |   dynamic_config.toml     現在の作業ディレクトリなど、アプリケーションの実行途中に動的に変更される設定
     //  |                           初期化では初期設定が書き込まれる。アプリケーションが起動したタイミングでAppConfigに読みこまれる
     //  |                           起動時の読み込みに失敗すると、シェルは起動せずに終了する。
     //  |                           このとき、ユーザーはこのファイルを編集して、起動可能な状態にするか、再度初期化をする。
     //  |   shell_history           インタラクティブシェルのコマンド履歴ファイル。初期状態では空で、あとからshellによって使用される
-    pub fn new() -> Result<Self, AppConfigError> {
-        let home_path = match home::home_dir() {
-            Some(dir) => dir,
-            None => panic!("home directory is not found"),
-        };
-        // ~/.pwnrail
-        let app_conf_path = home_path.join(APP_CONFIG_DIR_NAME);
-        // ~/.pwnrail/dynamic_config.toml
-        let dyn_conf_path = app_conf_path.join(DYNAMIC_CONFIG_FILE_NAME);
-        // ~/.pwnrail/shell_history
-        let shell_hist_path = app_conf_path.join(SHELL_HISTORY_FILE_NAME);
+    //
+    // app configディレクトリの場所は次の順番で決める
+    //  1. --config-dir で指定されたディレクトリ
+    //  2. 環境変数 PWNRAIL_HOME
+    //  3. ~/.pwnrail が既に存在していればそれを使う
+    //  4. 環境変数 XDG_CONFIG_HOME が設定されていれば $XDG_CONFIG_HOME/pwnrail
+    //  5. ~/.pwnrail
+    // 1, 2 で指定されたときは、プロファイルを分離できるようにshell_historyもそのディレクトリに置く。
+    // それ以外で XDG_STATE_HOME が設定されていれば、shell_historyは $XDG_STATE_HOME/pwnrail/shell_history に置く
+    //
+    // 起動時には足りないファイルを作り直し、パースできないdynamic_config.tomlはバックアップしてから初期設定で作り直す
+    pub fn new(config_dir: Option<PathBuf>) -> Result<Self, AppConfigError> {
+        let paths = AppPaths::resolve(config_dir)?;
 
-        let dyn_conf = match app_init(&app_conf_path, &dyn_conf_path, &shell_hist_path) {
+        let dyn_conf = match app_init(&paths) {
             Ok(dc) => dc,
-            Err(e) => match e {
-                AppInitError::InitAlreadyDone => match read_dyn_conf(&dyn_conf_path) {
-                    Ok(conf) => conf,
-                    Err(e) => return Err(AppConfigError::ReadDynConfError(e)),
-                },
-                _ => return Err(AppConfigError::AppInitError(e)),
-            },
+            Err(e) => return Err(AppConfigError::AppInitError(e)),
         };
 
         Ok(Self {
-            app_conf_path,
-            dyn_conf_path,
-            shell_hist_path,
+            app_conf_path: paths.app_conf_path,
+            dyn_conf_path: paths.dyn_conf_path,
+            shell_hist_path: paths.shell_hist_path,
             dyn_conf,
+            vars_fingerprint: Arc::new(Mutex::new(None)),
+            secret_key: None,
         })
     }
 
@@ -83,12 +117,12 @@ impl AppConfig {
         // 作成するワークスペースディレクトリの構造を取得
         let workspace_struct = Workspace::assemble_struct(&current_dir_path);
         // ワークスペース管理ディレクトリ・ファイル群を作成
-        let _ = match workspace_struct.create() {
+        match workspace_struct.create() {
             Ok(_) => (),
             Err(e) => return Err(InitCurrentDirAsWorkspaceError::CreateNewWorkspaceError(e)),
         };
         // 初期化したら、自動で初期化したディレクトリをワークスペースに設定するようにする
-        let _ = match self.use_current_dir_as_workspace() {
+        match self.use_current_dir_as_workspace(false) {
             Ok(_) => (),
             Err(e) => return Err(InitCurrentDirAsWorkspaceError::UseCurrentDirAsWorkspaceError(e)),
         };
@@ -96,7 +130,12 @@ impl AppConfig {
     }
 
     // カレントディレクトリをワークスペースとして使うように設定する関数
-    pub fn use_current_dir_as_workspace(&mut self) -> Result<(), UseCurrentDirAsWorkspaceError> {
+    // ワークスペースのフォーマットが古い場合は、切り替える前にマイグレーションを行う
+    // dry_runがtrueのときは、必要なマイグレーションを表示するだけで、ワークスペースの切り替えもしない
+    pub fn use_current_dir_as_workspace(
+        &mut self,
+        dry_run: bool,
+    ) -> Result<(), UseCurrentDirAsWorkspaceError> {
         // カレントディレクトリを取得
         let current_dir_path = match env::current_dir() {
             Ok(p) => p,
@@ -111,33 +150,77 @@ impl AppConfig {
             // ない場合はエラーを返す
             return Err(UseCurrentDirAsWorkspaceError::BeforeInitError);
         }
+        // 古いフォーマットのワークスペースをマイグレーションする
+        match migration::migrate(&workspace, dry_run) {
+            Ok(_) => (),
+            Err(e) => return Err(UseCurrentDirAsWorkspaceError::MigrateWorkspaceError(e)),
+        };
+        if dry_run {
+            return Ok(());
+        }
         // app configのdyanamic configの現在の作業ディレクトリを取得したカレントディレクトリに変更
         self.dyn_conf.current_workspace = current_dir_path.to_str().unwrap().to_string();
+        *self.vars_fingerprint.lock().unwrap() = None;
+        // ワークスペースの一覧にも登録しておく
+        self.dyn_conf.register_workspace(&current_dir_path);
         // app configの設定ファイルを更新する
-        let _ = match self.update_dyn_conf_file() {
+        match self.update_dyn_conf_file() {
             Ok(_) => (),
             Err(e) => return Err(UseCurrentDirAsWorkspaceError::UpdateDynConfFileError(e)),
         };
         Ok(())
     }
 
-    pub fn update_dyn_conf_file(&self) -> Result<(), UpdateDynConfFileError> {
-        let toml_ = match toml::to_string(&self.dyn_conf) {
-            Ok(t) => t,
-            Err(e) => {
-                return Err(UpdateDynConfFileError::ParseError(e));
+    // dynamic configをファイルに書き込む関数
+    // 他のシェルがこのプロセスの起動後にワークスペースを登録しているかもしれないので、
+    // ファイル上のワークスペースの一覧を取り込んでから書き込む
+    pub fn update_dyn_conf_file(&mut self) -> Result<(), UpdateDynConfFileError> {
+        if let Ok(on_disk) = read_dyn_conf(&self.dyn_conf_path) {
+            for (name, path) in on_disk.workspaces {
+                if !self.dyn_conf.workspaces.values().any(|p| *p == path) {
+                    self.dyn_conf.workspaces.entry(name).or_insert(path);
+                }
             }
+        }
+        write_dyn_conf(&self.dyn_conf_path, &self.dyn_conf)
+    }
+}
+
+// app configが使うファイルの場所
+// 初期化や修復をせずに場所だけを知りたいとき (doctorなど) に使う
+pub struct AppPaths {
+    pub app_conf_path: PathBuf,
+    pub dyn_conf_path: PathBuf,
+    pub shell_hist_path: PathBuf,
+}
+
+impl AppPaths {
+    pub fn resolve(config_dir: Option<PathBuf>) -> Result<Self, AppConfigError> {
+        let (app_conf_path, is_explicit) = resolve_app_conf_path(config_dir)?;
+        // <app conf>/dynamic_config.toml
+        let dyn_conf_path = app_conf_path.join(DYNAMIC_CONFIG_FILE_NAME);
+        // <app conf>/shell_history もしくは $XDG_STATE_HOME/pwnrail/shell_history
+        let shell_hist_path = match xdg_dir(XDG_STATE_HOME_ENV) {
+            Some(state_home) if !is_explicit => state_home
+                .join(XDG_APP_DIR_NAME)
+                .join(SHELL_HISTORY_FILE_NAME),
+            _ => app_conf_path.join(SHELL_HISTORY_FILE_NAME),
         };
-        let file = match fs::File::create(&self.dyn_conf_path) {
-            Ok(f) => f,
-            Err(e) => return Err(UpdateDynConfFileError::OpenError(e)),
-        };
-        let mut writer = io::BufWriter::new(file);
-        let _ = match writer.write_all(toml_.as_bytes()) {
-            Ok(_) => (),
-            Err(e) => return Err(UpdateDynConfFileError::WriteError(e)),
-        };
-        Ok(())
+        Ok(Self {
+            app_conf_path,
+            dyn_conf_path,
+            shell_hist_path,
+        })
+    }
+}
+
+impl Default for DynamicConfig {
+    // current_workspaceには最初は何も指定しない
+    fn default() -> Self {
+        Self {
+            current_workspace: "".to_string(),
+            workspaces: BTreeMap::new(),
+        }
     }
 }
 
@@ -146,25 +229,148 @@ impl DynamicConfig {
         let path = PathBuf::from(&self.current_workspace);
         Workspace::assemble_struct(&path)
     }
+
+    // ワークスペースを登録して、登録した名前を返す関数
+    // 名前はディレクトリ名にする。同じ名前で別のパスが登録されている場合は -2, -3 ... を付ける
+    // 既に登録されているパスの場合は、その名前をそのまま返す
+    pub fn register_workspace(&mut self, path: &Path) -> String {
+        let path_str = path.to_string_lossy().to_string();
+        if let Some((name, _)) = self.workspaces.iter().find(|(_, p)| **p == path_str) {
+            return name.clone();
+        }
+        let base = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "workspace".to_string());
+        let mut name = base.clone();
+        let mut n = 2;
+        while self.workspaces.contains_key(&name) {
+            name = format!("{}-{}", base, n);
+            n += 1;
+        }
+        self.workspaces.insert(name.clone(), path_str);
+        name
+    }
 }
 
 // .prail      管理ディレクトリという呼称にする
 //  | vars.json     ipアドレスなどの変数を気軽に収納するためのファイル  varsファイルという呼称にする
+//  |               vars.yaml, vars.tomlでもよい (store::formatを参照)
+//  | meta.toml     ワークスペースのフォーマットのバージョンなどを記録するファイル  metaファイルという呼称にする
+//  | history/      varsファイルの変更履歴 (store::historyを参照)
+//  | vars.schema.json  あればvarsファイルに書き込む前に検査するJSON Schema (store::schemaを参照)
+//  | hosts.json    ホストとサービスの一覧 (store::hostsを参照)
+//  | creds.json    認証情報の一覧 (store::credsを参照)
+//  | notes/        ワークスペース・ホスト・サービスのMarkdownのメモ (store::notesを参照)
+//  | timeline.jsonl    実行したコマンドとvarsの変更の記録 (store::timelineを参照)
+//  | findings.json     指摘事項の一覧 (store::findingsを参照)
+//  | evidence/     指摘事項の証拠としてコピーしたファイル
+//  | flags.json    取ったフラグと、プラットフォーム・取るべきフラグの数 (store::flagsを参照)
+//  | loot.json     持ち出したファイルの一覧とハッシュ・入手元 (store::lootを参照)
+//  | loot/         持ち出したファイルのコピー
 pub struct Workspace {
     pub mgr_path: PathBuf,
     pub vars_path: PathBuf,
+    pub vars_format: VarsFormat,
+    pub meta_path: PathBuf,
+    pub history_path: PathBuf,
+    pub schema_path: PathBuf,
+    pub hosts_path: PathBuf,
+    pub creds_path: PathBuf,
+    pub notes_path: PathBuf,
+    pub timeline_path: PathBuf,
+    pub findings_path: PathBuf,
+    pub evidence_path: PathBuf,
+    pub flags_path: PathBuf,
+    pub loot_index_path: PathBuf,
+    pub loot_path: PathBuf,
+}
+
+// metaファイルの内容
+// schema_versionはmigration::CURRENT_SCHEMA_VERSIONと比較され、古ければuseしたときにマイグレーションされる
+#[derive(Deserialize, Serialize, Debug)]
+pub struct WorkspaceMeta {
+    pub schema_version: u32,
+    pub created_at: DateTime<Utc>,
+    pub pwnrail_version: String,
+}
+
+impl WorkspaceMeta {
+    pub fn new(created_at: DateTime<Utc>) -> Self {
+        Self {
+            schema_version: migration::CURRENT_SCHEMA_VERSION,
+            created_at,
+            pwnrail_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
 }
 
 impl Workspace {
-    pub fn assemble_struct(path: &PathBuf) -> Self {
+    pub fn assemble_struct(path: &Path) -> Self {
         let mgr_path = path.join(WORKSPACE_DIR_NAME);
-        let vars_path = mgr_path.join(VARS_FILE_NAME);
+        // 存在するvarsファイルの形式を使う。どれもなければjson
+        let vars_format = format::FORMATS
+            .into_iter()
+            .find(|f| mgr_path.join(f.file_name()).exists())
+            .unwrap_or(VarsFormat::Json);
+        let vars_path = mgr_path.join(vars_format.file_name());
+        let meta_path = mgr_path.join(META_FILE_NAME);
+        let history_path = mgr_path.join(HISTORY_DIR_NAME);
+        let schema_path = mgr_path.join(schema::SCHEMA_FILE_NAME);
+        let hosts_path = mgr_path.join(HOSTS_FILE_NAME);
+        let creds_path = mgr_path.join(CREDS_FILE_NAME);
+        let notes_path = mgr_path.join(NOTES_DIR_NAME);
+        let timeline_path = mgr_path.join(TIMELINE_FILE_NAME);
+        let findings_path = mgr_path.join(FINDINGS_FILE_NAME);
+        let evidence_path = mgr_path.join(EVIDENCE_DIR_NAME);
+        let flags_path = mgr_path.join(FLAGS_FILE_NAME);
+        let loot_index_path = mgr_path.join(LOOT_INDEX_FILE_NAME);
+        let loot_path = mgr_path.join(LOOT_DIR_NAME);
         Self {
             mgr_path,
             vars_path,
+            vars_format,
+            meta_path,
+            history_path,
+            schema_path,
+            hosts_path,
+            creds_path,
+            notes_path,
+            timeline_path,
+            findings_path,
+            evidence_path,
+            flags_path,
+            loot_index_path,
+            loot_path,
         }
     }
 
+    // metaファイルを読み込む関数
+    // metaファイルが導入される前のワークスペースにはmetaファイルがないので、そのときはNoneを返す
+    pub fn read_meta(&self) -> Result<Option<WorkspaceMeta>, ReadWorkspaceMetaError> {
+        let content = match fs::read_to_string(&self.meta_path) {
+            Ok(c) => c,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(ReadWorkspaceMetaError::ReadError(e)),
+        };
+        match toml::from_str(&content) {
+            Ok(meta) => Ok(Some(meta)),
+            Err(e) => Err(ReadWorkspaceMetaError::ParseError(e)),
+        }
+    }
+
+    pub fn write_meta(&self, meta: &WorkspaceMeta) -> Result<(), WriteWorkspaceMetaError> {
+        let toml_ = match toml::to_string(meta) {
+            Ok(t) => t,
+            Err(e) => return Err(WriteWorkspaceMetaError::ParseError(e)),
+        };
+        match fs::write(&self.meta_path, toml_) {
+            Ok(_) => (),
+            Err(e) => return Err(WriteWorkspaceMetaError::WriteError(e)),
+        };
+        Ok(())
+    }
+
     fn create(&self) -> Result<(), CreateNewWorkspaceError> {
         // ワークスペースの管理ディレクトリの存在確認
         if match is_entry_exist(&self.mgr_path) {
@@ -175,7 +381,7 @@ impl Workspace {
             return Err(CreateNewWorkspaceError::MgrAlreadyExists);
         }
         // 管理ディレクトリを作成と初期値の設定
-        let _ = match fs::create_dir_all(&self.mgr_path) {
+        match fs::create_dir_all(&self.mgr_path) {
             Ok(_) => (),
             Err(e) => return Err(CreateNewWorkspaceError::CreateMgrError(e)),
         };
@@ -185,49 +391,107 @@ impl Workspace {
             Err(e) => return Err(CreateNewWorkspaceError::CreateVarsFileError(e)),
         };
         let mut vars_writer = io::BufWriter::new(vars_file);
-        let vars_initial_content = "{}".as_bytes();
-        let _ = match vars_writer.write_all(vars_initial_content) {
+        let vars_initial_content = self.vars_format.empty_content().as_bytes();
+        match vars_writer.write_all(vars_initial_content) {
             Ok(_) => (),
             Err(e) => return Err(CreateNewWorkspaceError::WriteVarsFileError(e)),
         };
+        // metaファイルを作成
+        match self.write_meta(&WorkspaceMeta::new(Utc::now())) {
+            Ok(_) => (),
+            Err(e) => return Err(CreateNewWorkspaceError::WriteMetaError(e)),
+        };
 
         Ok(())
     }
 }
 
-// アプリケーションを初期化する関数。
-// この関数では~/.pwnrailが存在しないときに~/.pwnrailを作成し、その配下に初期設定の設定ファイルたちを配置する。
-// ~/.pwnrailが存在するときは存在することを示すエラーを吐く
-fn app_init(
-    app_conf_path: &PathBuf,
-    dyn_conf_path: &PathBuf,
-    shell_hist_path: &PathBuf,
-) -> Result<DynamicConfig, AppInitError> {
-    // app confディレクトリの存在確認
-    if match is_entry_exist(app_conf_path) {
-        Ok(b) => b,
-        Err(e) => return Err(AppInitError::CheckAppConfPresenceError(e)),
-    } {
-        return Err(AppInitError::InitAlreadyDone);
+// app configディレクトリの場所を決める関数
+// 2つ目の返り値は、ユーザーが明示的に場所を指定したかどうか (--config-dir または PWNRAIL_HOME)
+fn resolve_app_conf_path(config_dir: Option<PathBuf>) -> Result<(PathBuf, bool), AppConfigError> {
+    if let Some(dir) = config_dir {
+        return Ok((dir, true));
+    }
+    if let Some(dir) = env::var_os(APP_HOME_ENV).filter(|v| !v.is_empty()) {
+        return Ok((PathBuf::from(dir), true));
     }
+    let legacy_path = home::home_dir().map(|home| home.join(APP_CONFIG_DIR_NAME));
+    // 以前から使っている ~/.pwnrail があれば、XDG_CONFIG_HOME よりも優先する
+    if let Some(path) = &legacy_path {
+        if path.is_dir() {
+            return Ok((path.clone(), false));
+        }
+    }
+    if let Some(config_home) = xdg_dir(XDG_CONFIG_HOME_ENV) {
+        return Ok((config_home.join(XDG_APP_DIR_NAME), false));
+    }
+    match legacy_path {
+        Some(path) => Ok((path, false)),
+        None => Err(AppConfigError::HomeDirNotFound),
+    }
+}
+
+// XDG base directory の環境変数を読む関数
+// 仕様に従って、空の値や相対パスは設定されていないものとして扱う
+fn xdg_dir(name: &str) -> Option<PathBuf> {
+    env::var_os(name)
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+}
 
-    // app confディレクトリの作成
-    let _ = match fs::create_dir_all(app_conf_path) {
+// アプリケーションを初期化する関数。
+// この関数はapp configディレクトリとその配下の設定ファイルたちのうち、存在しないものを作成する。
+// dynamic_config.tomlがパースできない場合は、壊れたファイルをバックアップしてから初期設定で作り直す。
+// すべて揃っているときは、dynamic configを読み込むだけになる
+fn app_init(paths: &AppPaths) -> Result<DynamicConfig, AppInitError> {
+    // app confディレクトリの作成 (既に存在する場合は何もしない)
+    match fs::create_dir_all(&paths.app_conf_path) {
         Ok(_) => (),
         Err(e) => return Err(AppInitError::AppConfDirCreateError(e)),
     };
 
-    // dynamic configの初期化
-    let dyn_conf = match init_dyn_conf(dyn_conf_path) {
-        Ok(c) => c,
-        Err(e) => return Err(AppInitError::DynConfInitError(e)),
+    // dynamic configの読み込み、なければ初期化
+    let dyn_conf = if !match is_entry_exist(&paths.dyn_conf_path) {
+        Ok(b) => b,
+        Err(e) => return Err(AppInitError::CheckDynConfPresenceError(e)),
+    } {
+        match init_dyn_conf(&paths.dyn_conf_path) {
+            Ok(c) => c,
+            Err(e) => return Err(AppInitError::DynConfInitError(e)),
+        }
+    } else {
+        match read_dyn_conf(&paths.dyn_conf_path) {
+            Ok(c) => c,
+            // パースできないときは壊れたファイルを退避して作り直す
+            Err(ReadDynConfError::ParseError(_)) => {
+                let backup = match backup_file(&paths.dyn_conf_path) {
+                    Ok(p) => p,
+                    Err(e) => return Err(AppInitError::BackupDynConfError(e)),
+                };
+                println!(
+                    "{} dynamic config was broken. it was moved to {} and regenerated",
+                    log_prefix(),
+                    backup.display()
+                );
+                match init_dyn_conf(&paths.dyn_conf_path) {
+                    Ok(c) => c,
+                    Err(e) => return Err(AppInitError::DynConfInitError(e)),
+                }
+            }
+            Err(e) => return Err(AppInitError::ReadDynConfError(e)),
+        }
     };
 
-    // shell historyの初期化
-    let _ = match init_shell_hist(shell_hist_path) {
-        Ok(_) => (),
-        Err(e) => return Err(AppInitError::ShellHistInitError(e)),
-    };
+    // shell historyがなければ初期化
+    if !match is_entry_exist(&paths.shell_hist_path) {
+        Ok(b) => b,
+        Err(e) => return Err(AppInitError::CheckShellHistPresenceError(e)),
+    } {
+        match init_shell_hist(&paths.shell_hist_path) {
+            Ok(_) => (),
+            Err(e) => return Err(AppInitError::ShellHistInitError(e)),
+        };
+    }
 
     Ok(dyn_conf)
 }
@@ -235,19 +499,15 @@ fn app_init(
 // dynamic_config を初期化する関数
 // app_init関数から呼び出される
 // やることはdynamic_cnfig.tomlの作成とそのファイルへの初期設定の書き込み
-fn init_dyn_conf(dyn_conf_path: &PathBuf) -> Result<DynamicConfig, DynConfInitError> {
+pub fn init_dyn_conf(dyn_conf_path: &PathBuf) -> Result<DynamicConfig, DynConfInitError> {
     // dynamic configファイルの作成
     let path = dyn_conf_path;
-    let file = match fs::File::create(&path) {
+    let file = match fs::File::create(path) {
         Ok(f) => f,
         Err(e) => return Err(DynConfInitError::CreateError(e)),
     };
     // 初期のdynamic_config.tomlの書き込み
-    //
-    // current_workspaceには最初は何も指定しない
-    let conf = DynamicConfig {
-        current_workspace: "".to_string(),
-    };
+    let conf = DynamicConfig::default();
     // DynamicConfigをTomlファイルに変換する
     let toml_ = match toml::to_string(&conf) {
         Ok(s) => s,
@@ -255,15 +515,43 @@ fn init_dyn_conf(dyn_conf_path: &PathBuf) -> Result<DynamicConfig, DynConfInitEr
     };
     // dybamic_config.tomlに設定を書き込む
     let mut writer = io::BufWriter::new(file);
-    let _ = match writer.write_all(toml_.as_bytes()) {
+    match writer.write_all(toml_.as_bytes()) {
         Ok(_) => (),
         Err(e) => return Err(DynConfInitError::WriteError(e)),
     };
 
     Ok(conf)
 }
+// dynamic configをファイルに書き込む関数
+// 他のプロセスと同時に書き込まないように排他ロックを取り、一時ファイル経由で書き込む
+pub fn write_dyn_conf(
+    dyn_conf_path: &Path,
+    dyn_conf: &DynamicConfig,
+) -> Result<(), UpdateDynConfFileError> {
+    let toml_ = match toml::to_string(dyn_conf) {
+        Ok(t) => t,
+        Err(e) => {
+            return Err(UpdateDynConfFileError::ParseError(e));
+        }
+    };
+    let _lock = match lock::lock_exclusive(dyn_conf_path) {
+        Ok(l) => l,
+        Err(e) => return Err(UpdateDynConfFileError::LockError(e)),
+    };
+    match lock::write_atomic(dyn_conf_path, toml_.as_bytes()) {
+        Ok(_) => (),
+        Err(e) => return Err(UpdateDynConfFileError::WriteError(e)),
+    };
+    Ok(())
+}
+
 // dynamic configを読み込む関数
-fn read_dyn_conf(dyn_conf_path: &PathBuf) -> Result<DynamicConfig, ReadDynConfError> {
+pub fn read_dyn_conf(dyn_conf_path: &PathBuf) -> Result<DynamicConfig, ReadDynConfError> {
+    // 書き込み途中の内容を読まないように共有ロックを取る
+    let _lock = match lock::lock_shared(dyn_conf_path) {
+        Ok(l) => l,
+        Err(e) => return Err(ReadDynConfError::LockError(e)),
+    };
     // dynamic configファイルを開く
     let file = match fs::File::open(dyn_conf_path) {
         Ok(f) => f,
@@ -271,7 +559,7 @@ fn read_dyn_conf(dyn_conf_path: &PathBuf) -> Result<DynamicConfig, ReadDynConfEr
     };
     let mut reader = io::BufReader::new(&file);
     let mut toml = String::new();
-    let _ = match reader.read_to_string(&mut toml) {
+    match reader.read_to_string(&mut toml) {
         Ok(_) => (),
         Err(e) => return Err(ReadDynConfError::ReadError(e)),
     };
@@ -285,16 +573,37 @@ fn read_dyn_conf(dyn_conf_path: &PathBuf) -> Result<DynamicConfig, ReadDynConfEr
 // shell_historyを初期化する関数
 // app_init関数から呼び出される
 // やることはshell_historyの作成
-fn init_shell_hist(shell_hist_path: &PathBuf) -> Result<(), ShellHistInitError> {
-    // ~/.pwnrail/shell_historyの作成
+pub fn init_shell_hist(shell_hist_path: &PathBuf) -> Result<(), ShellHistInitError> {
+    // shell_historyの作成
+    // XDG_STATE_HOMEを使う場合はapp configディレクトリの外になるので、親ディレクトリも作成する
     let path = shell_hist_path;
-    let _ = match fs::File::create(&path) {
+    if let Some(parent) = path.parent() {
+        match fs::create_dir_all(parent) {
+            Ok(_) => (),
+            Err(e) => return Err(ShellHistInitError::CreateDirError(e)),
+        };
+    }
+    match fs::File::create(path) {
         Ok(_) => (),
         Err(e) => return Err(ShellHistInitError::CreateError(e)),
     };
     Ok(())
 }
 
+// 壊れたファイルを <ファイル名>.broken-<unix time> にリネームして退避する関数
+// 退避先のパスを返す
+pub fn backup_file(path: &PathBuf) -> Result<PathBuf, io::Error> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".broken-{}", timestamp));
+    let backup_path = path.with_file_name(name);
+    fs::rename(path, &backup_path)?;
+    Ok(backup_path)
+}
+
 pub fn is_entry_exist(path: &PathBuf) -> Result<bool, io::Error> {
     match fs::metadata(path) {
         Ok(_) => Ok(true),
@@ -0,0 +1,458 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    config::{
+        backup_file, init_dyn_conf, init_shell_hist, is_entry_exist, read_dyn_conf, write_dyn_conf,
+        AppPaths, DynamicConfig, Workspace,
+    },
+    error::{self, DoctorRepairError, DocumentError, Error, ReadDynConfError, TimelineError},
+    migration,
+    output::{error_prefix, green, log_prefix, red},
+    store::{
+        creds::Vault, document, findings::FindingBook, flags::FlagBoard, format::VarsFormat,
+        hosts::Hosts, loot::LootIndex, timeline,
+    },
+};
+
+// pwnrail doctor
+// app configディレクトリ、dynamic config、shell history、登録されているすべてのワークスペースを検査して、
+// 問題が見つかったら修復を提案する。
+// AppConfig::newは起動時に自動で修復をしてしまうので、doctorはAppPathsだけを使って直接ファイルを調べる
+
+struct Finding {
+    subject: PathBuf,
+    problem: String,
+    repair: Option<Repair>,
+}
+
+enum Repair {
+    // ディレクトリを作成する
+    CreateDir(PathBuf),
+    // 初期設定のdynamic_config.tomlを作成する
+    CreateDynConf(PathBuf),
+    // 壊れたdynamic_config.tomlを退避してから作り直す
+    RegenerateDynConf(PathBuf),
+    // 空のshell_historyを作成する
+    CreateShellHist(PathBuf),
+    // 他のユーザーから読めないように権限を0700にする
+    RestrictPermission(PathBuf),
+    // 存在しないワークスペースを現在のワークスペースから外す
+    ForgetCurrentWorkspace(PathBuf),
+    // 存在しないワークスペースをワークスペースの一覧から外す
+    Unregister(PathBuf, String),
+    // 空のvarsファイルを作成する
+    CreateVarsFile(PathBuf),
+    // 壊れたvarsファイルを退避してから空のvarsファイルを作成する
+    ResetVarsFile(PathBuf),
+    // 古いフォーマットのワークスペースをマイグレーションする
+    MigrateWorkspace(PathBuf),
+}
+
+impl Repair {
+    fn describe(&self) -> String {
+        match self {
+            Repair::CreateDir(p) => format!("create directory {}", p.display()),
+            Repair::CreateDynConf(p) => format!("create default dynamic config {}", p.display()),
+            Repair::RegenerateDynConf(p) => {
+                format!("back up and regenerate dynamic config {}", p.display())
+            }
+            Repair::CreateShellHist(p) => format!("create shell history {}", p.display()),
+            Repair::RestrictPermission(p) => format!("chmod 700 {}", p.display()),
+            Repair::ForgetCurrentWorkspace(_) => "unset the current workspace".to_string(),
+            Repair::Unregister(_, name) => format!("unregister workspace {}", name),
+            Repair::CreateVarsFile(p) => format!("create empty vars file {}", p.display()),
+            Repair::ResetVarsFile(p) => {
+                format!("back up and reset vars file {}", p.display())
+            }
+            Repair::MigrateWorkspace(p) => format!("migrate workspace {}", p.display()),
+        }
+    }
+
+    fn apply(&self) -> Result<(), DoctorRepairError> {
+        match self {
+            Repair::CreateDir(p) => match fs::create_dir_all(p) {
+                Ok(_) => Ok(()),
+                Err(e) => Err(DoctorRepairError::CreateDirError(e)),
+            },
+            Repair::CreateDynConf(p) => match init_dyn_conf(p) {
+                Ok(_) => Ok(()),
+                Err(e) => Err(DoctorRepairError::DynConfInitError(e)),
+            },
+            Repair::RegenerateDynConf(p) => {
+                match backup_file(p) {
+                    Ok(_) => (),
+                    Err(e) => return Err(DoctorRepairError::BackupError(e)),
+                };
+                match init_dyn_conf(p) {
+                    Ok(_) => Ok(()),
+                    Err(e) => Err(DoctorRepairError::DynConfInitError(e)),
+                }
+            }
+            Repair::CreateShellHist(p) => match init_shell_hist(p) {
+                Ok(_) => Ok(()),
+                Err(e) => Err(DoctorRepairError::ShellHistInitError(e)),
+            },
+            Repair::RestrictPermission(p) => match restrict_permission(p) {
+                Ok(_) => Ok(()),
+                Err(e) => Err(DoctorRepairError::SetPermissionError(e)),
+            },
+            Repair::ForgetCurrentWorkspace(dyn_conf_path) => {
+                let mut conf = match read_dyn_conf(dyn_conf_path) {
+                    Ok(c) => c,
+                    Err(e) => return Err(DoctorRepairError::ReadDynConfError(e)),
+                };
+                conf.current_workspace = "".to_string();
+                match write_dyn_conf(dyn_conf_path, &conf) {
+                    Ok(_) => Ok(()),
+                    Err(e) => Err(DoctorRepairError::UpdateDynConfFileError(e)),
+                }
+            }
+            Repair::Unregister(dyn_conf_path, name) => {
+                let mut conf = match read_dyn_conf(dyn_conf_path) {
+                    Ok(c) => c,
+                    Err(e) => return Err(DoctorRepairError::ReadDynConfError(e)),
+                };
+                conf.workspaces.remove(name);
+                match write_dyn_conf(dyn_conf_path, &conf) {
+                    Ok(_) => Ok(()),
+                    Err(e) => Err(DoctorRepairError::UpdateDynConfFileError(e)),
+                }
+            }
+            Repair::CreateVarsFile(p) => match fs::write(p, empty_vars(p)) {
+                Ok(_) => Ok(()),
+                Err(e) => Err(DoctorRepairError::WriteVarsFileError(e)),
+            },
+            Repair::ResetVarsFile(p) => {
+                match backup_file(p) {
+                    Ok(_) => (),
+                    Err(e) => return Err(DoctorRepairError::BackupError(e)),
+                };
+                match fs::write(p, empty_vars(p)) {
+                    Ok(_) => Ok(()),
+                    Err(e) => Err(DoctorRepairError::WriteVarsFileError(e)),
+                }
+            }
+            Repair::MigrateWorkspace(p) => {
+                match migration::migrate(&Workspace::assemble_struct(p), false) {
+                    Ok(_) => Ok(()),
+                    Err(e) => Err(DoctorRepairError::MigrateWorkspaceError(e)),
+                }
+            }
+        }
+    }
+}
+
+// doctorのエントリポイント
+// yesがtrueのときは確認せずにすべての修復を行う
+pub fn run(paths: &AppPaths, yes: bool) -> bool {
+    let findings = check(paths);
+    if findings.is_empty() {
+        println!("{} no problem is found", log_prefix());
+        return false;
+    }
+
+    let mut is_error = false;
+    for finding in findings {
+        println!(
+            "{} {}: {}",
+            error_prefix(),
+            finding.subject.display(),
+            finding.problem
+        );
+        let repair = match finding.repair {
+            Some(r) => r,
+            None => {
+                is_error = true;
+                continue;
+            }
+        };
+        if !yes && !confirm(&repair.describe()) {
+            is_error = true;
+            continue;
+        }
+        match repair.apply() {
+            Ok(_) => println!("[{}] {}", green("fixed"), repair.describe()),
+            Err(e) => {
+                println!("[{}] {}", red("failed"), repair.describe());
+                error::print_error(Error::DoctorRepairError(e));
+                is_error = true;
+            }
+        };
+    }
+    is_error
+}
+
+fn check(paths: &AppPaths) -> Vec<Finding> {
+    let mut findings = vec![];
+
+    // app configディレクトリ
+    match is_entry_exist(&paths.app_conf_path) {
+        Ok(true) => check_permission(&paths.app_conf_path, &mut findings),
+        Ok(false) => findings.push(Finding {
+            subject: paths.app_conf_path.clone(),
+            problem: "app config directory doesn't exist".to_string(),
+            repair: Some(Repair::CreateDir(paths.app_conf_path.clone())),
+        }),
+        Err(e) => findings.push(io_finding(&paths.app_conf_path, e)),
+    }
+
+    // dynamic config
+    let dyn_conf = match read_dyn_conf(&paths.dyn_conf_path) {
+        Ok(c) => Some(c),
+        Err(ReadDynConfError::OpenError(e)) if e.kind() == io::ErrorKind::NotFound => {
+            findings.push(Finding {
+                subject: paths.dyn_conf_path.clone(),
+                problem: "dynamic config file doesn't exist".to_string(),
+                repair: Some(Repair::CreateDynConf(paths.dyn_conf_path.clone())),
+            });
+            None
+        }
+        Err(ReadDynConfError::ParseError(e)) => {
+            findings.push(Finding {
+                subject: paths.dyn_conf_path.clone(),
+                problem: format!("dynamic config can't be parsed: {}", e.message()),
+                repair: Some(Repair::RegenerateDynConf(paths.dyn_conf_path.clone())),
+            });
+            None
+        }
+        Err(e) => {
+            findings.push(Finding {
+                subject: paths.dyn_conf_path.clone(),
+                problem: e.to_string(),
+                repair: None,
+            });
+            None
+        }
+    };
+
+    // shell history
+    match is_entry_exist(&paths.shell_hist_path) {
+        Ok(true) => (),
+        Ok(false) => findings.push(Finding {
+            subject: paths.shell_hist_path.clone(),
+            problem: "shell history file doesn't exist".to_string(),
+            repair: Some(Repair::CreateShellHist(paths.shell_hist_path.clone())),
+        }),
+        Err(e) => findings.push(io_finding(&paths.shell_hist_path, e)),
+    }
+
+    // dynamic configに登録されているワークスペース
+    if let Some(conf) = dyn_conf {
+        if !conf.current_workspace.is_empty() {
+            check_workspace(paths, &conf, &mut findings);
+        }
+        for (name, path) in &conf.workspaces {
+            let workspace = Workspace::assemble_struct(&PathBuf::from(path));
+            // 現在のワークスペースは上で検査している
+            if workspace.mgr_path.is_dir() && *path != conf.current_workspace {
+                check_permission(&workspace.mgr_path, &mut findings);
+                check_workspace_data(&PathBuf::from(path), &mut findings);
+            } else if !workspace.mgr_path.is_dir() {
+                findings.push(Finding {
+                    subject: PathBuf::from(path),
+                    problem: format!("registered workspace {} doesn't exist", name),
+                    repair: Some(Repair::Unregister(
+                        paths.dyn_conf_path.clone(),
+                        name.clone(),
+                    )),
+                });
+            }
+        }
+    }
+
+    findings
+}
+
+fn check_workspace(paths: &AppPaths, conf: &DynamicConfig, findings: &mut Vec<Finding>) {
+    let workspace_path = PathBuf::from(&conf.current_workspace);
+    let workspace = Workspace::assemble_struct(&workspace_path);
+    let forget = || Some(Repair::ForgetCurrentWorkspace(paths.dyn_conf_path.clone()));
+
+    match is_entry_exist(&workspace.mgr_path) {
+        Ok(true) => check_permission(&workspace.mgr_path, findings),
+        Ok(false) => {
+            findings.push(Finding {
+                subject: workspace_path,
+                problem: "the current workspace doesn't have a workspace manager directory"
+                    .to_string(),
+                repair: forget(),
+            });
+            return;
+        }
+        Err(e) => {
+            findings.push(io_finding(&workspace.mgr_path, e));
+            return;
+        }
+    }
+
+    check_workspace_data(&workspace_path, findings);
+}
+
+// ワークスペースの管理ディレクトリの中のファイルを、各コマンドと同じ方法で読めるか確かめる
+fn check_workspace_data(workspace_path: &Path, findings: &mut Vec<Finding>) {
+    let workspace = Workspace::assemble_struct(workspace_path);
+    match workspace.read_meta() {
+        Ok(Some(meta)) if meta.schema_version == migration::CURRENT_SCHEMA_VERSION => (),
+        Ok(Some(meta)) if meta.schema_version > migration::CURRENT_SCHEMA_VERSION => {
+            findings.push(Finding {
+                subject: workspace.meta_path.clone(),
+                problem: format!(
+                    "schema version {} is newer than this pwnrail supports",
+                    meta.schema_version
+                ),
+                repair: None,
+            })
+        }
+        Ok(meta) => findings.push(Finding {
+            subject: workspace_path.to_path_buf(),
+            problem: format!(
+                "workspace uses an old schema version ({})",
+                meta.map(|m| m.schema_version).unwrap_or(0)
+            ),
+            repair: Some(Repair::MigrateWorkspace(workspace_path.to_path_buf())),
+        }),
+        Err(e) => findings.push(Finding {
+            subject: workspace.meta_path.clone(),
+            problem: e.to_string(),
+            repair: None,
+        }),
+    }
+
+    match fs::read_to_string(&workspace.vars_path) {
+        Ok(content) => {
+            if let Err(e) = workspace.vars_format.parse(&content) {
+                findings.push(Finding {
+                    subject: workspace.vars_path.clone(),
+                    problem: format!("vars file is broken: {}", e),
+                    repair: Some(Repair::ResetVarsFile(workspace.vars_path.clone())),
+                });
+            }
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => findings.push(Finding {
+            subject: workspace.vars_path.clone(),
+            problem: "vars file doesn't exist".to_string(),
+            repair: Some(Repair::CreateVarsFile(workspace.vars_path.clone())),
+        }),
+        Err(e) => findings.push(io_finding(&workspace.vars_path, e)),
+    }
+
+    // 壊れたファイルは各コマンドが上書きしないようになっているので、手で直してもらう
+    let documents = [
+        (
+            &workspace.hosts_path,
+            document::read::<Hosts>(&workspace.hosts_path).err(),
+        ),
+        (
+            &workspace.creds_path,
+            document::read::<Vault>(&workspace.creds_path).err(),
+        ),
+        (
+            &workspace.findings_path,
+            document::read::<FindingBook>(&workspace.findings_path).err(),
+        ),
+        (
+            &workspace.flags_path,
+            document::read::<FlagBoard>(&workspace.flags_path).err(),
+        ),
+        (
+            &workspace.loot_index_path,
+            document::read::<LootIndex>(&workspace.loot_index_path).err(),
+        ),
+    ];
+    for (path, e) in documents {
+        if let Some(e) = e {
+            findings.push(Finding {
+                subject: path.clone(),
+                problem: document_problem(e),
+                repair: None,
+            });
+        }
+    }
+    match timeline::read(&workspace.timeline_path) {
+        Ok(_) => (),
+        Err(TimelineError::ParseError(line, e)) => findings.push(Finding {
+            subject: workspace.timeline_path.clone(),
+            problem: format!("timeline is broken at line {}: {}", line, e),
+            repair: None,
+        }),
+        Err(e) => findings.push(Finding {
+            subject: workspace.timeline_path.clone(),
+            problem: e.to_string(),
+            repair: None,
+        }),
+    }
+}
+
+fn document_problem(e: DocumentError) -> String {
+    match e {
+        DocumentError::ParseError(e) => format!("file is broken: {}", e),
+        DocumentError::LockError(e)
+        | DocumentError::ReadError(e)
+        | DocumentError::WriteError(e) => e.to_string(),
+        DocumentError::SerializeError(e) => e.to_string(),
+    }
+}
+
+// varsファイルの形式に合わせた空のvarsファイルの内容
+fn empty_vars(path: &Path) -> &'static str {
+    VarsFormat::from_path(path)
+        .unwrap_or(VarsFormat::Json)
+        .empty_content()
+}
+
+// 認証情報などを置くディレクトリなので、他のユーザーからアクセスできる場合は警告する
+#[cfg(unix)]
+fn check_permission(path: &PathBuf, findings: &mut Vec<Finding>) {
+    use std::os::unix::fs::PermissionsExt;
+    match fs::metadata(path) {
+        Ok(m) => {
+            if m.permissions().mode() & 0o077 != 0 {
+                findings.push(Finding {
+                    subject: path.clone(),
+                    problem: format!(
+                        "accessible by other users (mode {:o})",
+                        m.permissions().mode() & 0o777
+                    ),
+                    repair: Some(Repair::RestrictPermission(path.clone())),
+                });
+            }
+        }
+        Err(e) => findings.push(io_finding(path, e)),
+    }
+}
+
+#[cfg(not(unix))]
+fn check_permission(_path: &PathBuf, _findings: &mut Vec<Finding>) {}
+
+#[cfg(unix)]
+fn restrict_permission(path: &PathBuf) -> Result<(), io::Error> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o700))
+}
+
+#[cfg(not(unix))]
+fn restrict_permission(_path: &PathBuf) -> Result<(), io::Error> {
+    Ok(())
+}
+
+fn io_finding(path: &Path, e: io::Error) -> Finding {
+    Finding {
+        subject: path.to_path_buf(),
+        problem: e.to_string(),
+        repair: None,
+    }
+}
+
+fn confirm(description: &str) -> bool {
+    print!("{} {}? [y/N] ", log_prefix(), description);
+    let _ = io::stdout().flush();
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim(), "y" | "Y" | "yes")
+}
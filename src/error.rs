@@ -1,9 +1,10 @@
-use std::io;
+use std::{io, path::PathBuf};
 
 use thiserror::Error;
 
-use crate::{json::error::JsonQueryError, output::error_prefix};
+use crate::{json::error::JsonQueryError, output::error_prefix, store::schema::Violation};
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("error around config")]
@@ -12,20 +13,63 @@ pub enum Error {
     InitCurrentDirAsWorkspaceError(InitCurrentDirAsWorkspaceError),
     #[error("failed to use current directory as a workspace")]
     UseCurrentDirAsWorkspaceError(UseCurrentDirAsWorkspaceError),
+    #[error("failed to repair")]
+    DoctorRepairError(DoctorRepairError),
+    #[error("failed to export the workspace")]
+    WsExportError(WsExportError),
+    #[error("failed to import the workspace")]
+    WsImportError(WsImportError),
+    #[error("failed to access the vars file")]
+    VarsStoreError(VarsStoreError),
+    #[error("failed to access the vars history")]
+    VarsHistoryError(VarsHistoryError),
+    #[error("failed to handle the secret value")]
+    SecretError(SecretError),
+    #[error("failed to convert vars from or to environment variables")]
+    VarsEnvError(VarsEnvError),
+    #[error("failed to check the vars against the schema")]
+    VarsSchemaError(VarsSchemaError),
+    #[error("failed to merge vars")]
+    VarsMergeError(VarsMergeError),
+    #[error("failed to search vars")]
+    VarsSearchError(VarsSearchError),
+    #[error("failed to handle hosts")]
+    HostsError(HostsError),
+    #[error("failed to access the credentials")]
+    CredsError(CredsError),
+    #[error("failed to read the workspace data")]
+    ViewError(ViewError),
+    #[error("failed to handle the notes")]
+    NotesError(NotesError),
+    #[error("failed to handle the timeline")]
+    TimelineError(TimelineError),
+    #[error("failed to build the report")]
+    ReportError(ReportError),
+    #[error("failed to handle the findings")]
+    FindingsError(FindingsError),
+    #[error("failed to handle the flags")]
+    FlagsError(FlagsError),
+    #[error("failed to handle the loot")]
+    LootError(LootError),
+    #[error("failed to import the tool output")]
+    ImportError(ImportError),
     #[error("json query error")]
     JsonQueryError(JsonQueryError),
 }
 
 #[derive(Error, Debug)]
 pub enum AppConfigError {
+    #[error("home directory is not found. set PWNRAIL_HOME or use --config-dir")]
+    HomeDirNotFound,
     #[error("failed to init app")]
     AppInitError(AppInitError),
-    #[error("failed to read dynamic config")]
-    ReadDynConfError(ReadDynConfError),
 }
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Error, Debug)]
 pub enum ReadDynConfError {
+    #[error("failed to lock dynamic config file")]
+    LockError(io::Error),
     #[error("failed to open dynamic config file")]
     OpenError(io::Error),
     #[error("failed to read dynamic config content from its file data")]
@@ -34,20 +78,26 @@ pub enum ReadDynConfError {
     ParseError(toml::de::Error),
 }
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Error, Debug)]
 pub enum AppInitError {
-    #[error("failed to get if app config directory")]
-    CheckAppConfPresenceError(io::Error),
-    #[error("initialization is already done")]
-    InitAlreadyDone,
     #[error("failed to create app config directory")]
     AppConfDirCreateError(io::Error),
+    #[error("failed to get whether dynamic config file exists")]
+    CheckDynConfPresenceError(io::Error),
+    #[error("failed to get whether shell history file exists")]
+    CheckShellHistPresenceError(io::Error),
+    #[error("failed to read dynamic config")]
+    ReadDynConfError(ReadDynConfError),
+    #[error("failed to back up the broken dynamic config file")]
+    BackupDynConfError(io::Error),
     #[error("failed to init dynamic config: `{0}`")]
     DynConfInitError(DynConfInitError),
     #[error("failed to init shell history: `{0}`")]
     ShellHistInitError(ShellHistInitError),
 }
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Error, Debug)]
 pub enum DynConfInitError {
     #[error("failed to create dynamic config file")]
@@ -60,20 +110,24 @@ pub enum DynConfInitError {
 
 #[derive(Error, Debug)]
 pub enum ShellHistInitError {
+    #[error("failed to create the directory for shell history")]
+    CreateDirError(io::Error),
     #[error("failed to create shell history file")]
     CreateError(io::Error),
 }
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Error, Debug)]
 pub enum UpdateDynConfFileError {
+    #[error("failed to lock dynamic config file")]
+    LockError(io::Error),
     #[error("failed to parse toml file as dynamic config")]
     ParseError(toml::ser::Error),
-    #[error("failed to open dynamic config file")]
-    OpenError(io::Error),
     #[error("failed to write new dynamic config to the file")]
     WriteError(io::Error),
 }
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Error, Debug)]
 pub enum UseCurrentDirAsWorkspaceError {
     #[error("failed to get current directory")]
@@ -84,8 +138,41 @@ pub enum UseCurrentDirAsWorkspaceError {
     UpdateDynConfFileError(UpdateDynConfFileError),
     #[error("the workspace manager doesn't exist in the current directory")]
     BeforeInitError,
+    #[error("failed to migrate the workspace")]
+    MigrateWorkspaceError(MigrateWorkspaceError),
+}
+
+#[derive(Error, Debug)]
+pub enum MigrateWorkspaceError {
+    #[error("failed to read the workspace meta file")]
+    ReadMetaError(ReadWorkspaceMetaError),
+    #[error("the workspace uses schema version {0}, which is newer than this pwnrail supports")]
+    TooNewSchema(u32),
+    #[error("failed to back up the workspace manager directory")]
+    BackupError(io::Error),
+    #[error("failed to apply the migration from schema version {0}")]
+    ApplyError(u32, io::Error),
+    #[error("failed to write the workspace meta file")]
+    WriteMetaError(WriteWorkspaceMetaError),
+}
+
+#[derive(Error, Debug)]
+pub enum ReadWorkspaceMetaError {
+    #[error("failed to read the workspace meta file")]
+    ReadError(io::Error),
+    #[error("failed to parse the workspace meta file")]
+    ParseError(toml::de::Error),
 }
 
+#[derive(Error, Debug)]
+pub enum WriteWorkspaceMetaError {
+    #[error("failed to serialize the workspace meta")]
+    ParseError(toml::ser::Error),
+    #[error("failed to write the workspace meta file")]
+    WriteError(io::Error),
+}
+
+#[allow(clippy::enum_variant_names)]
 #[derive(Error, Debug)]
 pub enum InitCurrentDirAsWorkspaceError {
     #[error("failed to get current directory")]
@@ -108,21 +195,465 @@ pub enum CreateNewWorkspaceError {
     CreateVarsFileError(io::Error),
     #[error("failed to write the initial content to the vars file")]
     WriteVarsFileError(io::Error),
+    #[error("failed to write the meta file")]
+    WriteMetaError(WriteWorkspaceMetaError),
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Error, Debug)]
+pub enum DoctorRepairError {
+    #[error("failed to create the directory")]
+    CreateDirError(io::Error),
+    #[error("failed to back up the broken file")]
+    BackupError(io::Error),
+    #[error("failed to init dynamic config")]
+    DynConfInitError(DynConfInitError),
+    #[error("failed to init shell history")]
+    ShellHistInitError(ShellHistInitError),
+    #[error("failed to change the permission")]
+    SetPermissionError(io::Error),
+    #[error("failed to read dynamic config")]
+    ReadDynConfError(ReadDynConfError),
+    #[error("failed to update dynamic config")]
+    UpdateDynConfFileError(UpdateDynConfFileError),
+    #[error("failed to write the vars file")]
+    WriteVarsFileError(io::Error),
+    #[error("failed to migrate the workspace")]
+    MigrateWorkspaceError(MigrateWorkspaceError),
+}
+
+#[derive(Error, Debug)]
+pub enum WsExportError {
+    #[error("no workspace is used now")]
+    NoCurrentWorkspace,
+    #[error("failed to read a file in the workspace")]
+    ReadError(io::Error),
+    #[error("failed to serialize the manifest")]
+    SerializeManifestError(serde_json::Error),
+    #[error("failed to handle creds.json")]
+    ParseCredsError(serde_json::Error),
+    #[error("failed to redact timeline.jsonl")]
+    TimelineError(TimelineError),
+    #[error("failed to read or write the vars file")]
+    VarsFormatError(VarsFormatError),
+    #[error("failed to create the archive file")]
+    CreateArchiveError(io::Error),
+    #[error("failed to write the archive")]
+    WriteArchiveError(io::Error),
+}
+
+#[derive(Error, Debug)]
+pub enum WsImportError {
+    #[error("failed to open the archive")]
+    OpenArchiveError(io::Error),
+    #[error("failed to read the archive")]
+    ReadArchiveError(io::Error),
+    #[error("the archive doesn't contain a manifest")]
+    ManifestNotFound,
+    #[error("failed to parse the manifest")]
+    ParseManifestError(serde_json::Error),
+    #[error("the archive uses format version {0}, which is newer than this pwnrail supports")]
+    TooNewFormat(u32),
+    #[error("the archive contains an unsafe path `{0}`")]
+    UnsafePath(PathBuf),
+    #[error("the archive has an unsafe workspace name `{0}`")]
+    UnsafeWorkspaceName(String),
+    #[error("the hash of `{0}` doesn't match the manifest")]
+    HashMismatch(String),
+    #[error("`{0}` is listed in the manifest but missing in the archive")]
+    MissingFile(String),
+    #[error("`{0}` is in the archive but not listed in the manifest")]
+    UnexpectedFile(String),
+    #[error("failed to get current directory")]
+    GetCurrentDirError(io::Error),
+    #[error("a workspace manager directory already exists in `{0}`")]
+    TargetAlreadyExists(PathBuf),
+    #[error("failed to extract the archive")]
+    ExtractError(io::Error),
+    #[error("failed to migrate the imported workspace")]
+    MigrateWorkspaceError(MigrateWorkspaceError),
+    #[error("failed to update dynamic config")]
+    UpdateDynConfFileError(UpdateDynConfFileError),
+}
+
+#[derive(Error, Debug)]
+pub enum VarsStoreError {
+    #[error("no workspace is used now")]
+    NoCurrentWorkspace,
+    #[error("failed to lock the vars file")]
+    LockError(io::Error),
+    #[error("failed to read the vars file")]
+    ReadError(io::Error),
+    #[error("the vars file is broken. fix it by hand; it is not overwritten until then")]
+    ParseError(VarsFormatError),
+    #[error("failed to serialize vars")]
+    SerializeError(VarsFormatError),
+    #[error("failed to write the vars file")]
+    WriteError(io::Error),
+    #[error("another process changed `{0}` at the same time. reload and try again")]
+    Conflict(String),
+    #[error("the vars file is already {0}")]
+    AlreadyInFormat(PathBuf),
+    #[error("the vars were not saved")]
+    SchemaError(VarsSchemaError),
+}
+
+#[derive(Error, Debug)]
+pub enum VarsMergeError {
+    #[error("`{0}` is neither a workspace name nor a file")]
+    SourceNotFound(String),
+    #[error("failed to read the file to merge")]
+    ReadFileError(io::Error),
+    #[error("failed to parse the file to merge")]
+    ParseFileError(VarsFormatError),
+    #[error("failed to read the vars of workspace `{0}`")]
+    ReadWorkspaceError(String, Box<VarsStoreError>),
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Error, Debug)]
+pub enum DocumentError {
+    #[error("failed to lock the file")]
+    LockError(io::Error),
+    #[error("failed to read the file")]
+    ReadError(io::Error),
+    #[error("the file is broken. fix it by hand; it is not overwritten until then")]
+    ParseError(serde_json::Error),
+    #[error("failed to serialize the data")]
+    SerializeError(serde_json::Error),
+    #[error("failed to write the file")]
+    WriteError(io::Error),
+}
+
+#[derive(Error, Debug)]
+pub enum HostsError {
+    #[error("no workspace is used now")]
+    NoCurrentWorkspace,
+    #[error("failed to access hosts.json")]
+    DocumentError(DocumentError),
+    #[error("host `{0}` is not found")]
+    HostNotFound(String),
+    #[error("host `{0}` already exists")]
+    HostAlreadyExists(String),
+    #[error("`{0}` is not an ip address")]
+    InvalidAddress(String),
+    #[error("`{0}` is not a port like 80 or 53/udp")]
+    InvalidPort(String),
+}
+
+#[derive(Error, Debug)]
+pub enum CredsError {
+    #[error("no workspace is used now")]
+    NoCurrentWorkspace,
+    #[error("failed to access creds.json")]
+    DocumentError(DocumentError),
+    #[error("credential {0} is not found")]
+    CredNotFound(u32),
+    #[error("the same credential is already registered as {0}")]
+    CredAlreadyExists(u32),
+    #[error(
+        "the secret key is locked. run `unlock` to store the value encrypted, or add --plaintext"
+    )]
+    Locked,
+    #[error("credential {0} is referenced by finding {1}. unlink it with `finding link <id> --cred {0} --rm` first")]
+    CredInUse(u32, String),
+    #[error("failed to read findings.json")]
+    ReadFindingsError(DocumentError),
+    #[error("failed to find the host")]
+    HostsError(HostsError),
+    #[error("failed to encrypt or decrypt the secret value")]
+    SecretError(SecretError),
+    #[error("failed to write the exported credentials")]
+    WriteError(io::Error),
+}
+
+#[derive(Error, Debug)]
+pub enum NotesError {
+    #[error("no workspace is used now")]
+    NoCurrentWorkspace,
+    #[error("failed to find the host")]
+    HostsError(HostsError),
+    #[error("failed to read the notes")]
+    ReadError(io::Error),
+    #[error("failed to write the note")]
+    WriteError(io::Error),
+    #[error("failed to edit the note")]
+    EditorError(String),
+    #[error("invalid search pattern")]
+    InvalidPattern(regex::Error),
+}
+
+#[derive(Error, Debug)]
+pub enum TimelineError {
+    #[error("no workspace is used now")]
+    NoCurrentWorkspace,
+    #[error("failed to read timeline.jsonl")]
+    ReadError(io::Error),
+    #[error("failed to parse timeline.jsonl at line {0}")]
+    ParseError(usize, serde_json::Error),
+    #[error("failed to serialize the entry")]
+    SerializeError(serde_json::Error),
+    #[error("failed to write the timeline")]
+    WriteError(io::Error),
+    #[error("`{0}` is not a time like 2h, 2024-05-01 or 2024-05-01T13:00")]
+    InvalidTime(String),
+}
+
+#[derive(Error, Debug)]
+pub enum FindingsError {
+    #[error("no workspace is used now")]
+    NoCurrentWorkspace,
+    #[error("failed to access findings.json")]
+    DocumentError(DocumentError),
+    #[error("finding {0} is not found")]
+    FindingNotFound(u32),
+    #[error("cvss must be between 0.0 and 10.0, but got {0}")]
+    InvalidCvss(f32),
+    #[error("failed to find the host")]
+    HostsError(HostsError),
+    #[error("failed to find the credential")]
+    CredsError(CredsError),
+    #[error("failed to read the evidence {0}")]
+    ReadEvidenceError(PathBuf, io::Error),
+    #[error("failed to copy the evidence to {0}")]
+    WriteEvidenceError(PathBuf, io::Error),
+    #[error("failed to edit the description")]
+    EditorError(String),
+}
+
+#[derive(Error, Debug)]
+pub enum FlagsError {
+    #[error("no workspace is used now")]
+    NoCurrentWorkspace,
+    #[error("failed to access flags.json")]
+    DocumentError(DocumentError),
+    #[error("failed to read the flag formats {0}")]
+    ReadFormatsError(PathBuf, io::Error),
+    #[error("failed to parse the flag formats {0}")]
+    ParseFormatsError(PathBuf, toml::de::Error),
+    #[error("platform `{0}` is not known. run `flag formats` to see the available platforms")]
+    UnknownPlatform(String),
+    #[error("the format of platform `{0}` is not a valid regex")]
+    InvalidFormat(String, regex::Error),
+    #[error(
+        "the flag does not match the format of platform `{0}` ({1}). use --force to add it anyway"
+    )]
+    FormatMismatch(String, String),
+    #[error("the flag is already captured at {0}")]
+    FlagAlreadyCaptured(String),
+    #[error("flag `{0}` is not found")]
+    FlagNotFound(String),
+    #[error("failed to read hosts")]
+    HostsError(HostsError),
+}
+
+#[derive(Error, Debug)]
+pub enum LootError {
+    #[error("no workspace is used now")]
+    NoCurrentWorkspace,
+    #[error("failed to access loot.json")]
+    DocumentError(DocumentError),
+    #[error("loot {0} is not found")]
+    LootNotFound(u32),
+    #[error("failed to read {0}")]
+    ReadError(PathBuf, io::Error),
+    #[error("failed to write {0}")]
+    WriteError(PathBuf, io::Error),
+    #[error("{0} already exists. use --force to overwrite it")]
+    OutputExists(PathBuf),
+    #[error("the stored copy {0} does not match the recorded sha256")]
+    HashMismatch(PathBuf),
+    #[error("failed to read hosts")]
+    HostsError(HostsError),
+}
+
+#[derive(Error, Debug)]
+pub enum ReportError {
+    #[error("no workspace is used now")]
+    NoCurrentWorkspace,
+    #[error("failed to read vars")]
+    VarsStoreError(VarsStoreError),
+    #[error("failed to read hosts")]
+    HostsError(HostsError),
+    #[error("failed to read credentials")]
+    CredsError(CredsError),
+    #[error("failed to read findings")]
+    FindingsError(FindingsError),
+    #[error("failed to read flags")]
+    FlagsError(FlagsError),
+    #[error("failed to read the note {0}")]
+    ReadNoteError(PathBuf, io::Error),
+    #[error("failed to read the template {0}")]
+    ReadTemplateError(PathBuf, io::Error),
+    #[error("failed to render the template")]
+    TemplateError(tera::Error),
+    #[error("failed to write {0}")]
+    WriteError(PathBuf, io::Error),
+}
+
+// vrや {{query}} で参照するワークスペースのデータ (store::viewを参照)
+#[derive(Error, Debug)]
+pub enum ViewError {
+    #[error("failed to read hosts")]
+    HostsError(HostsError),
+    #[error("failed to read credentials")]
+    CredsError(CredsError),
+}
+
+#[derive(Error, Debug)]
+pub enum ImportError {
+    #[error("failed to read the file")]
+    ReadError(io::Error),
+    #[error("failed to parse the xml")]
+    ParseXmlError(roxmltree::Error),
+    #[error("the file is not an xml output of nmap (-oX)")]
+    NotNmapXml,
+    #[error("failed to parse the json")]
+    ParseJsonError(serde_json::Error),
+    #[error("failed to parse the json at line {0}")]
+    ParseJsonLineError(usize, serde_json::Error),
+    #[error("the file is not a json output of ffuf (-of json)")]
+    NotFfufJson,
+    #[error("`{0}` is not a valid url")]
+    InvalidUrl(String, url::ParseError),
+    #[error("the output contains paths. give the base url with --url")]
+    BaseUrlRequired,
+    #[error("failed to update hosts")]
+    HostsError(HostsError),
+}
+
+#[derive(Error, Debug)]
+pub enum VarsSearchError {
+    #[error("the pattern is not a valid regular expression")]
+    InvalidPattern(regex::Error),
+}
+
+#[derive(Error, Debug)]
+pub enum VarsSchemaError {
+    #[error("failed to read vars.schema.json")]
+    ReadError(io::Error),
+    #[error("vars.schema.json is not valid json")]
+    ParseError(serde_json::Error),
+    #[error("vars.schema.json is not a valid json schema: {0}")]
+    InvalidSchema(String),
+    #[error("the vars do not match vars.schema.json")]
+    Violation(Vec<Violation>),
+    #[error("`{0}` already exists")]
+    AlreadyExists(PathBuf),
+    #[error("failed to write vars.schema.json")]
+    WriteError(io::Error),
+}
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Error, Debug)]
+pub enum VarsFormatError {
+    #[error("{0}")]
+    JsonError(serde_json::Error),
+    #[error("{0}")]
+    YamlError(serde_yaml::Error),
+    #[error("{0}")]
+    TomlDeError(toml::de::Error),
+    #[error("{0}")]
+    TomlSerError(toml::ser::Error),
+    #[error("toml can not store null. remove the null values or use another format")]
+    TomlNullError,
+}
+
+#[derive(Error, Debug)]
+pub enum VarsEnvError {
+    #[error("failed to write the snippet")]
+    WriteError(io::Error),
+    #[error("failed to read the dotenv file")]
+    ReadError(io::Error),
+    #[error("line {0} of the dotenv file is not in the form of KEY=VALUE")]
+    InvalidDotenvLine(usize),
+    #[error("`{0}` can not be converted to a json query")]
+    InvalidName(String),
+    #[error("more than one name is converted to `{0}`")]
+    NameCollision(String),
+    #[error("`{0}` on line {1} of the dotenv file is already defined")]
+    DuplicateDotenvName(String, usize),
+}
+
+#[derive(Error, Debug)]
+pub enum SecretError {
+    #[error("secrets are locked. run `unlock` first")]
+    Locked,
+    #[error("failed to read secret.toml")]
+    ReadConfError(io::Error),
+    #[error("failed to write secret.toml")]
+    WriteConfError(io::Error),
+    #[error("secret.toml is broken")]
+    InvalidConf,
+    #[error("failed to read secret.key")]
+    ReadKeyFileError(io::Error),
+    #[error("secret.key must contain a 32 byte key written in hex")]
+    InvalidKeyFile,
+    #[error("failed to read the passphrase")]
+    ReadPassphraseError(io::Error),
+    #[error("the passphrases do not match")]
+    PassphraseMismatch,
+    #[error("failed to derive a key from the passphrase: {0}")]
+    KeyDerivationError(String),
+    #[error("the passphrase or secret.key is wrong")]
+    WrongKey,
+    #[error("failed to encrypt the secret value")]
+    EncryptError,
+    #[error("the secret value is broken")]
+    InvalidFormat,
+    #[error("failed to decrypt the secret value. it may be encrypted with another key")]
+    DecryptError,
+}
+
+#[derive(Error, Debug)]
+pub enum VarsHistoryError {
+    #[error("failed to read the vars history")]
+    ReadError(io::Error),
+    #[error("failed to parse the vars history")]
+    ParseError(serde_json::Error),
+    #[error("failed to write the vars history")]
+    WriteError(io::Error),
+    #[error("revision {0} is not found")]
+    RevisionNotFound(u64),
 }
 
 pub fn print_error(error: Error) {
     let p = |msg: String| println!("{} {}", error_prefix(), msg);
-    let _ = match error {
+    match error {
         Error::AppConfigError(e) => {
             p(e.to_string());
             match e {
+                AppConfigError::HomeDirNotFound => {}
                 AppConfigError::AppInitError(e) => {
                     p(e.to_string());
                     match e {
                         AppInitError::AppConfDirCreateError(e) => {
                             p(e.to_string());
                         }
-                        AppInitError::CheckAppConfPresenceError(e) => {
+                        AppInitError::CheckDynConfPresenceError(e) => {
+                            p(e.to_string());
+                        }
+                        AppInitError::CheckShellHistPresenceError(e) => {
+                            p(e.to_string());
+                        }
+                        AppInitError::ReadDynConfError(e) => {
+                            p(e.to_string());
+                            match e {
+                                ReadDynConfError::LockError(e) => {
+                                    p(e.to_string());
+                                }
+                                ReadDynConfError::OpenError(e) => {
+                                    p(e.to_string());
+                                }
+                                ReadDynConfError::ReadError(e) => {
+                                    p(e.to_string());
+                                }
+                                ReadDynConfError::ParseError(e) => {
+                                    p(e.to_string());
+                                }
+                            }
+                        }
+                        AppInitError::BackupDynConfError(e) => {
                             p(e.to_string());
                         }
                         AppInitError::DynConfInitError(e) => {
@@ -139,10 +670,12 @@ pub fn print_error(error: Error) {
                                 }
                             }
                         }
-                        AppInitError::InitAlreadyDone => {}
                         AppInitError::ShellHistInitError(e) => {
                             p(e.to_string());
                             match e {
+                                ShellHistInitError::CreateDirError(e) => {
+                                    p(e.to_string());
+                                }
                                 ShellHistInitError::CreateError(e) => {
                                     p(e.to_string());
                                 }
@@ -150,26 +683,15 @@ pub fn print_error(error: Error) {
                         }
                     }
                 }
-                AppConfigError::ReadDynConfError(e) => {
-                    p(e.to_string());
-                    match e {
-                        ReadDynConfError::OpenError(e) => {
-                            p(e.to_string());
-                        }
-                        ReadDynConfError::ReadError(e) => {
-                            p(e.to_string());
-                        }
-                        ReadDynConfError::ParseError(e) => {
-                            p(e.to_string());
-                        }
-                    }
-                }
             }
         }
         Error::UseCurrentDirAsWorkspaceError(e) => {
             p(e.to_string());
             match e {
                 UseCurrentDirAsWorkspaceError::BeforeInitError => {}
+                UseCurrentDirAsWorkspaceError::MigrateWorkspaceError(e) => {
+                    print_migrate_workspace_error(&p, e);
+                }
                 UseCurrentDirAsWorkspaceError::CheckMgrPresenceError(e) => {
                     p(e.to_string());
                 }
@@ -179,7 +701,7 @@ pub fn print_error(error: Error) {
                 UseCurrentDirAsWorkspaceError::UpdateDynConfFileError(e) => {
                     p(e.to_string());
                     match e {
-                        UpdateDynConfFileError::OpenError(e) => {
+                        UpdateDynConfFileError::LockError(e) => {
                             p(e.to_string());
                         }
                         UpdateDynConfFileError::ParseError(e) => {
@@ -211,6 +733,10 @@ pub fn print_error(error: Error) {
                         CreateNewWorkspaceError::WriteVarsFileError(e) => {
                             p(e.to_string());
                         }
+                        CreateNewWorkspaceError::WriteMetaError(e) => {
+                            p(e.to_string());
+                            print_write_workspace_meta_error(&p, e);
+                        }
                     }
                 }
                 InitCurrentDirAsWorkspaceError::GetCurrentDirError(e) => {
@@ -220,6 +746,9 @@ pub fn print_error(error: Error) {
                     p(e.to_string());
                     match e {
                         UseCurrentDirAsWorkspaceError::BeforeInitError => {}
+                        UseCurrentDirAsWorkspaceError::MigrateWorkspaceError(e) => {
+                            print_migrate_workspace_error(&p, e);
+                        }
                         UseCurrentDirAsWorkspaceError::CheckMgrPresenceError(e) => {
                             p(e.to_string());
                         }
@@ -229,7 +758,7 @@ pub fn print_error(error: Error) {
                         UseCurrentDirAsWorkspaceError::UpdateDynConfFileError(e) => {
                             p(e.to_string());
                             match e {
-                                UpdateDynConfFileError::OpenError(e) => {
+                                UpdateDynConfFileError::LockError(e) => {
                                     p(e.to_string());
                                 }
                                 UpdateDynConfFileError::ParseError(e) => {
@@ -244,8 +773,551 @@ pub fn print_error(error: Error) {
                 }
             }
         }
+        Error::DoctorRepairError(e) => {
+            p(e.to_string());
+            match e {
+                DoctorRepairError::CreateDirError(e) => {
+                    p(e.to_string());
+                }
+                DoctorRepairError::BackupError(e) => {
+                    p(e.to_string());
+                }
+                DoctorRepairError::DynConfInitError(e) => {
+                    p(e.to_string());
+                    match e {
+                        DynConfInitError::CreateError(e) => {
+                            p(e.to_string());
+                        }
+                        DynConfInitError::ParseError(e) => {
+                            p(e.to_string());
+                        }
+                        DynConfInitError::WriteError(e) => {
+                            p(e.to_string());
+                        }
+                    }
+                }
+                DoctorRepairError::ShellHistInitError(e) => {
+                    p(e.to_string());
+                    match e {
+                        ShellHistInitError::CreateDirError(e) => {
+                            p(e.to_string());
+                        }
+                        ShellHistInitError::CreateError(e) => {
+                            p(e.to_string());
+                        }
+                    }
+                }
+                DoctorRepairError::SetPermissionError(e) => {
+                    p(e.to_string());
+                }
+                DoctorRepairError::ReadDynConfError(e) => {
+                    p(e.to_string());
+                    match e {
+                        ReadDynConfError::LockError(e) => {
+                            p(e.to_string());
+                        }
+                        ReadDynConfError::OpenError(e) => {
+                            p(e.to_string());
+                        }
+                        ReadDynConfError::ReadError(e) => {
+                            p(e.to_string());
+                        }
+                        ReadDynConfError::ParseError(e) => {
+                            p(e.to_string());
+                        }
+                    }
+                }
+                DoctorRepairError::UpdateDynConfFileError(e) => {
+                    p(e.to_string());
+                    match e {
+                        UpdateDynConfFileError::LockError(e) => {
+                            p(e.to_string());
+                        }
+                        UpdateDynConfFileError::ParseError(e) => {
+                            p(e.to_string());
+                        }
+                        UpdateDynConfFileError::WriteError(e) => {
+                            p(e.to_string());
+                        }
+                    }
+                }
+                DoctorRepairError::WriteVarsFileError(e) => {
+                    p(e.to_string());
+                }
+                DoctorRepairError::MigrateWorkspaceError(e) => {
+                    print_migrate_workspace_error(&p, e);
+                }
+            }
+        }
+        Error::WsExportError(e) => {
+            p(e.to_string());
+            match e {
+                WsExportError::NoCurrentWorkspace => {}
+                WsExportError::ReadError(e) => {
+                    p(e.to_string());
+                }
+                WsExportError::SerializeManifestError(e) | WsExportError::ParseCredsError(e) => {
+                    p(e.to_string());
+                }
+                WsExportError::TimelineError(e) => {
+                    print_timeline_error(&p, e);
+                }
+                WsExportError::VarsFormatError(e) => {
+                    p(e.to_string());
+                }
+                WsExportError::CreateArchiveError(e) => {
+                    p(e.to_string());
+                }
+                WsExportError::WriteArchiveError(e) => {
+                    p(e.to_string());
+                }
+            }
+        }
+        Error::WsImportError(e) => {
+            p(e.to_string());
+            match e {
+                WsImportError::OpenArchiveError(e) => {
+                    p(e.to_string());
+                }
+                WsImportError::ReadArchiveError(e) => {
+                    p(e.to_string());
+                }
+                WsImportError::ParseManifestError(e) => {
+                    p(e.to_string());
+                }
+                WsImportError::GetCurrentDirError(e) => {
+                    p(e.to_string());
+                }
+                WsImportError::ExtractError(e) => {
+                    p(e.to_string());
+                }
+                WsImportError::MigrateWorkspaceError(e) => {
+                    print_migrate_workspace_error(&p, e);
+                }
+                WsImportError::UpdateDynConfFileError(e) => {
+                    p(e.to_string());
+                    match e {
+                        UpdateDynConfFileError::LockError(e) => {
+                            p(e.to_string());
+                        }
+                        UpdateDynConfFileError::ParseError(e) => {
+                            p(e.to_string());
+                        }
+                        UpdateDynConfFileError::WriteError(e) => {
+                            p(e.to_string());
+                        }
+                    }
+                }
+                WsImportError::ManifestNotFound
+                | WsImportError::TooNewFormat(_)
+                | WsImportError::UnsafePath(_)
+                | WsImportError::UnsafeWorkspaceName(_)
+                | WsImportError::HashMismatch(_)
+                | WsImportError::MissingFile(_)
+                | WsImportError::UnexpectedFile(_)
+                | WsImportError::TargetAlreadyExists(_) => {}
+            }
+        }
+        Error::VarsStoreError(e) => {
+            print_vars_store_error(&p, e);
+        }
+        Error::VarsSchemaError(e) => {
+            print_vars_schema_error(&p, e);
+        }
+        Error::VarsMergeError(e) => {
+            p(e.to_string());
+            match e {
+                VarsMergeError::ReadFileError(e) => {
+                    p(e.to_string());
+                }
+                VarsMergeError::ParseFileError(e) => {
+                    p(e.to_string());
+                }
+                VarsMergeError::ReadWorkspaceError(_, e) => {
+                    print_vars_store_error(&p, *e);
+                }
+                VarsMergeError::SourceNotFound(_) => {}
+            }
+        }
+        Error::VarsHistoryError(e) => {
+            print_vars_history_error(&p, e);
+        }
+        Error::VarsEnvError(e) => {
+            p(e.to_string());
+            match e {
+                VarsEnvError::WriteError(e) | VarsEnvError::ReadError(e) => {
+                    p(e.to_string());
+                }
+                VarsEnvError::InvalidDotenvLine(_)
+                | VarsEnvError::InvalidName(_)
+                | VarsEnvError::NameCollision(_)
+                | VarsEnvError::DuplicateDotenvName(_, _) => {}
+            }
+        }
+        Error::SecretError(e) => {
+            p(e.to_string());
+            match e {
+                SecretError::ReadConfError(e)
+                | SecretError::WriteConfError(e)
+                | SecretError::ReadKeyFileError(e)
+                | SecretError::ReadPassphraseError(e) => {
+                    p(e.to_string());
+                }
+                SecretError::Locked
+                | SecretError::InvalidConf
+                | SecretError::InvalidKeyFile
+                | SecretError::PassphraseMismatch
+                | SecretError::KeyDerivationError(_)
+                | SecretError::WrongKey
+                | SecretError::EncryptError
+                | SecretError::InvalidFormat
+                | SecretError::DecryptError => {}
+            }
+        }
+        Error::VarsSearchError(e) => {
+            p(e.to_string());
+            match e {
+                VarsSearchError::InvalidPattern(e) => {
+                    p(e.to_string());
+                }
+            }
+        }
+        Error::HostsError(e) => {
+            print_hosts_error(&p, e);
+        }
+        Error::CredsError(e) => {
+            print_creds_error(&p, e);
+        }
+        Error::ViewError(e) => {
+            p(e.to_string());
+            match e {
+                ViewError::HostsError(e) => {
+                    print_hosts_error(&p, e);
+                }
+                ViewError::CredsError(e) => {
+                    print_creds_error(&p, e);
+                }
+            }
+        }
+        Error::NotesError(e) => {
+            p(e.to_string());
+            match e {
+                NotesError::HostsError(e) => {
+                    print_hosts_error(&p, e);
+                }
+                NotesError::ReadError(e) | NotesError::WriteError(e) => {
+                    p(e.to_string());
+                }
+                NotesError::EditorError(e) => {
+                    p(e);
+                }
+                NotesError::InvalidPattern(e) => {
+                    p(e.to_string());
+                }
+                NotesError::NoCurrentWorkspace => {}
+            }
+        }
+        Error::TimelineError(e) => {
+            print_timeline_error(&p, e);
+        }
+        Error::FindingsError(e) => {
+            print_findings_error(&p, e);
+        }
+        Error::FlagsError(e) => {
+            print_flags_error(&p, e);
+        }
+        Error::LootError(e) => {
+            print_loot_error(&p, e);
+        }
+        Error::ReportError(e) => {
+            p(e.to_string());
+            match e {
+                ReportError::VarsStoreError(e) => {
+                    print_vars_store_error(&p, e);
+                }
+                ReportError::HostsError(e) => {
+                    print_hosts_error(&p, e);
+                }
+                ReportError::CredsError(e) => {
+                    print_creds_error(&p, e);
+                }
+                ReportError::FindingsError(e) => {
+                    print_findings_error(&p, e);
+                }
+                ReportError::FlagsError(e) => {
+                    print_flags_error(&p, e);
+                }
+                ReportError::ReadNoteError(_, e)
+                | ReportError::ReadTemplateError(_, e)
+                | ReportError::WriteError(_, e) => {
+                    p(e.to_string());
+                }
+                ReportError::TemplateError(e) => {
+                    // Teraのエラーは原因をたどらないと、どこが間違っているのか分からない
+                    p(e.to_string());
+                    let mut source = std::error::Error::source(&e);
+                    while let Some(s) = source {
+                        p(s.to_string());
+                        source = s.source();
+                    }
+                }
+                ReportError::NoCurrentWorkspace => {}
+            }
+        }
+        Error::ImportError(e) => {
+            p(e.to_string());
+            match e {
+                ImportError::ReadError(e) => {
+                    p(e.to_string());
+                }
+                ImportError::ParseXmlError(e) => {
+                    p(e.to_string());
+                }
+                ImportError::HostsError(e) => {
+                    print_hosts_error(&p, e);
+                }
+                ImportError::ParseJsonError(e) => {
+                    p(e.to_string());
+                }
+                ImportError::ParseJsonLineError(_, e) => {
+                    p(e.to_string());
+                }
+                ImportError::InvalidUrl(_, e) => {
+                    p(e.to_string());
+                }
+                ImportError::NotNmapXml
+                | ImportError::NotFfufJson
+                | ImportError::BaseUrlRequired => {}
+            }
+        }
         Error::JsonQueryError(e) => {
             p(e.to_string());
         }
     };
 }
+
+fn print_migrate_workspace_error(p: &impl Fn(String), e: MigrateWorkspaceError) {
+    p(e.to_string());
+    match e {
+        MigrateWorkspaceError::ReadMetaError(e) => {
+            p(e.to_string());
+            match e {
+                ReadWorkspaceMetaError::ReadError(e) => {
+                    p(e.to_string());
+                }
+                ReadWorkspaceMetaError::ParseError(e) => {
+                    p(e.to_string());
+                }
+            }
+        }
+        MigrateWorkspaceError::TooNewSchema(_) => {}
+        MigrateWorkspaceError::BackupError(e) => {
+            p(e.to_string());
+        }
+        MigrateWorkspaceError::ApplyError(_, e) => {
+            p(e.to_string());
+        }
+        MigrateWorkspaceError::WriteMetaError(e) => {
+            p(e.to_string());
+            print_write_workspace_meta_error(p, e);
+        }
+    }
+}
+
+fn print_write_workspace_meta_error(p: &impl Fn(String), e: WriteWorkspaceMetaError) {
+    match e {
+        WriteWorkspaceMetaError::ParseError(e) => {
+            p(e.to_string());
+        }
+        WriteWorkspaceMetaError::WriteError(e) => {
+            p(e.to_string());
+        }
+    }
+}
+
+fn print_vars_store_error(p: &impl Fn(String), e: VarsStoreError) {
+    p(e.to_string());
+    match e {
+        VarsStoreError::LockError(e)
+        | VarsStoreError::ReadError(e)
+        | VarsStoreError::WriteError(e) => {
+            p(e.to_string());
+        }
+        VarsStoreError::ParseError(e) | VarsStoreError::SerializeError(e) => {
+            p(e.to_string());
+        }
+        VarsStoreError::NoCurrentWorkspace
+        | VarsStoreError::Conflict(_)
+        | VarsStoreError::AlreadyInFormat(_) => {}
+        VarsStoreError::SchemaError(e) => {
+            print_vars_schema_error(p, e);
+        }
+    }
+}
+
+fn print_timeline_error(p: &impl Fn(String), e: TimelineError) {
+    p(e.to_string());
+    match e {
+        TimelineError::ReadError(e) | TimelineError::WriteError(e) => {
+            p(e.to_string());
+        }
+        TimelineError::ParseError(_, e) | TimelineError::SerializeError(e) => {
+            p(e.to_string());
+        }
+        TimelineError::NoCurrentWorkspace | TimelineError::InvalidTime(_) => {}
+    }
+}
+
+fn print_vars_history_error(p: &impl Fn(String), e: VarsHistoryError) {
+    p(e.to_string());
+    match e {
+        VarsHistoryError::ReadError(e) | VarsHistoryError::WriteError(e) => {
+            p(e.to_string());
+        }
+        VarsHistoryError::ParseError(e) => {
+            p(e.to_string());
+        }
+        VarsHistoryError::RevisionNotFound(_) => {}
+    }
+}
+
+fn print_vars_schema_error(p: &impl Fn(String), e: VarsSchemaError) {
+    p(e.to_string());
+    match e {
+        VarsSchemaError::ReadError(e) | VarsSchemaError::WriteError(e) => {
+            p(e.to_string());
+        }
+        VarsSchemaError::ParseError(e) => {
+            p(e.to_string());
+        }
+        VarsSchemaError::Violation(violations) => {
+            for v in violations {
+                p(format!("{}: {} (rule: {})", v.path, v.message, v.rule));
+            }
+        }
+        VarsSchemaError::InvalidSchema(_) | VarsSchemaError::AlreadyExists(_) => {}
+    }
+}
+
+fn print_document_error(p: &impl Fn(String), e: DocumentError) {
+    p(e.to_string());
+    match e {
+        DocumentError::LockError(e)
+        | DocumentError::ReadError(e)
+        | DocumentError::WriteError(e) => {
+            p(e.to_string());
+        }
+        DocumentError::ParseError(e) | DocumentError::SerializeError(e) => {
+            p(e.to_string());
+        }
+    }
+}
+
+fn print_findings_error(p: &impl Fn(String), e: FindingsError) {
+    p(e.to_string());
+    match e {
+        FindingsError::DocumentError(e) => {
+            print_document_error(p, e);
+        }
+        FindingsError::HostsError(e) => {
+            print_hosts_error(p, e);
+        }
+        FindingsError::CredsError(e) => {
+            print_creds_error(p, e);
+        }
+        FindingsError::ReadEvidenceError(_, e) | FindingsError::WriteEvidenceError(_, e) => {
+            p(e.to_string());
+        }
+        FindingsError::EditorError(e) => {
+            p(e);
+        }
+        FindingsError::NoCurrentWorkspace
+        | FindingsError::FindingNotFound(_)
+        | FindingsError::InvalidCvss(_) => {}
+    }
+}
+
+fn print_creds_error(p: &impl Fn(String), e: CredsError) {
+    p(e.to_string());
+    match e {
+        CredsError::DocumentError(e) => {
+            print_document_error(p, e);
+        }
+        CredsError::ReadFindingsError(e) => {
+            print_document_error(p, e);
+        }
+        CredsError::HostsError(e) => {
+            print_hosts_error(p, e);
+        }
+        CredsError::SecretError(e) => {
+            p(e.to_string());
+        }
+        CredsError::WriteError(e) => {
+            p(e.to_string());
+        }
+        CredsError::NoCurrentWorkspace
+        | CredsError::CredNotFound(_)
+        | CredsError::CredAlreadyExists(_)
+        | CredsError::Locked
+        | CredsError::CredInUse(..) => {}
+    }
+}
+
+fn print_hosts_error(p: &impl Fn(String), e: HostsError) {
+    p(e.to_string());
+    match e {
+        HostsError::DocumentError(e) => {
+            print_document_error(p, e);
+        }
+        HostsError::NoCurrentWorkspace
+        | HostsError::HostNotFound(_)
+        | HostsError::HostAlreadyExists(_)
+        | HostsError::InvalidAddress(_)
+        | HostsError::InvalidPort(_) => {}
+    }
+}
+
+fn print_flags_error(p: &impl Fn(String), e: FlagsError) {
+    p(e.to_string());
+    match e {
+        FlagsError::DocumentError(e) => {
+            print_document_error(p, e);
+        }
+        FlagsError::ReadFormatsError(_, e) => {
+            p(e.to_string());
+        }
+        FlagsError::ParseFormatsError(_, e) => {
+            p(e.to_string());
+        }
+        FlagsError::InvalidFormat(_, e) => {
+            p(e.to_string());
+        }
+        FlagsError::HostsError(e) => {
+            print_hosts_error(p, e);
+        }
+        FlagsError::NoCurrentWorkspace
+        | FlagsError::UnknownPlatform(_)
+        | FlagsError::FormatMismatch(_, _)
+        | FlagsError::FlagAlreadyCaptured(_)
+        | FlagsError::FlagNotFound(_) => {}
+    }
+}
+
+fn print_loot_error(p: &impl Fn(String), e: LootError) {
+    p(e.to_string());
+    match e {
+        LootError::DocumentError(e) => {
+            print_document_error(p, e);
+        }
+        LootError::ReadError(_, e) | LootError::WriteError(_, e) => {
+            p(e.to_string());
+        }
+        LootError::HostsError(e) => {
+            print_hosts_error(p, e);
+        }
+        LootError::NoCurrentWorkspace
+        | LootError::LootNotFound(_)
+        | LootError::OutputExists(_)
+        | LootError::HashMismatch(_) => {}
+    }
+}
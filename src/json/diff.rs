@@ -0,0 +1,60 @@
+use serde_json::Value;
+
+// 2つのjsonの違いを、vrで使えるクエリのパスごとに列挙する
+// オブジェクトはキーごと、配列は要素ごとに比較する
+pub enum Change {
+    Added(String, Value),
+    Removed(String, Value),
+    Modified(String, Value, Value),
+}
+
+pub fn diff(old: &Value, new: &Value) -> Vec<Change> {
+    let mut changes = vec![];
+    diff_(old, new, "", &mut changes);
+    changes
+}
+
+fn diff_(old: &Value, new: &Value, path: &str, changes: &mut Vec<Change>) {
+    if old == new {
+        return;
+    }
+    match (old, new) {
+        (Value::Object(o), Value::Object(n)) => {
+            for (k, v) in o {
+                let child = child_path(path, k);
+                match n.get(k) {
+                    Some(nv) => diff_(v, nv, &child, changes),
+                    None => changes.push(Change::Removed(child, v.clone())),
+                }
+            }
+            for (k, v) in n {
+                if !o.contains_key(k) {
+                    changes.push(Change::Added(child_path(path, k), v.clone()));
+                }
+            }
+        }
+        (Value::Array(o), Value::Array(n)) if !path.is_empty() => {
+            for i in 0..o.len().max(n.len()) {
+                let child = format!("{}[{}]", path, i);
+                match (o.get(i), n.get(i)) {
+                    (Some(ov), Some(nv)) => diff_(ov, nv, &child, changes),
+                    (Some(ov), None) => changes.push(Change::Removed(child, ov.clone())),
+                    (None, Some(nv)) => changes.push(Change::Added(child, nv.clone())),
+                    (None, None) => (),
+                }
+            }
+        }
+        _ => {
+            let path = if path.is_empty() { "." } else { path };
+            changes.push(Change::Modified(path.to_string(), old.clone(), new.clone()))
+        }
+    }
+}
+
+fn child_path(parent: &str, key: &str) -> String {
+    if parent.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", parent, key)
+    }
+}
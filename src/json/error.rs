@@ -14,4 +14,12 @@ pub enum JsonQueryError {
     NotEndWithBracket,
     #[error("query is empty")]
     EmptyQuery,
+    #[error("a value on the query path is not an object")]
+    NotObject,
+    #[error("the template has {{{{ without closing }}}}")]
+    UnclosedTemplate,
+    #[error("the template refers to itself: {0}")]
+    CircularReference(String),
+    #[error("failed to resolve `{0}` in a template: {1}")]
+    TemplateError(String, Box<JsonQueryError>),
 }
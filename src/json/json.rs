@@ -15,24 +15,115 @@ pub struct Json {
 }
 
 impl Json {
-    pub fn new(data: &String) -> Self {
-        Json {
-            data: serde_json::from_str::<Value>(data).unwrap(),
+    // 文字列をパースしてJsonを作る。不正なjsonの場合はエラーを返す
+    pub fn new(data: &str) -> Result<Self, serde_json::Error> {
+        Ok(Json {
+            data: serde_json::from_str::<Value>(data)?,
+        })
+    }
+
+    // 指定された場所の値を、文字列の中の {{query}} を参照先の値で置き換えてから返す関数 (派生した変数)
+    // url = "http://{{ip}}:{{ports.http}}/" のような値は読むたびに評価されるので、ipを変えればurlも変わる
+    // 文字列全体が1つの {{query}} である場合は、参照先の値を型もそのまま返す
+    // textは埋め込む値を文字列にする関数で、Noneを返した値は文字列ならそのまま、それ以外はjsonとして埋め込む
+    pub fn refer(
+        &mut self,
+        query: &String,
+        text: &dyn Fn(&Value) -> Option<String>,
+    ) -> Result<Value, JsonQueryError> {
+        let mut stack = vec![];
+        self.evaluate_query(query, text, &mut stack)
+    }
+
+    // vars以外の文字列の中の {{query}} を置き換える関数 (vi)
+    pub fn render(
+        &mut self,
+        template: &str,
+        text: &dyn Fn(&Value) -> Option<String>,
+    ) -> Result<String, JsonQueryError> {
+        let mut stack = vec![];
+        let v = self.render_(template, text, &mut stack)?;
+        Ok(embed(&v, text))
+    }
+
+    // stackは評価中のクエリで、同じクエリをもう一度評価しようとしたら循環参照のエラーにする
+    fn evaluate_query(
+        &mut self,
+        query: &String,
+        text: &dyn Fn(&Value) -> Option<String>,
+        stack: &mut Vec<String>,
+    ) -> Result<Value, JsonQueryError> {
+        if stack.contains(query) {
+            let mut chain = stack.clone();
+            chain.push(query.clone());
+            return Err(JsonQueryError::CircularReference(chain.join(" -> ")));
         }
+        let v = self.get(query)?;
+        stack.push(query.clone());
+        let result = self.evaluate(&v, text, stack);
+        stack.pop();
+        result
     }
 
-    pub fn refer(&mut self, query: &String) -> Result<String, JsonQueryError> {
-        let v = self.refer_(query, RefPurpose::Ref)?;
-        match v {
-            Value::String(v) => Ok(v.to_string()),
-            _ => Ok(v.to_string()),
+    fn evaluate(
+        &mut self,
+        value: &Value,
+        text: &dyn Fn(&Value) -> Option<String>,
+        stack: &mut Vec<String>,
+    ) -> Result<Value, JsonQueryError> {
+        match value {
+            Value::String(s) if s.contains("{{") => self.render_(s, text, stack),
+            Value::Object(map) => {
+                let mut evaluated = serde_json::Map::new();
+                for (k, v) in map {
+                    evaluated.insert(k.clone(), self.evaluate(v, text, stack)?);
+                }
+                Ok(Value::Object(evaluated))
+            }
+            Value::Array(arr) => {
+                let mut evaluated = vec![];
+                for v in arr {
+                    evaluated.push(self.evaluate(v, text, stack)?);
+                }
+                Ok(Value::Array(evaluated))
+            }
+            v => Ok(v.clone()),
         }
     }
 
-    pub fn delete(&mut self, path: &String) -> Result<(), JsonQueryError> {
-        let locate_vec = path.split('.').collect::<Vec<&str>>();
+    fn render_(
+        &mut self,
+        template: &str,
+        text: &dyn Fn(&Value) -> Option<String>,
+        stack: &mut Vec<String>,
+    ) -> Result<Value, JsonQueryError> {
+        let mut result = String::new();
+        let mut rest = template;
+        while let Some(start) = rest.find("{{") {
+            let end = match rest[start..].find("}}") {
+                Some(e) => start + e,
+                None => return Err(JsonQueryError::UnclosedTemplate),
+            };
+            let query = rest[start + 2..end].trim().to_string();
+            let v = match self.evaluate_query(&query, text, stack) {
+                Ok(v) => v,
+                Err(e @ JsonQueryError::CircularReference(_))
+                | Err(e @ JsonQueryError::TemplateError(..)) => return Err(e),
+                Err(e) => return Err(JsonQueryError::TemplateError(query, Box::new(e))),
+            };
+            // 文字列全体が1つの {{query}} なら値をそのまま返す
+            if start == 0 && end + 2 == template.len() {
+                return Ok(v);
+            }
+            result.push_str(&rest[..start]);
+            result.push_str(&embed(&v, text));
+            rest = &rest[end + 2..];
+        }
+        result.push_str(rest);
+        Ok(Value::String(result))
+    }
 
-        let last_locate = locate_vec.get(locate_vec.len() - 1).unwrap();
+    pub fn delete(&mut self, path: &str) -> Result<(), JsonQueryError> {
         // {
         //      "aaa": {
         //          *locate*
@@ -40,20 +131,20 @@ impl Json {
         //          "ddd": "eee"
         //      }
         // {
-        // prepare a mutable reference of parent of target, update parent with copy of parent that doesn't contain target
-        // with above example(target is bbb), prepare the mutable reference of aaa, delete bbb from copy of aaa, update aaa with copy of it
+        // prepare a mutable reference of parent of target, and remove target from parent
+        // with above example(target is bbb), prepare the mutable reference of aaa, and remove bbb from aaa
         // if that target of deletion is an element of an array, parent element is array that contains target
-        // 指定された要素の親要素の可変参照をrefer_を使って用意し、親要素のコピーの中から削除対象の要素を削除したオブジェクトで親要素を更新する
-        // 上の図でいうとキー名bbbが削除対象である場合、aaaの値を取得して、そのなかからキー名がbbbの要素を削除したものでaaaを更新する
+        // 指定された要素の親要素の可変参照をrefer_tokensを使って用意し、親要素から削除対象の要素を取り除く
+        // 上の図でいうとキー名bbbが削除対象である場合、aaaの可変参照を取得して、そのなかからキー名がbbbの要素を取り除く
         // 削除対象が配列の一要素である場合は、親要素は対象を含んでいる配列全体になる
+        // 親要素や削除対象が存在しない場合はNotFoundを返し、dataは変更しない
         //
-        // 削除対象が配列の要素かどうかを、パスの最後の部分(aaa.bbb.ccc[1]ならccc[1])が"["と"]"を含んでいるかどうかで判定している
-        // したがって、要素の名前に大括弧を含むことはできない。
-        // determining whether a target of deletion is an element of array with ...
-        // knowing whether the last part of location (i.e aaa.bbb.ccc[1] -> ccc[1]) contains both [ and ],
-        // both [ and ] can't be contained by name of element
-        if last_locate.contains(&"[") && last_locate.contains(&"]") {
-            let token = last_locate;
+        // クエリはrefer_と同じようにsplit_queryで区切るので、hosts["dc01.corp.local"] のように ["..."] で書いたキーも削除できる
+        // 削除対象が配列の要素かどうかは、パスの最後の部分(aaa.bbb.ccc[1]ならccc[1])が ["..."] で書かれておらず "[" を含んでいるかで判定する
+        let mut tokens = validate_query(path)?;
+        let (last, quoted) = tokens.pop().unwrap_or_default();
+        if !quoted && is_contain_list_ref(&last) {
+            let token = last.as_str();
             if unclosed_bracket(token) {
                 return Err(JsonQueryError::UnclosedBracket);
             }
@@ -63,47 +154,50 @@ impl Json {
             if not_ends_with_bracket(token) {
                 return Err(JsonQueryError::NotEndWithBracket);
             }
-            let iter = token.split('[').collect::<Vec<&str>>();
-            let name = iter[0];
-            let index = iter[1].split(']').collect::<Vec<&str>>()[0]
-                .to_string()
+            parse_list_ref(token)?;
+            // 最後の添字より前が親の配列になる。a[0][1] なら a[0]
+            let (name, index) = token.rsplit_once('[').unwrap_or_default();
+            let index = index
+                .trim_end_matches(']')
                 .parse::<usize>()
-                .unwrap();
-            let mut parent_locate = locate_vec.get(0..locate_vec.len() - 1).unwrap().to_vec();
-            parent_locate.push(name);
-            let parent_locate = parent_locate.join(".");
-            let parent = self.refer_(&parent_locate, RefPurpose::Mod)?;
-            match parent.clone() {
-                Value::Array(arr) => {
-                    let mut new = arr;
-                    new.remove(index);
-                    *parent = Value::Array(new);
+                .unwrap_or_default();
+            if !name.is_empty() {
+                tokens.push((name.to_string(), false));
+            } else if tokens.is_empty() {
+                return Err(JsonQueryError::NotFound);
+            }
+            // 存在しない親を作ってしまわないように、親要素は閲覧と同じように参照する
+            match self.refer_tokens(&tokens, RefPurpose::Ref)? {
+                Value::Array(arr) if index < arr.len() => {
+                    arr.remove(index);
+                    Ok(())
                 }
-                _ => (),
+                _ => Err(JsonQueryError::NotFound),
             }
-            Ok(())
         } else {
-            let parent_locate = locate_vec.get(0..locate_vec.len() - 1).unwrap().join(".");
-            let dest_name = locate_vec
-                .get(locate_vec.len() - 1..locate_vec.len())
-                .unwrap()
-                .join("");
-            let parent = self.refer_(&parent_locate, RefPurpose::Mod)?;
-            if let Value::Object(map) = parent.clone() {
-                let mut new_map = serde_json::Map::new();
-                for (k, v) in map {
-                    if k == dest_name {
-                        continue;
-                    }
-                    new_map.insert(k, v);
+            // トップレベルの要素を削除する場合は、親要素はルートになる
+            match self.refer_tokens(&tokens, RefPurpose::Ref)? {
+                Value::Object(map) if map.contains_key(&last) => {
+                    map.remove(&last);
+                    Ok(())
                 }
-                let new_child = Value::from(new_map);
-                *parent = new_child;
+                _ => Err(JsonQueryError::NotFound),
             }
-            Ok(())
         }
     }
 
+    // 指定された場所の値をそのまま返す関数
+    pub fn get(&mut self, query: &String) -> Result<Value, JsonQueryError> {
+        Ok(self.refer_(query, RefPurpose::Ref)?.clone())
+    }
+
+    // modifyと同じだが、文字列から変換せずにValueをそのまま書き込む
+    pub fn set(&mut self, locate: &String, value: Value) -> Result<(), JsonQueryError> {
+        let dest = self.refer_(locate, RefPurpose::Mod)?;
+        *dest = value;
+        Ok(())
+    }
+
     pub fn modify(&mut self, locate: &String, raw_value: &String) -> Result<(), JsonQueryError> {
         let value = to_value(raw_value);
         let dest = self.refer_(locate, RefPurpose::Mod)?;
@@ -122,46 +216,71 @@ impl Json {
         path: &String,
         ref_purpose: RefPurpose,
     ) -> Result<&mut Value, JsonQueryError> {
-        let mut current = &mut self.data;
-        if path.to_string() == ".".to_string() {
-            return Ok(current);
-        }
-        if path.is_empty() {
-            return Err(JsonQueryError::EmptyQuery);
-        }
-        if start_or_end_by_dot(&path) {
-            return Err(JsonQueryError::StartOrEndByDot);
+        if *path == "." {
+            return Ok(&mut self.data);
         }
-        let query = path.split('.').collect::<Vec<&str>>();
-        for token in query {
-            if is_contain_list_ref(token) {
-                if unclosed_bracket(&token) {
+        let query = validate_query(path)?;
+        self.refer_tokens(&query, ref_purpose)
+    }
+
+    // refer_と同じだが、split_queryで区切ったクエリを受け取る。空ならルートを返す
+    fn refer_tokens(
+        &mut self,
+        query: &[(String, bool)],
+        ref_purpose: RefPurpose,
+    ) -> Result<&mut Value, JsonQueryError> {
+        let mut current = &mut self.data;
+        for (i, (token, quoted)) in query.iter().enumerate() {
+            let token = token.as_str();
+            if !quoted && is_contain_list_ref(token) {
+                if unclosed_bracket(token) {
                     return Err(JsonQueryError::UnclosedBracket);
                 }
-                if not_num_in_bracket(&token) {
+                if not_num_in_bracket(token) {
                     return Err(JsonQueryError::NotNumInBracket);
                 }
-                if not_ends_with_bracket(&token) {
+                if not_ends_with_bracket(token) {
                     return Err(JsonQueryError::NotEndWithBracket);
                 }
-                let iter = token.split('[').collect::<Vec<&str>>();
-                let name = iter[0];
-                let index = iter[1].split(']').collect::<Vec<&str>>()[0]
-                    .to_string()
-                    .parse::<usize>()
-                    .unwrap();
-                if current.get(name).is_none() {
-                    match ref_purpose {
-                        RefPurpose::Ref => {
-                            return Err(JsonQueryError::NotFound);
+                let (name, indexes) = parse_list_ref(token)?;
+                // hosts["dc01"][0] のように ["..."] の後ろに続く [0] は、直前の値の要素を指す
+                let mut list = if name.is_empty() && i > 0 {
+                    current
+                } else {
+                    if current.get(name).is_none() {
+                        match ref_purpose {
+                            RefPurpose::Ref => {
+                                return Err(JsonQueryError::NotFound);
+                            }
+                            RefPurpose::Mod => {
+                                if !current.is_object() && !current.is_null() {
+                                    return Err(JsonQueryError::NotObject);
+                                }
+                                current[name] = Value::Null;
+                            }
                         }
-                        RefPurpose::Mod => {
-                            current[name] = Value::Null;
+                    }
+                    current.get_mut(name).unwrap()
+                };
+                // a[0][1] のように続けて書いた添字は、順に配列の中をたどる
+                for index in indexes {
+                    // 改変のときは、配列の末尾の次の要素を指定すると新しい要素を追加する
+                    if let RefPurpose::Mod = ref_purpose {
+                        if list.is_null() {
+                            *list = Value::Array(vec![]);
+                        }
+                        if let Value::Array(arr) = list {
+                            if arr.len() == index {
+                                arr.push(Value::Null);
+                            }
                         }
                     }
+                    list = match list.get_mut(index) {
+                        Some(next) => next,
+                        None => return Err(JsonQueryError::NotFound),
+                    };
                 }
-                let next = current.get_mut(name).unwrap().get_mut(index).unwrap();
-                current = next;
+                current = list;
             } else {
                 if current.get(token).is_none() {
                     match ref_purpose {
@@ -169,6 +288,9 @@ impl Json {
                             return Err(JsonQueryError::NotFound);
                         }
                         RefPurpose::Mod => {
+                            if !current.is_object() && !current.is_null() {
+                                return Err(JsonQueryError::NotObject);
+                            }
                             current[token] = Value::Null;
                         }
                     }
@@ -180,10 +302,18 @@ impl Json {
     }
 }
 
-fn start_or_end_by_dot(query: &String) -> bool {
-    let start = query.get(0..1).unwrap();
-    let end = query.get(query.len() - 1..query.len()).unwrap();
-    start == "." || end == "."
+fn embed(value: &Value, text: &dyn Fn(&Value) -> Option<String>) -> String {
+    match text(value) {
+        Some(t) => t,
+        None => match value {
+            Value::String(s) => s.clone(),
+            v => v.to_string(),
+        },
+    }
+}
+
+fn start_or_end_by_dot(query: &str) -> bool {
+    query.starts_with('.') || query.ends_with('.')
 }
 
 fn not_num_in_bracket(token: &str) -> bool {
@@ -195,15 +325,177 @@ fn not_num_in_bracket(token: &str) -> bool {
 }
 
 fn not_ends_with_bracket(token: &str) -> bool {
-    "]" != token.get(token.len() - 1..token.len()).unwrap()
+    !token.ends_with(']')
 }
 
 fn unclosed_bracket(token: &str) -> bool {
     !token.contains(']')
 }
 
+// クエリの形を確かめて、split_queryで区切ったものを返す
+fn validate_query(path: &str) -> Result<Vec<(String, bool)>, JsonQueryError> {
+    if path.is_empty() {
+        return Err(JsonQueryError::EmptyQuery);
+    }
+    if start_or_end_by_dot(path) {
+        return Err(JsonQueryError::StartOrEndByDot);
+    }
+    split_query(path)
+}
+
+// クエリを . で区切る。hosts["dc01.corp.local"] のように ["..."] で書いたキーは . を含んでいてもよい
+// 返り値の bool は ["..."] で書かれたキーかどうか
+fn split_query(path: &str) -> Result<Vec<(String, bool)>, JsonQueryError> {
+    let mut tokens = vec![];
+    let mut current = String::new();
+    let mut after_quoted = false;
+    let mut chars = path.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '.' => {
+                // hosts["dc01"].ip の "] の後ろの . は区切りなので空のキーにしない
+                if !(after_quoted && current.is_empty()) {
+                    tokens.push((std::mem::take(&mut current), false));
+                }
+                after_quoted = false;
+            }
+            '[' if chars.peek() == Some(&'"') => {
+                chars.next();
+                if !current.is_empty() {
+                    tokens.push((std::mem::take(&mut current), false));
+                }
+                let mut key = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') if chars.peek() == Some(&']') => {
+                            chars.next();
+                            break;
+                        }
+                        Some(c) => key.push(c),
+                        None => return Err(JsonQueryError::UnclosedBracket),
+                    }
+                }
+                tokens.push((key, true));
+                after_quoted = true;
+            }
+            c => current.push(c),
+        }
+    }
+    if !(after_quoted && current.is_empty()) {
+        tokens.push((current, false));
+    }
+    Ok(tokens)
+}
+
+// creds[0] や a[0][1] を名前と添字に分ける
+fn parse_list_ref(token: &str) -> Result<(&str, Vec<usize>), JsonQueryError> {
+    let mut iter = token.split('[');
+    let name = iter.next().unwrap_or_default();
+    let mut indexes = vec![];
+    for c in iter {
+        match c.strip_suffix(']').and_then(|n| n.parse::<usize>().ok()) {
+            Some(i) => indexes.push(i),
+            None => return Err(JsonQueryError::NotNumInBracket),
+        }
+    }
+    Ok((name, indexes))
+}
+
+// コマンドの引数を空白で区切る。クエリの ["..."] の中の空白では区切らない
+// vr hosts["web 01"].ip のように、空白を含むキーも1つの引数として渡せる
+pub fn split_args(args: &str) -> Vec<&str> {
+    let mut words = vec![];
+    let mut start = None;
+    let mut in_quoted = false;
+    let bytes = args.as_bytes();
+    for (i, c) in args.char_indices() {
+        if in_quoted {
+            if c == '"' && bytes.get(i + 1) == Some(&b']') {
+                in_quoted = false;
+            }
+            continue;
+        }
+        if c.is_whitespace() {
+            if let Some(s) = start.take() {
+                words.push(&args[s..i]);
+            }
+            continue;
+        }
+        if start.is_none() {
+            start = Some(i);
+        }
+        if c == '[' && bytes.get(i + 1) == Some(&b'"') {
+            in_quoted = true;
+        }
+    }
+    if let Some(s) = start {
+        words.push(&args[s..]);
+    }
+    words
+}
+
+// コマンドの引数をシェルのように空白で区切る。'...' と "..." の中の空白では区切らない
+// cred add --pass "a b" のように、空白や引用符を含む値を1つの引数として渡せる
+// 区切った引数は引用符を残したままなので、値として使うときはunquoteで外す (履歴に残すときに元の形で伏せられるように)
+// 閉じていない引用符は行の最後までとする
+pub fn split_quoted_args(args: &str) -> Vec<&str> {
+    let mut words = vec![];
+    let mut start = None;
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in args.char_indices() {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if q == '"' && c == '\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        if c.is_whitespace() {
+            if let Some(s) = start.take() {
+                words.push(&args[s..i]);
+            }
+            continue;
+        }
+        if start.is_none() {
+            start = Some(i);
+        }
+        if c == '"' || c == '\'' {
+            quote = Some(c);
+        }
+    }
+    if let Some(s) = start {
+        words.push(&args[s..]);
+    }
+    words
+}
+
+// split_quoted_argsで区切った引数から引用符を外す
+// '...' の中はそのまま、"..." の中では \" と \\ だけを1文字として扱う
+// 引用符の外の \ はそのまま残すので、CORP\svc のようなユーザー名は引用符なしで書ける
+pub fn unquote(word: &str) -> String {
+    let mut result = String::new();
+    let mut quote = None;
+    let mut chars = word.chars().peekable();
+    while let Some(c) = chars.next() {
+        match quote {
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None => result.push(c),
+            Some(q) if c == q => quote = None,
+            Some('"') if c == '\\' && matches!(chars.peek(), Some('"' | '\\')) => {
+                result.extend(chars.next());
+            }
+            Some(_) => result.push(c),
+        }
+    }
+    result
+}
+
 fn is_contain_list_ref(token: &str) -> bool {
-    return token.contains('[');
+    token.contains('[')
 }
 
 fn to_value(raw: &String) -> Value {
@@ -212,9 +504,8 @@ fn to_value(raw: &String) -> Value {
         return Value::String("".to_string());
     }
     // number
-    let num_r = serde_json::Number::from_str(raw.as_str());
-    if num_r.is_ok() {
-        return Value::Number(num_r.unwrap());
+    if let Ok(num) = serde_json::Number::from_str(raw.as_str()) {
+        return Value::Number(num);
     }
     // bool
     if raw == "true" {
@@ -228,11 +519,10 @@ fn to_value(raw: &String) -> Value {
         return Value::Null;
     }
     // array
-    let start = raw.get(0..1).unwrap();
-    let end = raw.get(raw.len() - 1..raw.len()).unwrap();
-    if start == "[" && end == "]" {
+    // 値が複数バイトの文字で始まったり終わったりしても区切れるように、バイト位置ではなく文字で確かめる
+    if raw.len() >= 2 && raw.starts_with('[') && raw.ends_with(']') {
         let mut vec_value = Vec::<Value>::new();
-        let contents_raw = raw.get(1..raw.len() - 1).unwrap();
+        let contents_raw = &raw[1..raw.len() - 1];
         let contents = contents_raw.split(',').collect::<Vec<&str>>();
         for content in contents {
             vec_value.push(to_value(&content.to_string()));
@@ -240,5 +530,5 @@ fn to_value(raw: &String) -> Value {
         return Value::Array(vec_value);
     }
     // String
-    return Value::String(raw.to_string());
+    Value::String(raw.to_string())
 }
@@ -1,2 +1,6 @@
+pub mod diff;
+pub mod error;
+#[allow(clippy::module_inception)]
 pub mod json;
-pub mod error;
\ No newline at end of file
+
+mod test;
@@ -1,12 +1,12 @@
-pub mod error;
-pub mod json;
-
 #[cfg(test)]
 mod tests {
 
-    use crate::error::Error;
+    use serde_json::json;
 
-    use super::*;
+    use super::super::{
+        error::JsonQueryError,
+        json::{split_quoted_args, unquote, Json},
+    };
 
     const JSON_DATA: &str = r#"
         {
@@ -26,178 +26,165 @@ mod tests {
 
     #[test]
     fn ref_() {
-        let mut j = json::Json::new(&JSON_DATA.to_string());
-        let r = j.refer(&"store.book[0].title".to_string());
-        if r.is_err() {
-            assert_eq!(1, 2);
-        } else {
-            let r = r.unwrap();
-            assert_eq!("\"Book 1\"", &r);
+        let mut j = Json::new(JSON_DATA).unwrap();
+        let r = j.get(&"store.book[0].title".to_string());
+        match r {
+            Ok(r) => assert_eq!("\"Book 1\"", r.to_string()),
+            Err(_) => assert_eq!(1, 2),
         }
     }
 
     #[test]
     fn ref_num() {
-        let mut j = json::Json::new(&JSON_DATA.to_string());
-        let r = j.refer(&"store.bicycle.price".to_string()).unwrap();
-        assert_eq!("19.95", &r)
+        let mut j = Json::new(JSON_DATA).unwrap();
+        let r = j.get(&"store.bicycle.price".to_string()).unwrap();
+        assert_eq!("19.95", r.to_string())
     }
 
     #[test]
     fn ref_null() {
-        let mut j = json::Json::new(&JSON_DATA.to_string());
-        let r = j.refer(&"store.game".to_string()).unwrap();
-        assert_eq!("null", &r)
+        let mut j = Json::new(JSON_DATA).unwrap();
+        let r = j.get(&"store.game".to_string()).unwrap();
+        assert_eq!("null", r.to_string())
     }
 
     #[test]
     fn ref_err_not_found() {
-        let mut j = json::Json::new(&JSON_DATA.to_string());
-        let r = j.refer(&"store.book.math".to_string()).unwrap_err();
+        let mut j = Json::new(JSON_DATA).unwrap();
+        let r = j.get(&"store.book.math".to_string()).unwrap_err();
         match r {
-            Error::NotFound => assert_eq!(1, 1),
+            JsonQueryError::NotFound => assert_eq!(1, 1),
             _ => assert_eq!(1, 2),
         }
     }
 
     #[test]
     fn ref_err_dot_start_or_end() {
-        let mut j = json::Json::new(&JSON_DATA.to_string());
-        let r = j.refer(&".store".to_string()).unwrap_err();
+        let mut j = Json::new(JSON_DATA).unwrap();
+        let r = j.get(&".store".to_string()).unwrap_err();
         match r {
-            Error::StartOrEndByDot => assert_eq!(1, 1),
+            JsonQueryError::StartOrEndByDot => assert_eq!(1, 1),
             _ => assert_eq!(1, 2),
         }
     }
 
     #[test]
     fn ref_err_unclosed_bracket() {
-        let mut j = json::Json::new(&JSON_DATA.to_string());
-        let r = j.refer(&"store.book[0.title".to_string()).unwrap_err();
+        let mut j = Json::new(JSON_DATA).unwrap();
+        let r = j.get(&"store.book[0.title".to_string()).unwrap_err();
         match r {
-            Error::UnclosedBracket => assert_eq!(1, 1),
+            JsonQueryError::UnclosedBracket => assert_eq!(1, 1),
             _ => assert_eq!(1, 2),
         }
     }
 
     #[test]
     fn ref_err_not_num_bracket() {
-        let mut j = json::Json::new(&JSON_DATA.to_string());
-        let r = j.refer(&"store.book[a].title".to_string()).unwrap_err();
+        let mut j = Json::new(JSON_DATA).unwrap();
+        let r = j.get(&"store.book[a].title".to_string()).unwrap_err();
         match r {
-            Error::NotNumInBracket => assert_eq!(1, 1),
+            JsonQueryError::NotNumInBracket => assert_eq!(1, 1),
             _ => assert_eq!(1, 2),
         }
     }
 
     #[test]
     fn ref_err_invalid_end_of_bracket_token() {
-        let mut j = json::Json::new(&JSON_DATA.to_string());
-        let r = j.refer(&"store.book[1]aaa.title".to_string()).unwrap_err();
+        let mut j = Json::new(JSON_DATA).unwrap();
+        let r = j.get(&"store.book[1]aaa.title".to_string()).unwrap_err();
         match r {
-            Error::NotEndWithBracket => assert_eq!(1, 1),
+            JsonQueryError::NotEndWithBracket => assert_eq!(1, 1),
             _ => assert_eq!(1, 2),
         }
     }
 
     #[test]
     fn ref_err_empty_query() {
-        let mut j = json::Json::new(&JSON_DATA.to_string());
-        let r = j.refer(&"".to_string()).unwrap_err();
+        let mut j = Json::new(JSON_DATA).unwrap();
+        let r = j.get(&"".to_string()).unwrap_err();
         match r {
-            Error::EmptyQuery => assert_eq!(1, 1),
+            JsonQueryError::EmptyQuery => assert_eq!(1, 1),
             _ => assert_eq!(1, 2),
         }
     }
 
     #[test]
     fn mod0() {
-        let mut j = json::Json::new(&JSON_DATA.to_string());
-        let _ = j
-            .modify(&"store.game".to_string(), &"hello".to_string())
+        let mut j = Json::new(JSON_DATA).unwrap();
+        j.modify(&"store.game".to_string(), &"hello".to_string())
             .unwrap();
-        let r2 = j.refer(&"store.game".to_string()).unwrap();
-        assert_eq!("\"hello\"", &r2)
+        let r2 = j.get(&"store.game".to_string()).unwrap();
+        assert_eq!("\"hello\"", r2.to_string())
     }
 
     #[test]
     fn mod1() {
-        let mut j = json::Json::new(&JSON_DATA.to_string());
-        let _ = j
-            .modify(&"store.book[1].title".to_string(), &"hello".to_string())
+        let mut j = Json::new(JSON_DATA).unwrap();
+        j.modify(&"store.book[1].title".to_string(), &"hello".to_string())
             .unwrap();
-        let r2 = j.refer(&"store.book[1].title".to_string()).unwrap();
-        assert_eq!("\"hello\"", &r2)
+        let r2 = j.get(&"store.book[1].title".to_string()).unwrap();
+        assert_eq!("\"hello\"", r2.to_string())
     }
 
     #[test]
     fn mod2() {
-        let mut j = json::Json::new(&JSON_DATA.to_string());
-        let _ = j
-            .modify(&"store.book[1].title".to_string(), &"1".to_string())
+        let mut j = Json::new(JSON_DATA).unwrap();
+        j.modify(&"store.book[1].title".to_string(), &"1".to_string())
             .unwrap();
-        let r2 = j.refer(&"store.book[1].title".to_string()).unwrap();
-        assert_eq!("1", &r2)
+        let r2 = j.get(&"store.book[1].title".to_string()).unwrap();
+        assert_eq!("1", r2.to_string())
     }
 
     #[test]
     fn mod3() {
-        let mut j = json::Json::new(&JSON_DATA.to_string());
-        let _ = j
-            .modify(&"store.book".to_string(), &"true".to_string())
+        let mut j = Json::new(JSON_DATA).unwrap();
+        j.modify(&"store.book".to_string(), &"true".to_string())
             .unwrap();
-        let r2 = j.refer(&"store.book".to_string()).unwrap();
-        assert_eq!("true", &r2)
+        let r2 = j.get(&"store.book".to_string()).unwrap();
+        assert_eq!("true", r2.to_string())
     }
 
     #[test]
     fn mod4() {
-        let mut j = json::Json::new(&JSON_DATA.to_string());
-        let _ = j
-            .modify(&"store.test".to_string(), &"test".to_string())
+        let mut j = Json::new(JSON_DATA).unwrap();
+        j.modify(&"store.test".to_string(), &"test".to_string())
             .unwrap();
-        let r2 = j.refer(&"store.test".to_string()).unwrap();
-        assert_eq!("\"test\"", &r2)
+        let r2 = j.get(&"store.test".to_string()).unwrap();
+        assert_eq!("\"test\"", r2.to_string())
     }
     #[test]
     fn mod5() {
-        let mut j = json::Json::new(&JSON_DATA.to_string());
-        let _ = j
-            .modify(&"store.test".to_string(), &"test".to_string())
+        let mut j = Json::new(JSON_DATA).unwrap();
+        j.modify(&"store.test".to_string(), &"test".to_string())
             .unwrap();
-        let r2 = j.refer(&"store.test".to_string()).unwrap();
-        assert_eq!("\"test\"", &r2)
+        let r2 = j.get(&"store.test".to_string()).unwrap();
+        assert_eq!("\"test\"", r2.to_string())
     }
 
     #[test]
     fn mod6() {
-        let mut j = json::Json::new(&JSON_DATA.to_string());
-        let _ = j
-            .modify(&"store.game".to_string(), &"[a,1,true]".to_string())
+        let mut j = Json::new(JSON_DATA).unwrap();
+        j.modify(&"store.game".to_string(), &"[a,1,true]".to_string())
             .unwrap();
-        let r2 = j.refer(&"store.game".to_string()).unwrap();
-        assert_eq!("[\"a\",1,true]", &r2)
+        let r2 = j.get(&"store.game".to_string()).unwrap();
+        assert_eq!("[\"a\",1,true]", r2.to_string())
     }
 
     #[test]
     fn del0() {
-        let mut j = json::Json::new(&JSON_DATA.to_string());
-        j.delete(&"store.game".to_string()).unwrap();
-        let r = j.refer(&"store.game".to_string());
-        if r.is_err() {
-            match r.unwrap_err() {
-                Error::NotFound => assert_eq!(1, 1),
-                _ => assert_eq!(1, 2),
-            }
-        } else {
-            assert_eq!(1, 2)
+        let mut j = Json::new(JSON_DATA).unwrap();
+        j.delete("store.game").unwrap();
+        let r = j.get(&"store.game".to_string());
+        match r {
+            Err(JsonQueryError::NotFound) => assert_eq!(1, 1),
+            _ => assert_eq!(1, 2),
         }
     }
 
     #[test]
     fn del1() {
-        let mut j = json::Json::new(&JSON_DATA.to_string());
-        j.delete(&"store.book[0]".to_string()).unwrap();
+        let mut j = Json::new(JSON_DATA).unwrap();
+        j.delete("store.book[0]").unwrap();
         let d = r#"
         {
             "store": {
@@ -211,7 +198,137 @@ mod tests {
                 "game": null
             }
         }"#;
-        let j2 = json::Json::new(&d.to_string());
+        let j2 = Json::new(d).unwrap();
         assert_eq!(j2.data, j.data)
     }
+
+    #[test]
+    fn del_quoted_key() {
+        let mut j = Json::new(
+            r#"{"hosts": {"dc01.corp.local": {"ip": "10.0.0.5"}, "web01": {"ip": "10.0.0.8"}}}"#,
+        )
+        .unwrap();
+        j.delete(r#"hosts["dc01.corp.local"].ip"#).unwrap();
+        assert_eq!(j.data["hosts"]["dc01.corp.local"], json!({}));
+        j.delete(r#"hosts["dc01.corp.local"]"#).unwrap();
+        assert_eq!(j.data, json!({"hosts": {"web01": {"ip": "10.0.0.8"}}}));
+        j.delete("hosts").unwrap();
+        assert_eq!(j.data, json!({}));
+    }
+
+    #[test]
+    fn template_evaluation() {
+        let mut j = Json::new(
+            r#"{"ip": "10.0.0.5", "port": 8080, "url": "http://{{ip}}:{{port}}/", "target": {"base": "{{url}}", "port": "{{port}}"}}"#,
+        )
+        .unwrap();
+        let text = |_: &serde_json::Value| None;
+        assert_eq!(
+            j.refer(&"url".to_string(), &text).unwrap(),
+            json!("http://10.0.0.5:8080/")
+        );
+        // 文字列全体が1つの {{query}} なら型もそのまま
+        assert_eq!(
+            j.refer(&"target".to_string(), &text).unwrap(),
+            json!({"base": "http://10.0.0.5:8080/", "port": 8080})
+        );
+        assert_eq!(
+            j.render("curl {{ target.base }}admin", &text).unwrap(),
+            "curl http://10.0.0.5:8080/admin"
+        );
+        match j.render("{{missing}}", &text).unwrap_err() {
+            JsonQueryError::TemplateError(q, _) => assert_eq!(q, "missing"),
+            e => panic!("unexpected error: {}", e),
+        }
+        match j.render("{{ip", &text).unwrap_err() {
+            JsonQueryError::UnclosedTemplate => (),
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn template_cycle_detection() {
+        let mut j =
+            Json::new(r#"{"a": "{{b}}", "b": "x{{c}}", "c": "{{a}}", "d": "{{d}}"}"#).unwrap();
+        let text = |_: &serde_json::Value| None;
+        match j.refer(&"a".to_string(), &text).unwrap_err() {
+            JsonQueryError::CircularReference(chain) => assert_eq!(chain, "a -> b -> c -> a"),
+            e => panic!("unexpected error: {}", e),
+        }
+        match j.render("{{d}}", &text).unwrap_err() {
+            JsonQueryError::CircularReference(chain) => assert_eq!(chain, "d -> d"),
+            e => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn del_missing_leaves_data_unchanged() {
+        let mut j = Json::new(JSON_DATA).unwrap();
+        let before = j.data.clone();
+        for query in [
+            "typo.key",
+            "missing[0]",
+            "store.book[5]",
+            "store.nothing",
+            "store.bicycle.color.x",
+        ] {
+            match j.delete(query) {
+                Err(JsonQueryError::NotFound) => (),
+                r => panic!("{}: unexpected result {:?}", query, r),
+            }
+            assert_eq!(j.data, before);
+        }
+    }
+
+    #[test]
+    fn non_ascii_keys_and_values() {
+        let mut j = Json::new(r#"{"é": "café"}"#).unwrap();
+        j.modify(&"user".to_string(), &"josé".to_string()).unwrap();
+        j.modify(&"ünï".to_string(), &"é".to_string()).unwrap();
+        j.modify(&"list".to_string(), &"[é,ü]".to_string()).unwrap();
+        j.modify(&"bracket".to_string(), &"[".to_string()).unwrap();
+        assert_eq!(j.get(&"user".to_string()).unwrap(), json!("josé"));
+        assert_eq!(j.get(&"ünï".to_string()).unwrap(), json!("é"));
+        assert_eq!(j.get(&"é".to_string()).unwrap(), json!("café"));
+        assert_eq!(j.get(&"list".to_string()).unwrap(), json!(["é", "ü"]));
+        assert_eq!(j.get(&"bracket".to_string()).unwrap(), json!("["));
+        assert!(j.get(&"ü".to_string()).is_err());
+        assert!(j.get(&"é[0é".to_string()).is_err());
+        j.delete("é").unwrap();
+        assert!(j.get(&"é".to_string()).is_err());
+    }
+
+    #[test]
+    fn split_quoted_args_and_unquote() {
+        let line = r#"add --user CORP\svc --pass "p a\"ss\\" --source 'it''s here' --key="a b" x"#;
+        let words = split_quoted_args(line);
+        assert_eq!(
+            words,
+            vec![
+                "add",
+                "--user",
+                r"CORP\svc",
+                "--pass",
+                r#""p a\"ss\\""#,
+                "--source",
+                "'it''s here'",
+                r#"--key="a b""#,
+                "x",
+            ]
+        );
+        let values = words.into_iter().map(unquote).collect::<Vec<String>>();
+        assert_eq!(values[2], r"CORP\svc");
+        assert_eq!(values[4], r#"p a"ss\"#);
+        assert_eq!(values[6], "its here");
+        assert_eq!(values[7], "--key=a b");
+        // '...' の中の \ はそのまま。空の引用符は空の値になる
+        assert_eq!(unquote(r"'a\b'"), r"a\b");
+        assert_eq!(unquote(r#""""#), "");
+        // 閉じていない引用符は行の最後まで
+        assert_eq!(
+            split_quoted_args(r#"--pass "a b"#),
+            vec!["--pass", r#""a b"#]
+        );
+        assert_eq!(unquote(r#""a b"#), "a b");
+    }
 }
@@ -1,16 +1,61 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
 use command::command::start_shell;
 
 mod command;
 mod config;
+mod doctor;
 mod error;
 mod json;
+mod migration;
 mod output;
 mod shell;
+mod store;
+
+mod test;
+
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Cli {
+    /// directory used instead of ~/.pwnrail (overrides PWNRAIL_HOME)
+    #[arg(long)]
+    config_dir: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Option<CliCommand>,
+}
+
+#[derive(Subcommand, Debug)]
+enum CliCommand {
+    /// check config and workspace files and offer repairs
+    Doctor {
+        /// apply every repair without asking
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
 
 #[tokio::main]
 async fn main() {
+    let cli = Cli::parse();
+
+    if let Some(CliCommand::Doctor { yes }) = cli.command {
+        let paths = match config::AppPaths::resolve(cli.config_dir) {
+            Ok(p) => p,
+            Err(e) => {
+                crate::error::print_error(crate::error::Error::AppConfigError(e));
+                println!();
+                return;
+            }
+        };
+        if doctor::run(&paths, yes) {
+            std::process::exit(1);
+        }
+        return;
+    }
+
     // 初期設定
-    let mut app_conf = match config::AppConfig::new() {
+    let mut app_conf = match config::AppConfig::new(cli.config_dir) {
         Ok(conf) => conf,
         Err(e) => {
             crate::error::print_error(crate::error::Error::AppConfigError(e));
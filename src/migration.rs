@@ -0,0 +1,150 @@
+use std::{fs, io, path::PathBuf};
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    config::{is_entry_exist, Workspace, WorkspaceMeta},
+    error::MigrateWorkspaceError,
+    output::log_prefix,
+};
+
+// 現在のワークスペースのフォーマットのバージョン
+// Workspaceやvarsファイルの構造を変えるときは、この値を上げてmigrations()にマイグレーションを追加する
+pub const CURRENT_SCHEMA_VERSION: u32 = 1;
+
+// fromのバージョンのワークスペースをfrom + 1のバージョンに変換するマイグレーション
+// metaファイルのschema_versionの更新はmigrate関数が行うので、applyはそれ以外の変更だけをする
+struct Migration {
+    from: u32,
+    description: &'static str,
+    apply: fn(&Workspace) -> Result<(), io::Error>,
+}
+
+fn migrations() -> Vec<Migration> {
+    vec![
+        // version 0: metaファイルが導入される前のワークスペース
+        Migration {
+            from: 0,
+            description: "record the schema version in .prail/meta.toml",
+            apply: |_| Ok(()),
+        },
+    ]
+}
+
+// ワークスペースを現在のバージョンまでマイグレーションする関数
+// マイグレーションの前に管理ディレクトリ全体を .prail.backup-v<version>-<unix time> にコピーしておく
+// dry_runのときは、適用されるマイグレーションを表示するだけで何も変更しない
+pub fn migrate(workspace: &Workspace, dry_run: bool) -> Result<(), MigrateWorkspaceError> {
+    let meta = match workspace.read_meta() {
+        Ok(m) => m,
+        Err(e) => return Err(MigrateWorkspaceError::ReadMetaError(e)),
+    };
+    let (version, created_at) = match &meta {
+        Some(m) => (m.schema_version, m.created_at),
+        None => (0, guess_created_at(&workspace.mgr_path)),
+    };
+    if version > CURRENT_SCHEMA_VERSION {
+        return Err(MigrateWorkspaceError::TooNewSchema(version));
+    }
+
+    let pending = migrations()
+        .into_iter()
+        .filter(|m| m.from >= version)
+        .collect::<Vec<Migration>>();
+    if pending.is_empty() {
+        if dry_run {
+            println!(
+                "{} workspace is up to date (schema version {})",
+                log_prefix(),
+                version
+            );
+        }
+        return Ok(());
+    }
+
+    if dry_run {
+        for m in &pending {
+            println!(
+                "{} would migrate v{} -> v{}: {}",
+                log_prefix(),
+                m.from,
+                m.from + 1,
+                m.description
+            );
+        }
+        return Ok(());
+    }
+
+    let backup_path = match backup_mgr(workspace, version) {
+        Ok(p) => p,
+        Err(e) => return Err(MigrateWorkspaceError::BackupError(e)),
+    };
+    println!(
+        "{} backed up the workspace to {}",
+        log_prefix(),
+        backup_path.display()
+    );
+
+    let mut meta = meta.unwrap_or_else(|| WorkspaceMeta::new(created_at));
+    for m in pending {
+        match (m.apply)(workspace) {
+            Ok(_) => (),
+            Err(e) => return Err(MigrateWorkspaceError::ApplyError(m.from, e)),
+        };
+        // 1つ適用するごとにバージョンを記録しておき、途中で失敗しても続きから再開できるようにする
+        meta.schema_version = m.from + 1;
+        meta.pwnrail_version = env!("CARGO_PKG_VERSION").to_string();
+        match workspace.write_meta(&meta) {
+            Ok(_) => (),
+            Err(e) => return Err(MigrateWorkspaceError::WriteMetaError(e)),
+        };
+        println!(
+            "{} migrated v{} -> v{}: {}",
+            log_prefix(),
+            m.from,
+            m.from + 1,
+            m.description
+        );
+    }
+    Ok(())
+}
+
+// metaファイルがない古いワークスペースの作成日時は、管理ディレクトリの作成日時(取れなければ更新日時)で代用する
+fn guess_created_at(mgr_path: &PathBuf) -> DateTime<Utc> {
+    fs::metadata(mgr_path)
+        .and_then(|m| m.created().or_else(|_| m.modified()))
+        .map(DateTime::<Utc>::from)
+        .unwrap_or_else(|_| Utc::now())
+}
+
+fn backup_mgr(workspace: &Workspace, version: u32) -> Result<PathBuf, io::Error> {
+    let mut name = workspace
+        .mgr_path
+        .file_name()
+        .unwrap_or_default()
+        .to_os_string();
+    name.push(format!(".backup-v{}-{}", version, Utc::now().timestamp()));
+    let backup_path = workspace.mgr_path.with_file_name(name);
+    if is_entry_exist(&backup_path)? {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", backup_path.display()),
+        ));
+    }
+    copy_dir_all(&workspace.mgr_path, &backup_path)?;
+    Ok(backup_path)
+}
+
+pub fn copy_dir_all(src: &PathBuf, dest: &PathBuf) -> Result<(), io::Error> {
+    fs::create_dir_all(dest)?;
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let dest_path = dest.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir_all(&entry.path(), &dest_path)?;
+        } else {
+            fs::copy(entry.path(), dest_path)?;
+        }
+    }
+    Ok(())
+}
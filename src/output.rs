@@ -18,6 +18,7 @@ pub fn blue(text: &str) -> String {
     format!("\x1b[34m{}\x1b[0m", text)
 }
 
+#[allow(dead_code)]
 pub fn gray(text: &str) -> String {
     format!("\x1b[2;N{}\x1b[0m", text)
 }
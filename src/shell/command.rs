@@ -1,15 +1,14 @@
+pub type CommandFunc = Box<dyn Fn(String, &mut crate::config::AppConfig) -> super::types::IsError>;
+
 pub struct Command {
     pub name: String,
     // variable to store function that will be called when the paired name is used on the prompt
     // when it's called, arguments of the command is passed via a string argument.
-    pub func: Box<dyn Fn(String, &mut crate::config::AppConfig) -> super::types::IsError>,
+    pub func: CommandFunc,
 }
 
 impl Command {
-    pub fn new(
-        name: &str,
-        func: Box<dyn Fn(String, &mut crate::config::AppConfig) -> super::types::IsError>,
-    ) -> Self {
+    pub fn new(name: &str, func: CommandFunc) -> Self {
         let name = name.to_string();
         Command { name, func }
     }
@@ -19,7 +18,7 @@ pub fn builtins() -> Vec<Command> {
     vec![Command::new("h", Box::new(hello))]
 }
 
-pub fn hello(arg: String, app_conf: &mut crate::config::AppConfig) -> super::types::IsError {
+pub fn hello(_arg: String, _app_conf: &mut crate::config::AppConfig) -> super::types::IsError {
     println!("hello");
     false
 }
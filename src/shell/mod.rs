@@ -1,3 +1,4 @@
 pub mod command;
+#[allow(clippy::module_inception)]
 pub mod shell;
-pub mod types;
\ No newline at end of file
+pub mod types;
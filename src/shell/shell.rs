@@ -1,3 +1,18 @@
+use std::{process, time::Instant};
+
+use chrono::{DateTime, Utc};
+use rustyline::ExternalPrinter;
+
+use crate::{
+    error::{self, Error},
+    output::{blue, error_prefix},
+    store::{
+        flags,
+        timeline::{self, Entry, EntryKind},
+        watch::VarsWatcher,
+    },
+};
+
 pub struct Shell<'a> {
     pub commands: Vec<super::command::Command>,
     pub prompt: String,
@@ -43,28 +58,42 @@ impl<'a> Shell<'a> {
     pub async fn start(&mut self) {
         let mut rl = rustyline::DefaultEditor::new().unwrap();
         let _ = rl.load_history(&self.app_conf.shell_hist_path);
+        // 入力中のプロンプトを崩さずに通知を出すためにExternalPrinterを使う。端末でない場合は普通に出力する
+        let print: Box<dyn FnMut(String) + Send> = match rl.create_external_printer() {
+            Ok(mut p) => Box::new(move |msg| {
+                let _ = p.print(format!("{}\n", msg));
+            }),
+            Err(_) => Box::new(|msg| println!("{}", msg)),
+        };
+        let mut watcher = VarsWatcher::new(self.app_conf, print).ok();
         loop {
             let ws_name = if self.app_conf.dyn_conf.current_workspace.is_empty() {
                 "".to_string()
             } else {
                 let path = &self.app_conf.dyn_conf.current_workspace;
                 let path_iter = path.split("/").collect::<Vec<&str>>();
-                let name = path_iter.get(path_iter.len() - 1).unwrap();
+                let _name = path_iter.last().unwrap();
                 // format!("({})", gray(name))
                 "".to_string()
             };
+            let notice = if self.vars_changed_elsewhere() {
+                format!("({}) ", blue("vars changed"))
+            } else {
+                "".to_string()
+            };
+            let flags = match self.flag_progress() {
+                Some(p) => format!("(flags {}) ", p),
+                None => "".to_string(),
+            };
             let prompt = if !self.prev_state {
-                format!("{}{} ", ws_name, self.prompt)
+                format!("{}{}{}{} ", notice, flags, ws_name, self.prompt)
             } else {
-                format!("{}{} ", ws_name, self.err_prompt)
+                format!("{}{}{}{} ", notice, flags, ws_name, self.err_prompt)
             };
             let readline = rl.readline(&prompt);
-            let mut input = String::new();
 
-            match readline {
-                Ok(line) => {
-                    input = line;
-                }
+            let input = match readline {
+                Ok(line) => line,
                 Err(rustyline::error::ReadlineError::Interrupted) => {
                     continue;
                 }
@@ -75,42 +104,98 @@ impl<'a> Shell<'a> {
                     println!("Error: {:?}", err);
                     break;
                 }
-            }
+            };
 
-            let _ = rl.add_history_entry(input.as_str());
+            // vm --secretやcred add --passの値がshell_historyに平文で残らないように伏せてから追加する
+            let _ = rl.add_history_entry(timeline::redact_line(&input));
 
             if input == "exit" {
                 break;
             }
 
-            let raw_command: Vec<String> = input
-                .split_whitespace()
-                .into_iter()
-                .map(|x| x.to_string())
-                .collect::<Vec<String>>();
+            self.execute_line(&input);
+            // useなどでワークスペースが変わっていたら監視対象を切り替える
+            if let Some(w) = &mut watcher {
+                w.follow(self.app_conf);
+            }
+        }
+        let _ = rl.save_history(&self.app_conf.shell_hist_path);
+    }
 
-            let command_len = raw_command.len();
+    fn execute_line(&mut self, input: &str) {
+        let input = input.trim();
+        if input.is_empty() {
+            return;
+        }
+        let started_at = Utc::now();
+        let timer = Instant::now();
 
-            if command_len == 0 {
-                continue;
-            }
+        // !で始まる行はシェル (sh -c) で外部コマンドとして実行する
+        if let Some(external) = input.strip_prefix('!') {
+            let (state, exit_code) = execute_external(external.trim());
+            self.prev_state = state;
+            self.record(
+                started_at,
+                timer,
+                EntryKind::External,
+                external.trim(),
+                !state,
+                exit_code,
+            );
+            return;
+        }
 
-            if command_len == 1 {
-                let name = &raw_command[0];
-                let state = self.execute_command(name, None);
-                self.prev_state = state;
-                continue;
-            }
+        let (name, args) = match input.split_once(char::is_whitespace) {
+            Some((name, args)) => (
+                name.to_string(),
+                Some(args.split_whitespace().collect::<Vec<&str>>().join(" ")),
+            ),
+            None => (input.to_string(), None),
+        };
+        let state = self.execute_command(&name, args.as_ref());
+        self.prev_state = state;
+        self.record(started_at, timer, EntryKind::Command, input, !state, None);
+    }
 
-            if command_len > 1 {
-                let name = &raw_command[0];
-                let args = &raw_command[1..command_len].join(" ");
-                let state = self.execute_command(name, Some(args));
-                self.prev_state = state;
-                continue;
-            }
+    // 実行したコマンドをワークスペースのtimelineに記録する。ワークスペースを使っていなければ記録しない
+    // useやinitで切り替えた場合は、切り替えたあとのワークスペースに記録する
+    fn record(
+        &self,
+        time: DateTime<Utc>,
+        timer: Instant,
+        kind: EntryKind,
+        line: &str,
+        ok: bool,
+        exit_code: Option<i32>,
+    ) {
+        if self.app_conf.dyn_conf.current_workspace.is_empty() {
+            return;
         }
-        let _ = rl.save_history(&self.app_conf.shell_hist_path);
+        let (command, args) = match line.split_once(char::is_whitespace) {
+            Some((c, a)) => (c, timeline::redact(c, a)),
+            None => (line, "".to_string()),
+        };
+        let entry = Entry {
+            time,
+            kind,
+            command: command.to_string(),
+            args: if args.is_empty() { None } else { Some(args) },
+            duration_ms: Some(timer.elapsed().as_millis() as u64),
+            ok: Some(ok),
+            exit_code,
+            rev: None,
+        };
+        let path = self.app_conf.dyn_conf.to_workspace().timeline_path;
+        match timeline::append(&path, &entry) {
+            Ok(_) => (),
+            Err(e) => {
+                println!(
+                    "{} failed to record the command to the timeline",
+                    error_prefix()
+                );
+                error::print_error(Error::TimelineError(e));
+            }
+        };
     }
 
     fn execute_command(&mut self, name: &String, args: Option<&String>) -> super::types::IsError {
@@ -118,10 +203,9 @@ impl<'a> Shell<'a> {
         match cmd_lct {
             Some(lct) => {
                 let func = &self.commands[lct].func;
-                if args.is_some() {
-                    func(args.unwrap().to_string(), self.app_conf)
-                } else {
-                    func("".to_string(), self.app_conf)
+                match args {
+                    Some(args) => func(args.to_string(), self.app_conf),
+                    None => func("".to_string(), self.app_conf),
                 }
             }
             None => {
@@ -131,12 +215,49 @@ impl<'a> Shell<'a> {
         }
     }
 
-    fn search_command(&self, name: &String) -> Option<usize> {
-        for n in 0..self.commands.len() {
-            if &self.commands[n].name == name {
-                return Some(n);
+    // 他のプロセスがvarsファイルを変更したかどうかを返す関数
+    // このプロセスが最後に読み書きしたときのハッシュと比較し、vrなどで読み込み直すまでtrueを返し続ける
+    fn vars_changed_elsewhere(&mut self) -> bool {
+        let current = match crate::store::vars::current_fingerprint(self.app_conf) {
+            Some(c) => c,
+            None => return false,
+        };
+        let mut seen = self.app_conf.vars_fingerprint.lock().unwrap();
+        match &*seen {
+            Some(s) => *s != current,
+            None => {
+                *seen = Some(current);
+                false
             }
         }
-        None
+    }
+
+    // 現在のワークスペースで 取ったフラグの数/取るべき数 を返す関数
+    // フラグを記録していないワークスペースや、flags.jsonを読めないときはプロンプトに何も出さない
+    fn flag_progress(&self) -> Option<String> {
+        if self.app_conf.dyn_conf.current_workspace.is_empty() {
+            return None;
+        }
+        let board = flags::load(self.app_conf).ok()?;
+        flags::progress(&board)
+    }
+
+    fn search_command(&self, name: &String) -> Option<usize> {
+        self.commands.iter().position(|c| &c.name == name)
+    }
+}
+
+// 外部コマンドを実行して、エラーかどうかと終了コードを返す
+fn execute_external(command: &str) -> (super::types::IsError, Option<i32>) {
+    if command.is_empty() {
+        println!("{} give a command after !", error_prefix());
+        return (true, None);
+    }
+    match process::Command::new("sh").arg("-c").arg(command).status() {
+        Ok(status) => (!status.success(), status.code()),
+        Err(e) => {
+            println!("{} failed to run `{}`: {}", error_prefix(), command, e);
+            (true, None)
+        }
     }
 }
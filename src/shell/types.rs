@@ -1 +1 @@
-pub type IsError = bool;
\ No newline at end of file
+pub type IsError = bool;
@@ -0,0 +1,223 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::{document, secret};
+use crate::{
+    config::AppConfig,
+    error::{CredsError, SecretError},
+};
+
+// ワークスペースで見つけた認証情報の一覧 (.prail/creds.json)
+//  {
+//      "next_id": 2,
+//      "creds": [
+//          {
+//              "id": 1,
+//              "user": "svc_backup",
+//              "domain": "CORP",
+//              "kind": "password",
+//              "secret": "pwnrail-secret:v1:...",
+//              "source": "smb share backups/web.config",
+//              "tries": [{"host": "dc01", "service": "445/tcp", "result": "success", "tried_at": "..."}]
+//          }
+//      ]
+//  }
+// idはfindingsから参照されるので、cred rmで消したidを別の認証情報に使わないようにnext_idで次のidを覚えておく
+// next_idがなかったころのcreds.jsonは認証情報の配列だけなので、そのまま読めるようにしておく
+// secretはunlockされていればvm --secretと同じ形式で暗号化して保存する (store::secretを参照)
+// vrや {{query}} からは vault[0].user (varsにcredsがなければ creds[0].user も) のように参照できる (store::viewを参照)
+pub type Creds = Vec<Credential>;
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(from = "VaultFile")]
+pub struct Vault {
+    pub next_id: u32,
+    pub creds: Creds,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum VaultFile {
+    Vault {
+        #[serde(default)]
+        next_id: u32,
+        #[serde(default)]
+        creds: Creds,
+    },
+    Creds(Creds),
+}
+
+impl From<VaultFile> for Vault {
+    fn from(file: VaultFile) -> Self {
+        match file {
+            VaultFile::Vault { next_id, creds } => Vault { next_id, creds },
+            VaultFile::Creds(creds) => Vault { next_id: 0, creds },
+        }
+    }
+}
+
+impl Vault {
+    // 新しい認証情報のidを取る。next_idが古い (手で編集されたなど) 場合も、今あるidとは重ならないようにする
+    pub fn take_id(&mut self) -> u32 {
+        let max = self.creds.iter().map(|c| c.id).max().unwrap_or(0);
+        let id = self.next_id.max(max + 1);
+        self.next_id = id + 1;
+        id
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Credential {
+    pub id: u32,
+    pub user: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+    pub kind: CredKind,
+    pub secret: String,
+    // どこで見つけたか
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default)]
+    pub tries: Vec<Try>,
+    pub added_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CredKind {
+    Password,
+    // NTLMハッシュなど
+    Hash,
+    // 秘密鍵 (鍵ファイルのパスや中身)
+    Key,
+}
+
+impl fmt::Display for CredKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CredKind::Password => write!(f, "password"),
+            CredKind::Hash => write!(f, "hash"),
+            CredKind::Key => write!(f, "key"),
+        }
+    }
+}
+
+// どのホスト・サービスで試して、使えたかどうか
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Try {
+    pub host: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub service: Option<String>,
+    pub result: TryResult,
+    pub tried_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum TryResult {
+    Success,
+    Failure,
+}
+
+impl Credential {
+    // DOMAIN\user の形の名前。ドメインがなければユーザー名だけ
+    pub fn account(&self) -> String {
+        match &self.domain {
+            Some(d) => format!("{}\\{}", d, self.user),
+            None => self.user.clone(),
+        }
+    }
+
+    // 同じホスト・サービスで試した結果があれば上書きする
+    pub fn record_try(&mut self, t: Try) {
+        match self
+            .tries
+            .iter_mut()
+            .find(|old| old.host == t.host && old.service == t.service)
+        {
+            Some(old) => *old = t,
+            None => self.tries.push(t),
+        }
+    }
+
+    // このホストでの結果。サービスごとの結果が混ざっている場合は、1つでも成功していれば成功とする
+    pub fn result_on(&self, host: &str) -> Option<TryResult> {
+        let mut results = self
+            .tries
+            .iter()
+            .filter(|t| t.host == host)
+            .map(|t| t.result);
+        let first = results.next()?;
+        if first == TryResult::Success || results.any(|r| r == TryResult::Success) {
+            Some(TryResult::Success)
+        } else {
+            Some(TryResult::Failure)
+        }
+    }
+
+    // 平文の値。暗号化されている場合はunlockされている必要がある
+    pub fn reveal(&self, key: Option<&secret::SecretKey>) -> Result<String, SecretError> {
+        if !secret::is_secret(&Value::String(self.secret.clone())) {
+            return Ok(self.secret.clone());
+        }
+        match key {
+            Some(k) => secret::decrypt(k, &self.secret),
+            None => Err(SecretError::Locked),
+        }
+    }
+}
+
+// 鍵があれば暗号化する。なければ平文のまま保存する (cred addでは --plaintext が指定されたときだけ)
+pub fn seal(key: Option<&secret::SecretKey>, plaintext: &str) -> Result<String, SecretError> {
+    match key {
+        Some(k) => secret::encrypt(k, plaintext),
+        None => Ok(plaintext.to_string()),
+    }
+}
+
+pub fn find_mut(creds: &mut Creds, id: u32) -> Result<&mut Credential, CredsError> {
+    match creds.iter_mut().find(|c| c.id == id) {
+        Some(c) => Ok(c),
+        None => Err(CredsError::CredNotFound(id)),
+    }
+}
+
+pub fn load(app_conf: &AppConfig) -> Result<Creds, CredsError> {
+    if app_conf.dyn_conf.current_workspace.is_empty() {
+        return Err(CredsError::NoCurrentWorkspace);
+    }
+    let path = app_conf.dyn_conf.to_workspace().creds_path;
+    match document::read::<Vault>(&path) {
+        Ok(v) => Ok(v.creds),
+        Err(e) => Err(CredsError::DocumentError(e)),
+    }
+}
+
+pub fn update<R>(
+    app_conf: &AppConfig,
+    f: impl FnOnce(&mut Vault) -> Result<R, CredsError>,
+) -> Result<R, CredsError> {
+    if app_conf.dyn_conf.current_workspace.is_empty() {
+        return Err(CredsError::NoCurrentWorkspace);
+    }
+    let path = app_conf.dyn_conf.to_workspace().creds_path;
+    document::update_private(&path, CredsError::DocumentError, f)
+}
+
+// クエリで参照するときの形。DOMAIN\user をaccountとしても参照できるようにする
+// secretは暗号化されたままなので、{{query}} ではunlockされているときだけ復号される
+pub fn to_value(creds: &Creds) -> Value {
+    Value::Array(
+        creds
+            .iter()
+            .map(|c| {
+                let mut v = json!(c);
+                v["account"] = Value::String(c.account());
+                v
+            })
+            .collect(),
+    )
+}
@@ -0,0 +1,84 @@
+use std::{fs, io, path::Path};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::lock;
+use crate::error::DocumentError;
+
+// hosts.jsonなど、管理ディレクトリに置く型の決まったjsonファイルの読み書き
+// varsファイルと同じように、読み込みは共有ロック、書き込みは排他ロックを取ってから一時ファイルとrenameで行う
+// ファイルがまだない場合は空の値(Default)として扱い、最初に書き込むときに作る
+
+pub fn read<T: DeserializeOwned + Default>(path: &Path) -> Result<T, DocumentError> {
+    let _lock = match lock::lock_shared(path) {
+        Ok(l) => l,
+        Err(e) => return Err(DocumentError::LockError(e)),
+    };
+    read_(path)
+}
+
+// 排他ロックを取ったまま読み込んで、fで変更してから書き込む
+// fがErrを返した場合は書き込まない。読み書きのエラーはwrapで呼び出し側のエラー型にする
+pub fn update<T, R, E>(
+    path: &Path,
+    wrap: impl Fn(DocumentError) -> E,
+    f: impl FnOnce(&mut T) -> Result<R, E>,
+) -> Result<R, E>
+where
+    T: DeserializeOwned + Serialize + Default,
+{
+    update_(path, wrap, f, lock::write_atomic)
+}
+
+// updateと同じだが、ほかのユーザーから読めないように 0600 で書き込む (creds.jsonなど)
+pub fn update_private<T, R, E>(
+    path: &Path,
+    wrap: impl Fn(DocumentError) -> E,
+    f: impl FnOnce(&mut T) -> Result<R, E>,
+) -> Result<R, E>
+where
+    T: DeserializeOwned + Serialize + Default,
+{
+    update_(path, wrap, f, lock::write_atomic_private)
+}
+
+fn update_<T, R, E>(
+    path: &Path,
+    wrap: impl Fn(DocumentError) -> E,
+    f: impl FnOnce(&mut T) -> Result<R, E>,
+    write: fn(&Path, &[u8]) -> Result<(), io::Error>,
+) -> Result<R, E>
+where
+    T: DeserializeOwned + Serialize + Default,
+{
+    let _lock = match lock::lock_exclusive(path) {
+        Ok(l) => l,
+        Err(e) => return Err(wrap(DocumentError::LockError(e))),
+    };
+    let mut doc = match read_(path) {
+        Ok(d) => d,
+        Err(e) => return Err(wrap(e)),
+    };
+    let result = f(&mut doc)?;
+    let content = match serde_json::to_vec_pretty(&doc) {
+        Ok(c) => c,
+        Err(e) => return Err(wrap(DocumentError::SerializeError(e))),
+    };
+    match write(path, &content) {
+        Ok(_) => (),
+        Err(e) => return Err(wrap(DocumentError::WriteError(e))),
+    };
+    Ok(result)
+}
+
+fn read_<T: DeserializeOwned + Default>(path: &Path) -> Result<T, DocumentError> {
+    let content = match fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(T::default()),
+        Err(e) => return Err(DocumentError::ReadError(e)),
+    };
+    match serde_json::from_str(&content) {
+        Ok(d) => Ok(d),
+        Err(e) => Err(DocumentError::ParseError(e)),
+    }
+}
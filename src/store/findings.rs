@@ -0,0 +1,225 @@
+use std::{fmt, fs, path::Path};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::document;
+use crate::{config::AppConfig, error::FindingsError};
+
+// ワークスペースの指摘事項の一覧 (.prail/findings.json)
+//  {
+//      "next_id": 2,
+//      "findings": [
+//          {
+//              "id": 1,
+//              "title": "SMB signing is not required",
+//              "severity": "medium",
+//              "cvss": 5.3,
+//              "status": "confirmed",
+//              "hosts": ["dc01"],
+//              "creds": [2],
+//              "description": "Markdownの説明",
+//              "evidence": [{"file": "1/smb.png", "sha256": "...", "added_at": "..."}]
+//          }
+//      ]
+//  }
+// hostsはhosts.jsonのホストの名前、credsはcreds.jsonのIDで参照する
+// 証拠のファイルは .prail/evidence/<finding id>/ にコピーし、fileにはevidenceからの相対パスを入れる
+// 消した指摘事項のidを使い回すと、新しい指摘事項が前の証拠のディレクトリを引き継いでしまうので、next_idで次のidを覚えておく
+// next_idがなかったころのfindings.jsonは指摘事項の配列だけなので、そのまま読めるようにしておく
+pub type Findings = Vec<Finding>;
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(from = "FindingBookFile")]
+pub struct FindingBook {
+    pub next_id: u32,
+    pub findings: Findings,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FindingBookFile {
+    Book {
+        #[serde(default)]
+        next_id: u32,
+        #[serde(default)]
+        findings: Findings,
+    },
+    Findings(Findings),
+}
+
+impl From<FindingBookFile> for FindingBook {
+    fn from(file: FindingBookFile) -> Self {
+        match file {
+            FindingBookFile::Book { next_id, findings } => FindingBook { next_id, findings },
+            FindingBookFile::Findings(findings) => FindingBook {
+                next_id: 0,
+                findings,
+            },
+        }
+    }
+}
+
+impl FindingBook {
+    // 新しい指摘事項のidを取る。next_idが古い (手で編集されたなど) 場合も、今あるidとは重ならないようにする
+    pub fn take_id(&mut self) -> u32 {
+        let max = self.findings.iter().map(|f| f.id).max().unwrap_or(0);
+        let id = self.next_id.max(max + 1);
+        self.next_id = id + 1;
+        id
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Finding {
+    pub id: u32,
+    pub title: String,
+    pub severity: Severity,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cvss: Option<f32>,
+    pub status: FindingStatus,
+    #[serde(default)]
+    pub hosts: Vec<String>,
+    #[serde(default)]
+    pub creds: Vec<u32>,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub evidence: Vec<Evidence>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Evidence {
+    pub file: String,
+    pub sha256: String,
+    pub added_at: DateTime<Utc>,
+}
+
+// 並べ替えで重いものから並ぶように、重い順に定義する
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Critical,
+    High,
+    Medium,
+    Low,
+    Info,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Critical => write!(f, "critical"),
+            Severity::High => write!(f, "high"),
+            Severity::Medium => write!(f, "medium"),
+            Severity::Low => write!(f, "low"),
+            Severity::Info => write!(f, "info"),
+        }
+    }
+}
+
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, clap::ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum FindingStatus {
+    Open,
+    Confirmed,
+    Reported,
+}
+
+impl fmt::Display for FindingStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FindingStatus::Open => write!(f, "open"),
+            FindingStatus::Confirmed => write!(f, "confirmed"),
+            FindingStatus::Reported => write!(f, "reported"),
+        }
+    }
+}
+
+pub fn validate_cvss(cvss: f32) -> Result<(), FindingsError> {
+    if (0.0..=10.0).contains(&cvss) {
+        Ok(())
+    } else {
+        Err(FindingsError::InvalidCvss(cvss))
+    }
+}
+
+pub fn find_mut(findings: &mut Findings, id: u32) -> Result<&mut Finding, FindingsError> {
+    match findings.iter_mut().find(|f| f.id == id) {
+        Some(f) => Ok(f),
+        None => Err(FindingsError::FindingNotFound(id)),
+    }
+}
+
+// 証拠のファイルを evidence/<id>/ にコピーする
+// 同じ内容のファイルがすでに添付されていればコピーせずにそれを返す。名前だけが同じ場合は名前に番号を付ける
+pub fn copy_evidence(
+    evidence_path: &Path,
+    finding: &Finding,
+    source: &Path,
+) -> Result<(Evidence, bool), FindingsError> {
+    let content = match fs::read(source) {
+        Ok(c) => c,
+        Err(e) => return Err(FindingsError::ReadEvidenceError(source.to_path_buf(), e)),
+    };
+    let sha256 = hex::encode(Sha256::digest(&content));
+    if let Some(e) = finding.evidence.iter().find(|e| e.sha256 == sha256) {
+        return Ok((e.clone(), false));
+    }
+    let dir = evidence_path.join(finding.id.to_string());
+    if let Err(e) = fs::create_dir_all(&dir) {
+        return Err(FindingsError::WriteEvidenceError(dir, e));
+    }
+    let name = source
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "evidence".to_string());
+    let mut file_name = name.clone();
+    let mut n = 1;
+    while dir.join(&file_name).exists() {
+        file_name = match name.rsplit_once('.') {
+            Some((stem, ext)) if !stem.is_empty() => format!("{}-{}.{}", stem, n, ext),
+            _ => format!("{}-{}", name, n),
+        };
+        n += 1;
+    }
+    let dest = dir.join(&file_name);
+    if let Err(e) = fs::write(&dest, &content) {
+        return Err(FindingsError::WriteEvidenceError(dest, e));
+    }
+    let evidence = Evidence {
+        file: format!("{}/{}", finding.id, file_name),
+        sha256,
+        added_at: Utc::now(),
+    };
+    Ok((evidence, true))
+}
+
+pub fn load(app_conf: &AppConfig) -> Result<Findings, FindingsError> {
+    if app_conf.dyn_conf.current_workspace.is_empty() {
+        return Err(FindingsError::NoCurrentWorkspace);
+    }
+    let path = app_conf.dyn_conf.to_workspace().findings_path;
+    match document::read::<FindingBook>(&path) {
+        Ok(b) => Ok(b.findings),
+        Err(e) => Err(FindingsError::DocumentError(e)),
+    }
+}
+
+pub fn update<R>(
+    app_conf: &AppConfig,
+    f: impl FnOnce(&mut FindingBook) -> Result<R, FindingsError>,
+) -> Result<R, FindingsError> {
+    if app_conf.dyn_conf.current_workspace.is_empty() {
+        return Err(FindingsError::NoCurrentWorkspace);
+    }
+    let path = app_conf.dyn_conf.to_workspace().findings_path;
+    document::update(&path, FindingsError::DocumentError, f)
+}
@@ -0,0 +1,165 @@
+use std::{collections::BTreeMap, fmt, fs, io, path::Path};
+
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::document;
+use crate::{config::AppConfig, error::FlagsError};
+
+// ワークスペースで取ったフラグ (.prail/flags.json)
+//  {
+//      "platform": "htb",
+//      "expected": 2,
+//      "flags": [
+//          {"value": "...", "kind": "user", "host": "box", "source": "/home/alice/user.txt", "captured_at": "..."}
+//      ]
+//  }
+// platformを設定すると、flag addでそのプラットフォームの形式に合うかを確かめる
+// expectedは取るべきフラグの数で、プロンプトに 取った数/expected を表示するのに使う
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct FlagBoard {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub platform: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected: Option<u32>,
+    #[serde(default)]
+    pub flags: Vec<Flag>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Flag {
+    pub value: String,
+    pub kind: FlagKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    // どこで見つけたか
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    pub captured_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum FlagKind {
+    User,
+    Root,
+    Other,
+}
+
+impl fmt::Display for FlagKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FlagKind::User => write!(f, "user"),
+            FlagKind::Root => write!(f, "root"),
+            FlagKind::Other => write!(f, "other"),
+        }
+    }
+}
+
+// プラットフォームごとのフラグの形式
+// app configディレクトリの flag_formats.toml に `名前 = "正規表現"` と書くと、追加や上書きができる
+//  htb = '^(HTB\{[^}]+\}|[0-9a-f]{32})$'
+const FORMATS_FILE_NAME: &str = "flag_formats.toml";
+const BUILTIN_FORMATS: [(&str, &str); 5] = [
+    ("htb", r"^(HTB\{[^}]+\}|[0-9a-f]{32})$"),
+    ("thm", r"^THM\{[^}]+\}$"),
+    ("picoctf", r"^picoCTF\{[^}]+\}$"),
+    ("ctfd", r"^[A-Za-z0-9_]+\{[^}]+\}$"),
+    ("any", r"^\S+$"),
+];
+
+pub struct FlagFormat {
+    pub regex: String,
+    pub custom: bool,
+}
+
+pub fn formats(app_conf_path: &Path) -> Result<BTreeMap<String, FlagFormat>, FlagsError> {
+    let mut formats = BUILTIN_FORMATS
+        .iter()
+        .map(|(name, regex)| {
+            (
+                name.to_string(),
+                FlagFormat {
+                    regex: regex.to_string(),
+                    custom: false,
+                },
+            )
+        })
+        .collect::<BTreeMap<String, FlagFormat>>();
+    let path = app_conf_path.join(FORMATS_FILE_NAME);
+    let content = match fs::read_to_string(&path) {
+        Ok(c) => c,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(formats),
+        Err(e) => return Err(FlagsError::ReadFormatsError(path, e)),
+    };
+    let custom: BTreeMap<String, String> = match toml::from_str(&content) {
+        Ok(c) => c,
+        Err(e) => return Err(FlagsError::ParseFormatsError(path, e)),
+    };
+    for (name, regex) in custom {
+        formats.insert(
+            name,
+            FlagFormat {
+                regex,
+                custom: true,
+            },
+        );
+    }
+    Ok(formats)
+}
+
+// valueがplatformの形式に合うかを確かめる。前後の空白は呼び出し側で取り除いておく
+pub fn validate(
+    formats: &BTreeMap<String, FlagFormat>,
+    platform: &str,
+    value: &str,
+) -> Result<(), FlagsError> {
+    let format = match formats.get(platform) {
+        Some(f) => f,
+        None => return Err(FlagsError::UnknownPlatform(platform.to_string())),
+    };
+    let re = match Regex::new(&format.regex) {
+        Ok(r) => r,
+        Err(e) => return Err(FlagsError::InvalidFormat(platform.to_string(), e)),
+    };
+    if re.is_match(value) {
+        Ok(())
+    } else {
+        Err(FlagsError::FormatMismatch(
+            platform.to_string(),
+            format.regex.clone(),
+        ))
+    }
+}
+
+// プロンプトに出す 取った数/取るべき数。フラグもexpectedもなければNone
+pub fn progress(board: &FlagBoard) -> Option<String> {
+    match board.expected {
+        Some(n) => Some(format!("{}/{}", board.flags.len(), n)),
+        None if !board.flags.is_empty() => Some(board.flags.len().to_string()),
+        None => None,
+    }
+}
+
+pub fn load(app_conf: &AppConfig) -> Result<FlagBoard, FlagsError> {
+    if app_conf.dyn_conf.current_workspace.is_empty() {
+        return Err(FlagsError::NoCurrentWorkspace);
+    }
+    let path = app_conf.dyn_conf.to_workspace().flags_path;
+    match document::read(&path) {
+        Ok(f) => Ok(f),
+        Err(e) => Err(FlagsError::DocumentError(e)),
+    }
+}
+
+pub fn update<R>(
+    app_conf: &AppConfig,
+    f: impl FnOnce(&mut FlagBoard) -> Result<R, FlagsError>,
+) -> Result<R, FlagsError> {
+    if app_conf.dyn_conf.current_workspace.is_empty() {
+        return Err(FlagsError::NoCurrentWorkspace);
+    }
+    let path = app_conf.dyn_conf.to_workspace().flags_path;
+    document::update(&path, FlagsError::DocumentError, f)
+}
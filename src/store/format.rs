@@ -0,0 +1,107 @@
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::{error::VarsFormatError, json::json::Json};
+
+// varsファイルの保存形式
+// どの形式でも読み込んだ後はserde_json::Valueとして扱うので、vr/vm/vdなどのクエリは形式によらず同じように動く
+// 変更履歴(store::history)は形式によらずjsonで保存する
+//
+// tomlにはnullがないので、tomlのワークスペースでnullを書き込もうとするとエラーになる
+// また、tomlのトップレベルはテーブルでなければならない
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+pub enum VarsFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+pub const VARS_FILE_STEM: &str = "vars";
+// ワークスペースにvarsファイルが複数ある場合はこの順番で優先する
+pub const FORMATS: [VarsFormat; 3] = [VarsFormat::Json, VarsFormat::Yaml, VarsFormat::Toml];
+
+impl VarsFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            VarsFormat::Json => "json",
+            VarsFormat::Yaml => "yaml",
+            VarsFormat::Toml => "toml",
+        }
+    }
+
+    pub fn file_name(&self) -> String {
+        format!("{}.{}", VARS_FILE_STEM, self.extension())
+    }
+
+    // varsファイルのパスから形式を判定する。varsファイルでなければNone
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy();
+        FORMATS.into_iter().find(|f| name == f.file_name())
+    }
+
+    // 拡張子から形式を判定する。yamlは.ymlでもよい
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "json" => Some(VarsFormat::Json),
+            "yaml" | "yml" => Some(VarsFormat::Yaml),
+            "toml" => Some(VarsFormat::Toml),
+            _ => None,
+        }
+    }
+
+    pub fn parse(&self, content: &str) -> Result<Value, VarsFormatError> {
+        match self {
+            VarsFormat::Json => match Json::new(content) {
+                Ok(j) => Ok(j.data),
+                Err(e) => Err(VarsFormatError::JsonError(e)),
+            },
+            VarsFormat::Yaml => match serde_yaml::from_str(content) {
+                // 空のyamlファイルはnullになるので、空のオブジェクトとして扱う
+                Ok(Value::Null) => Ok(Value::Object(Default::default())),
+                Ok(v) => Ok(v),
+                Err(e) => Err(VarsFormatError::YamlError(e)),
+            },
+            VarsFormat::Toml => match toml::from_str(content) {
+                Ok(v) => Ok(v),
+                Err(e) => Err(VarsFormatError::TomlDeError(e)),
+            },
+        }
+    }
+
+    pub fn serialize(&self, value: &Value) -> Result<Vec<u8>, VarsFormatError> {
+        match self {
+            VarsFormat::Json => match serde_json::to_vec_pretty(value) {
+                Ok(v) => Ok(v),
+                Err(e) => Err(VarsFormatError::JsonError(e)),
+            },
+            VarsFormat::Yaml => match serde_yaml::to_string(value) {
+                Ok(v) => Ok(v.into_bytes()),
+                Err(e) => Err(VarsFormatError::YamlError(e)),
+            },
+            VarsFormat::Toml if contains_null(value) => Err(VarsFormatError::TomlNullError),
+            VarsFormat::Toml => match toml::to_string_pretty(value) {
+                Ok(v) => Ok(v.into_bytes()),
+                Err(e) => Err(VarsFormatError::TomlSerError(e)),
+            },
+        }
+    }
+
+    // 新しく作るvarsファイルの内容
+    pub fn empty_content(&self) -> &'static str {
+        match self {
+            VarsFormat::Json => "{}",
+            VarsFormat::Yaml => "{}\n",
+            VarsFormat::Toml => "",
+        }
+    }
+}
+
+fn contains_null(value: &Value) -> bool {
+    match value {
+        Value::Null => true,
+        Value::Object(map) => map.values().any(contains_null),
+        Value::Array(arr) => arr.iter().any(contains_null),
+        _ => false,
+    }
+}
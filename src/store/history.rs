@@ -0,0 +1,166 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::vars::fingerprint;
+use crate::error::VarsHistoryError;
+
+// varsファイルの変更履歴
+// .prail/history
+//  | journal.jsonl     リビジョンの一覧。1行に1つのリビジョンを追記していく
+//  | <rev>.json        そのリビジョンを書き込んだ直後のvarsファイルの内容 (varsファイルの形式によらずjson)
+// 書き込みはstore::vars::saveからvarsファイルの排他ロックを取った状態で行われる
+const JOURNAL_FILE_NAME: &str = "journal.jsonl";
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct Revision {
+    pub rev: u64,
+    pub time: DateTime<Utc>,
+    pub summary: String,
+    pub fingerprint: String,
+}
+
+pub fn revisions(history_path: &Path) -> Result<Vec<Revision>, VarsHistoryError> {
+    let content = match fs::read_to_string(history_path.join(JOURNAL_FILE_NAME)) {
+        Ok(c) => c,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(VarsHistoryError::ReadError(e)),
+    };
+    let mut revisions = vec![];
+    for line in content.lines().filter(|l| !l.trim().is_empty()) {
+        match serde_json::from_str::<Revision>(line) {
+            Ok(r) => revisions.push(r),
+            Err(e) => return Err(VarsHistoryError::ParseError(e)),
+        }
+    }
+    Ok(revisions)
+}
+
+// vars undoで記録するリビジョンのsummary。undoを続けたときにどこまで戻ったかをここから読み取る
+const UNDO_SUMMARY_PREFIX: &str = "vars undo (back to revision ";
+
+pub fn undo_summary(target: u64) -> String {
+    format!("{}{})", UNDO_SUMMARY_PREFIX, target)
+}
+
+fn undone_to(revision: &Revision) -> Option<u64> {
+    revision
+        .summary
+        .strip_prefix(UNDO_SUMMARY_PREFIX)?
+        .strip_suffix(')')?
+        .parse()
+        .ok()
+}
+
+// vars undoで戻す先のリビジョン。戻せるものがなければNone
+// undoで記録されたリビジョンは戻した先のリビジョンと同じ位置にいるものとして扱うので、
+// undoを繰り返すと2つの状態を行き来せずに、1つずつ前のリビジョンに戻っていく
+pub fn undo_target(revisions: &[Revision]) -> Option<u64> {
+    let mut i = revisions.len().checked_sub(1)?;
+    while let Some(target) = undone_to(&revisions[i]) {
+        // 戻した先は必ずundoより前にある。見つからなければ (履歴が壊れていれば) そこで止める
+        match revisions[..i].iter().position(|r| r.rev == target) {
+            Some(j) => i = j,
+            None => break,
+        }
+    }
+    Some(revisions[i.checked_sub(1)?].rev)
+}
+
+pub fn read_revision(history_path: &Path, rev: u64) -> Result<Value, VarsHistoryError> {
+    let content = match fs::read(history_path.join(format!("{}.json", rev))) {
+        Ok(c) => c,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(VarsHistoryError::RevisionNotFound(rev))
+        }
+        Err(e) => return Err(VarsHistoryError::ReadError(e)),
+    };
+    match serde_json::from_slice(&content) {
+        Ok(v) => Ok(v),
+        Err(e) => Err(VarsHistoryError::ParseError(e)),
+    }
+}
+
+// 記録するvarsファイルの内容
+// rawはファイルの中身そのもので、ハッシュの計算に使う。valueはjsonとして保存する
+pub struct Content<'a> {
+    pub raw: &'a [u8],
+    pub value: &'a Value,
+}
+
+// 書き込み前(before)と書き込み後(after)のvarsファイルの内容から、リビジョンを記録する関数
+// 履歴がまだない場合はbeforeを最初のリビジョンとして記録する。
+// beforeが最後のリビジョンと違う場合は、エディタなどで直接編集されたものとして、そのリビジョンも記録する
+pub fn record(
+    history_path: &Path,
+    before: Content,
+    after: Content,
+    summary: &str,
+) -> Result<u64, VarsHistoryError> {
+    match fs::create_dir_all(history_path) {
+        Ok(_) => (),
+        Err(e) => return Err(VarsHistoryError::WriteError(e)),
+    };
+    let revisions = revisions(history_path)?;
+    let next = match revisions.last() {
+        Some(last) => {
+            if last.fingerprint != fingerprint(before.raw) {
+                append(history_path, last.rev + 1, &before, "external edit")?;
+                last.rev + 2
+            } else {
+                last.rev + 1
+            }
+        }
+        None => {
+            append(history_path, 0, &before, "initial state")?;
+            1
+        }
+    };
+    append(history_path, next, &after, summary)?;
+    Ok(next)
+}
+
+fn append(
+    history_path: &Path,
+    rev: u64,
+    content: &Content,
+    summary: &str,
+) -> Result<(), VarsHistoryError> {
+    let json = match serde_json::to_vec_pretty(content.value) {
+        Ok(j) => j,
+        Err(e) => return Err(VarsHistoryError::ParseError(e)),
+    };
+    match fs::write(history_path.join(format!("{}.json", rev)), json) {
+        Ok(_) => (),
+        Err(e) => return Err(VarsHistoryError::WriteError(e)),
+    };
+    let revision = Revision {
+        rev,
+        time: Utc::now(),
+        summary: summary.to_string(),
+        fingerprint: fingerprint(content.raw),
+    };
+    let line = match serde_json::to_string(&revision) {
+        Ok(l) => l,
+        Err(e) => return Err(VarsHistoryError::ParseError(e)),
+    };
+    let mut journal = match fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(history_path.join(JOURNAL_FILE_NAME))
+    {
+        Ok(f) => f,
+        Err(e) => return Err(VarsHistoryError::WriteError(e)),
+    };
+    match writeln!(journal, "{}", line) {
+        Ok(_) => (),
+        Err(e) => return Err(VarsHistoryError::WriteError(e)),
+    };
+    Ok(())
+}
@@ -0,0 +1,224 @@
+use std::{collections::BTreeMap, fmt, net::IpAddr};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::document;
+use crate::{config::AppConfig, error::HostsError};
+
+// ワークスペースのホストとサービスの一覧 (.prail/hosts.json)
+// ホストは名前をキーにしたオブジェクトとして保存する
+//  {
+//      "dc01": {
+//          "addresses": ["10.0.0.10"],
+//          "hostnames": ["dc01.corp.local"],
+//          "os": "Windows Server 2019",
+//          "tags": ["ad"],
+//          "services": [{"port": 445, "protocol": "tcp", "state": "open", "product": "microsoft-ds"}]
+//      }
+//  }
+// vrや {{query}} からは hosts["dc01"].ip のように参照できる (store::viewを参照)
+pub type Hosts = BTreeMap<String, Host>;
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct Host {
+    #[serde(default)]
+    pub addresses: Vec<String>,
+    #[serde(default)]
+    pub hostnames: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub os: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub services: Vec<Service>,
+    // nmapのNSEスクリプトの出力 (スクリプトID -> 出力)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub scripts: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Service {
+    pub port: u16,
+    pub protocol: Protocol,
+    pub state: String,
+    // http, microsoft-ds などのサービス名
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub product: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub scripts: BTreeMap<String, String>,
+    // ffufなどで見つけたWebのパス (httpのサービスのみ)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<WebPath>,
+}
+
+// 見つけたURLとそのレスポンス。URLが同じものは1つにまとめる
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct WebPath {
+    pub url: String,
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub words: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    Tcp,
+    Udp,
+}
+
+impl fmt::Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Protocol::Tcp => write!(f, "tcp"),
+            Protocol::Udp => write!(f, "udp"),
+        }
+    }
+}
+
+impl Host {
+    // 同じポートとプロトコルのサービスがあれば、与えられた値で更新する
+    // 更新した場合は更新する前のサービスを返す
+    pub fn upsert_service(&mut self, service: Service) -> Option<Service> {
+        match self
+            .services
+            .iter_mut()
+            .find(|s| s.port == service.port && s.protocol == service.protocol)
+        {
+            Some(s) => {
+                let old = s.clone();
+                s.state = service.state;
+                if service.name.is_some() {
+                    s.name = service.name;
+                }
+                if service.product.is_some() {
+                    s.product = service.product;
+                }
+                if service.version.is_some() {
+                    s.version = service.version;
+                }
+                s.scripts.extend(service.scripts);
+                for p in service.paths {
+                    s.upsert_path(p);
+                }
+                Some(old)
+            }
+            None => {
+                self.services.push(service);
+                self.services.sort_by_key(|s| (s.protocol, s.port));
+                None
+            }
+        }
+    }
+}
+
+impl Service {
+    // 同じURLのパスがあれば更新する。更新した場合は更新する前の値を返す
+    pub fn upsert_path(&mut self, path: WebPath) -> Option<WebPath> {
+        match self.paths.iter_mut().find(|p| p.url == path.url) {
+            Some(p) => Some(std::mem::replace(p, path)),
+            None => {
+                self.paths.push(path);
+                self.paths.sort_by(|a, b| a.url.cmp(&b.url));
+                None
+            }
+        }
+    }
+}
+
+// このアドレスを持つホストの名前を探す
+pub fn find_by_address(hosts: &Hosts, address: &str) -> Option<String> {
+    hosts
+        .iter()
+        .find(|(_, h)| h.addresses.iter().any(|a| a == address))
+        .map(|(name, _)| name.clone())
+}
+
+// このホスト名を持つホストの名前を探す
+pub fn find_by_hostname(hosts: &Hosts, hostname: &str) -> Option<String> {
+    hosts
+        .iter()
+        .find(|(_, h)| h.hostnames.iter().any(|n| n.eq_ignore_ascii_case(hostname)))
+        .map(|(name, _)| name.clone())
+}
+
+// ホストの名前、アドレス、ホスト名のどれかで指定されたホストの名前を返す
+pub fn resolve(hosts: &Hosts, s: &str) -> Option<String> {
+    if hosts.contains_key(s) {
+        return Some(s.to_string());
+    }
+    find_by_address(hosts, s).or_else(|| find_by_hostname(hosts, s))
+}
+
+// アドレスやタグなど、重複させたくないリストに追加する
+pub fn add_unique(list: &mut Vec<String>, items: Vec<String>) {
+    for item in items {
+        if !list.contains(&item) {
+            list.push(item);
+        }
+    }
+}
+
+// 80, 80/tcp, 53/udp のような文字列を読む。プロトコルを省略した場合はtcp
+pub fn parse_port(s: &str) -> Result<(u16, Protocol), HostsError> {
+    let (port, protocol) = match s.split_once('/') {
+        Some((p, "tcp")) => (p, Protocol::Tcp),
+        Some((p, "udp")) => (p, Protocol::Udp),
+        Some(_) => return Err(HostsError::InvalidPort(s.to_string())),
+        None => (s, Protocol::Tcp),
+    };
+    match port.parse::<u16>() {
+        Ok(p) => Ok((p, protocol)),
+        Err(_) => Err(HostsError::InvalidPort(s.to_string())),
+    }
+}
+
+pub fn validate_address(address: &str) -> Result<(), HostsError> {
+    match address.parse::<IpAddr>() {
+        Ok(_) => Ok(()),
+        Err(_) => Err(HostsError::InvalidAddress(address.to_string())),
+    }
+}
+
+pub fn load(app_conf: &AppConfig) -> Result<Hosts, HostsError> {
+    if app_conf.dyn_conf.current_workspace.is_empty() {
+        return Err(HostsError::NoCurrentWorkspace);
+    }
+    let path = app_conf.dyn_conf.to_workspace().hosts_path;
+    match document::read(&path) {
+        Ok(h) => Ok(h),
+        Err(e) => Err(HostsError::DocumentError(e)),
+    }
+}
+
+pub fn update<R>(
+    app_conf: &AppConfig,
+    f: impl FnOnce(&mut Hosts) -> Result<R, HostsError>,
+) -> Result<R, HostsError> {
+    if app_conf.dyn_conf.current_workspace.is_empty() {
+        return Err(HostsError::NoCurrentWorkspace);
+    }
+    let path = app_conf.dyn_conf.to_workspace().hosts_path;
+    document::update(&path, HostsError::DocumentError, f)
+}
+
+// クエリで参照するときの形。最初のアドレスをipとしても参照できるようにする
+pub fn to_value(hosts: &Hosts) -> Value {
+    let mut map = serde_json::Map::new();
+    for (name, host) in hosts {
+        let mut v = json!(host);
+        v["ip"] = match host.addresses.first() {
+            Some(a) => Value::String(a.clone()),
+            None => Value::Null,
+        };
+        map.insert(name.clone(), v);
+    }
+    Value::Object(map)
+}
@@ -0,0 +1,101 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+// 複数のpwnrailシェルやスクリプトが同じファイルを同時に読み書きしても壊れないようにするためのアドバイザリロック
+// ロックは対象ファイルそのものではなく <ファイル名>.lock に対して取る。
+// 書き込みは一時ファイルへの書き込みとrenameで行うので、対象ファイルのinodeは書き込みのたびに変わってしまうため
+pub struct FileLock {
+    // dropされるとファイルが閉じられ、ロックも解放される
+    _file: fs::File,
+}
+
+pub fn lock_shared(path: &Path) -> Result<FileLock, io::Error> {
+    let file = open_lock_file(path)?;
+    file.lock_shared()?;
+    Ok(FileLock { _file: file })
+}
+
+pub fn lock_exclusive(path: &Path) -> Result<FileLock, io::Error> {
+    let file = open_lock_file(path)?;
+    file.lock()?;
+    Ok(FileLock { _file: file })
+}
+
+fn open_lock_file(path: &Path) -> Result<fs::File, io::Error> {
+    fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(with_suffix(path, ".lock"))
+}
+
+// 書き込みの途中で他のプロセスが中途半端な内容を読まないように、一時ファイルに書いてからrenameする
+pub fn write_atomic(path: &Path, content: &[u8]) -> Result<(), io::Error> {
+    let tmp_path = with_suffix(path, &format!(".tmp-{}", std::process::id()));
+    fs::write(&tmp_path, content)?;
+    if let Err(e) = fs::rename(&tmp_path, path) {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(e);
+    }
+    Ok(())
+}
+
+// 0600 でファイルを作る。すでにあるファイルは中身を消してからパーミッションを 0600 にする
+pub fn create_private(path: &Path) -> Result<fs::File, io::Error> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let file = options.open(path)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    }
+    Ok(file)
+}
+
+// creds.jsonなど秘密の値を含むファイルを、他のユーザーから読めないように 0600 で書き込む
+pub fn write_atomic_private(path: &Path, content: &[u8]) -> Result<(), io::Error> {
+    let tmp_path = with_suffix(path, &format!(".tmp-{}", std::process::id()));
+    let result = create_private(&tmp_path).and_then(|mut f| f.write_all(content));
+    if let Err(e) = result.and_then(|_| fs::rename(&tmp_path, path)) {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(e);
+    }
+    Ok(())
+}
+
+// エディタで編集させる一時ファイルを置くための、自分だけが使える (0700) ディレクトリを一時ディレクトリに作る
+// 他のユーザーが先に同じ名前のファイルやシンボリックリンクを作っていても使わないように、新しく作れるまで名前を変える
+// 使い終わったら呼び出し側でディレクトリごと消す
+pub fn create_private_temp_dir(name: &str) -> Result<PathBuf, io::Error> {
+    let mut builder = fs::DirBuilder::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    let mut n = 0;
+    loop {
+        let dir = std::env::temp_dir().join(format!("{}-{}-{}", name, std::process::id(), n));
+        match builder.create(&dir) {
+            Ok(_) => return Ok(dir),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists && n < 100 => n += 1,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
@@ -0,0 +1,227 @@
+use std::{fs, path::Path};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::{document, lock};
+use crate::{config::AppConfig, error::LootError};
+
+// ホストから持ち出したファイルの一覧 (.prail/loot.json)
+//  {
+//      "next_id": 2,
+//      "loot": [
+//          {
+//              "id": 1,
+//              "name": "id_rsa",
+//              "file": "1-id_rsa",
+//              "sha256": "...",
+//              "size": 2602,
+//              "mime": "application/x-pem-file",
+//              "sources": [
+//                  {"host": "web01", "path": "/tmp/id_rsa", "desc": "/home/alice/.ssh/id_rsa", "added_at": "..."}
+//              ],
+//              "added_at": "..."
+//          }
+//      ]
+//  }
+// ファイルは .prail/loot/ に <id>-<name> としてコピーし、fileにはlootからの相対パスを入れる
+// 同じ内容のファイルは一度だけコピーし、どこから持ってきたかをsourcesに足していく
+// 鍵やハッシュなどを含むので、コピーは他のユーザーから読めないように 0600 で書き込む
+// 消したlootのidを使い回すと、残っているファイルを上書きしてしまうので、next_idで次のidを覚えておく (store::credsと同じ)
+// next_idがなかったころのloot.jsonはlootの配列だけなので、そのまま読めるようにしておく
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(from = "LootIndexFile")]
+pub struct LootIndex {
+    pub next_id: u32,
+    pub loot: Vec<Loot>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LootIndexFile {
+    Index {
+        #[serde(default)]
+        next_id: u32,
+        #[serde(default)]
+        loot: Vec<Loot>,
+    },
+    Loot(Vec<Loot>),
+}
+
+impl From<LootIndexFile> for LootIndex {
+    fn from(file: LootIndexFile) -> Self {
+        match file {
+            LootIndexFile::Index { next_id, loot } => LootIndex { next_id, loot },
+            LootIndexFile::Loot(loot) => LootIndex { next_id: 0, loot },
+        }
+    }
+}
+
+impl LootIndex {
+    // 新しいlootのidを取る。next_idが古い (手で編集されたなど) 場合も、今あるidとは重ならないようにする
+    pub fn take_id(&mut self) -> u32 {
+        let max = self.loot.iter().map(|l| l.id).max().unwrap_or(0);
+        let id = self.next_id.max(max + 1);
+        self.next_id = id + 1;
+        id
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Loot {
+    pub id: u32,
+    pub name: String,
+    pub file: String,
+    pub sha256: String,
+    pub size: u64,
+    pub mime: String,
+    #[serde(default)]
+    pub sources: Vec<LootSource>,
+    pub added_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LootSource {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    // loot addに渡されたファイルの絶対パス
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub desc: Option<String>,
+    pub added_at: DateTime<Utc>,
+}
+
+pub fn sha256(content: &[u8]) -> String {
+    hex::encode(Sha256::digest(content))
+}
+
+// ファイルの先頭のバイト列と拡張子からMIMEタイプを推測する
+// 分からなければ、UTF-8として読めるものはtext/plain、それ以外はapplication/octet-stream
+pub fn guess_mime(name: &str, content: &[u8]) -> String {
+    const MAGIC: [(&[u8], &str); 10] = [
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF8", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+        (b"\x7fELF", "application/x-elf"),
+        (b"MZ", "application/x-msdownload"),
+        (b"SQLite format 3\0", "application/vnd.sqlite3"),
+        (b"\x28\xb5\x2f\xfd", "application/zstd"),
+    ];
+    if let Some((_, mime)) = MAGIC.iter().find(|(m, _)| content.starts_with(m)) {
+        return mime.to_string();
+    }
+    // 秘密鍵や証明書 (id_rsa, *.pem など)
+    if content.starts_with(b"-----BEGIN ") {
+        return "application/x-pem-file".to_string();
+    }
+    let ext = name
+        .rsplit_once('.')
+        .map(|(_, e)| e.to_lowercase())
+        .unwrap_or_default();
+    let mime = match ext.as_str() {
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "html" | "htm" => "text/html",
+        "csv" => "text/csv",
+        "kdbx" => "application/x-keepass2",
+        "pcap" | "pcapng" => "application/vnd.tcpdump.pcap",
+        _ if std::str::from_utf8(content).is_ok() && !content.contains(&0) => "text/plain",
+        _ => "application/octet-stream",
+    };
+    mime.to_string()
+}
+
+// ファイルを loot/ にコピーして一覧に加える
+// 同じ内容のものがすでにあればコピーせずに、まだ記録していない入手元だけを足す
+// 戻り値は (一覧の中のファイル, 新しくコピーしたかどうか)
+pub fn store(
+    loot_path: &Path,
+    index: &mut LootIndex,
+    source: &Path,
+    host: Option<String>,
+    desc: Option<String>,
+) -> Result<(Loot, bool), LootError> {
+    let content = match fs::read(source) {
+        Ok(c) => c,
+        Err(e) => return Err(LootError::ReadError(source.to_path_buf(), e)),
+    };
+    let path = match fs::canonicalize(source) {
+        Ok(p) => p,
+        Err(e) => return Err(LootError::ReadError(source.to_path_buf(), e)),
+    };
+    let new_source = LootSource {
+        host,
+        path: path.display().to_string(),
+        desc,
+        added_at: Utc::now(),
+    };
+    let hash = sha256(&content);
+    if let Some(l) = index.loot.iter_mut().find(|l| l.sha256 == hash) {
+        let known = l
+            .sources
+            .iter()
+            .any(|s| s.host == new_source.host && s.path == new_source.path);
+        if !known {
+            l.sources.push(new_source);
+        }
+        return Ok((l.clone(), false));
+    }
+
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "loot".to_string());
+    if let Err(e) = fs::create_dir_all(loot_path) {
+        return Err(LootError::WriteError(loot_path.to_path_buf(), e));
+    }
+    // loot.jsonを手で編集した場合などに備えて、同じ名前のファイルがあれば次のidにする
+    let (id, file) = loop {
+        let id = index.take_id();
+        let file = format!("{}-{}", id, name);
+        if !loot_path.join(&file).exists() {
+            break (id, file);
+        }
+    };
+    let dest = loot_path.join(&file);
+    if let Err(e) = lock::write_atomic_private(&dest, &content) {
+        return Err(LootError::WriteError(dest, e));
+    }
+    let loot = Loot {
+        id,
+        mime: guess_mime(&name, &content),
+        name,
+        file,
+        sha256: hash,
+        size: content.len() as u64,
+        sources: vec![new_source],
+        added_at: Utc::now(),
+    };
+    index.loot.push(loot.clone());
+    Ok((loot, true))
+}
+
+pub fn load(app_conf: &AppConfig) -> Result<Vec<Loot>, LootError> {
+    if app_conf.dyn_conf.current_workspace.is_empty() {
+        return Err(LootError::NoCurrentWorkspace);
+    }
+    let path = app_conf.dyn_conf.to_workspace().loot_index_path;
+    match document::read::<LootIndex>(&path) {
+        Ok(i) => Ok(i.loot),
+        Err(e) => Err(LootError::DocumentError(e)),
+    }
+}
+
+pub fn update<R>(
+    app_conf: &AppConfig,
+    f: impl FnOnce(&mut LootIndex) -> Result<R, LootError>,
+) -> Result<R, LootError> {
+    if app_conf.dyn_conf.current_workspace.is_empty() {
+        return Err(LootError::NoCurrentWorkspace);
+    }
+    let path = app_conf.dyn_conf.to_workspace().loot_index_path;
+    document::update(&path, LootError::DocumentError, f)
+}
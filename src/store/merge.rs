@@ -0,0 +1,129 @@
+use serde_json::Value;
+
+// 3-way merge
+// baseは読み込んだ時点の内容、oursはこのプロセスが変更した内容、theirsは書き込む直前のファイルの内容。
+// オブジェクトはキーごとに再帰的にマージし、両方が同じ場所を別の値に変更していた場合はその場所をErrで返す。
+// 配列は要素ごとにはマージせず、1つの値として扱う
+pub fn merge3(base: &Value, ours: &Value, theirs: &Value) -> Result<Value, String> {
+    match merge3_(Some(base), Some(ours), Some(theirs), "")? {
+        Some(v) => Ok(v),
+        None => Ok(Value::Object(serde_json::Map::new())),
+    }
+}
+
+// Noneはそのキーが存在しないことを表す
+fn merge3_(
+    base: Option<&Value>,
+    ours: Option<&Value>,
+    theirs: Option<&Value>,
+    path: &str,
+) -> Result<Option<Value>, String> {
+    if ours == base {
+        return Ok(theirs.cloned());
+    }
+    if theirs == base || ours == theirs {
+        return Ok(ours.cloned());
+    }
+    if let (Some(Value::Object(o)), Some(Value::Object(t))) = (ours, theirs) {
+        let empty = serde_json::Map::new();
+        let b = match base {
+            Some(Value::Object(b)) => b,
+            _ => &empty,
+        };
+        let mut keys = o.keys().chain(t.keys()).collect::<Vec<&String>>();
+        keys.sort();
+        keys.dedup();
+        let mut merged = serde_json::Map::new();
+        for key in keys {
+            let child_path = if path.is_empty() {
+                key.clone()
+            } else {
+                format!("{}.{}", path, key)
+            };
+            if let Some(v) = merge3_(b.get(key), o.get(key), t.get(key), &child_path)? {
+                merged.insert(key.clone(), v);
+            }
+        }
+        return Ok(Some(Value::Object(merged)));
+    }
+    if path.is_empty() {
+        Err(".".to_string())
+    } else {
+        Err(path.to_string())
+    }
+}
+
+// vars mergeで別のjsonを取り込むときの、両方に値がある場所の扱い方
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+pub enum MergeStrategy {
+    // 取り込む値で上書きする
+    Overwrite,
+    // 今の値を残す
+    Keep,
+    // 配列は今の配列にない要素を後ろに足す。それ以外はkeepと同じ
+    Append,
+}
+
+// 両方に別の値があった場所。pathはvrで使えるクエリ
+pub struct MergeConflict {
+    pub path: String,
+    pub ours: Value,
+    pub theirs: Value,
+}
+
+// theirsをoursに再帰的にマージする
+// オブジェクトはキーごとにマージし、片方にしかないキーはそのまま使う
+// pathはoursがvars全体のどこにあるかを表すクエリ (全体なら空文字列)
+pub fn deep_merge(
+    ours: &Value,
+    theirs: &Value,
+    strategy: MergeStrategy,
+    path: &str,
+    conflicts: &mut Vec<MergeConflict>,
+) -> Value {
+    if ours == theirs {
+        return ours.clone();
+    }
+    match (ours, theirs) {
+        (Value::Object(o), Value::Object(t)) => {
+            let mut merged = o.clone();
+            for (key, tv) in t {
+                let child_path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                let v = match o.get(key) {
+                    Some(ov) => deep_merge(ov, tv, strategy, &child_path, conflicts),
+                    None => tv.clone(),
+                };
+                merged.insert(key.clone(), v);
+            }
+            Value::Object(merged)
+        }
+        (Value::Array(o), Value::Array(t)) if strategy == MergeStrategy::Append => {
+            let mut merged = o.clone();
+            for v in t {
+                if !merged.contains(v) {
+                    merged.push(v.clone());
+                }
+            }
+            Value::Array(merged)
+        }
+        _ => {
+            conflicts.push(MergeConflict {
+                path: if path.is_empty() {
+                    ".".to_string()
+                } else {
+                    path.to_string()
+                },
+                ours: ours.clone(),
+                theirs: theirs.clone(),
+            });
+            match strategy {
+                MergeStrategy::Overwrite => theirs.clone(),
+                MergeStrategy::Keep | MergeStrategy::Append => ours.clone(),
+            }
+        }
+    }
+}
@@ -0,0 +1,20 @@
+pub mod creds;
+pub mod document;
+pub mod findings;
+pub mod flags;
+pub mod format;
+pub mod history;
+pub mod hosts;
+pub mod lock;
+pub mod loot;
+pub mod merge;
+pub mod notes;
+pub mod schema;
+pub mod secret;
+pub mod timeline;
+pub mod vars;
+pub mod view;
+pub mod watch;
+
+#[cfg(test)]
+mod test;
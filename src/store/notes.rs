@@ -0,0 +1,105 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::Local;
+
+use super::hosts::Protocol;
+
+// ワークスペースのMarkdownのメモ (.prail/notes/)
+//  notes/
+//  | workspace.md          ワークスペース全体のメモ
+//  | hosts/dc01.md         ホストのメモ
+//  | hosts/dc01/445-tcp.md サービスのメモ
+// ホストはhosts.jsonの名前でファイルにするので、ホストとメモの対応はファイル名で分かる
+pub enum NoteTarget {
+    Workspace,
+    Host(String),
+    Service(String, u16, Protocol),
+}
+
+impl NoteTarget {
+    pub fn path(&self, notes_path: &Path) -> PathBuf {
+        match self {
+            NoteTarget::Workspace => notes_path.join("workspace.md"),
+            NoteTarget::Host(host) => notes_path
+                .join("hosts")
+                .join(format!("{}.md", file_name(host))),
+            NoteTarget::Service(host, port, protocol) => notes_path
+                .join("hosts")
+                .join(file_name(host))
+                .join(format!("{}-{}.md", port, protocol)),
+        }
+    }
+
+    // 新しく作るメモの見出し
+    fn title(&self) -> String {
+        match self {
+            NoteTarget::Workspace => "# notes".to_string(),
+            NoteTarget::Host(host) => format!("# {}", host),
+            NoteTarget::Service(host, port, protocol) => {
+                format!("# {} {}/{}", host, port, protocol)
+            }
+        }
+    }
+}
+
+// ホストの名前にパスの区切りなどが含まれていても、notesの外にファイルを作らないようにする
+fn file_name(name: &str) -> String {
+    let name = name
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect::<String>();
+    match name.as_str() {
+        "." | ".." => name.replace('.', "_"),
+        _ => name,
+    }
+}
+
+// メモがなければ見出しだけのファイルを作る。作ったときはtrueを返す
+pub fn ensure(notes_path: &Path, target: &NoteTarget) -> Result<bool, io::Error> {
+    let path = target.path(notes_path);
+    if path.exists() {
+        return Ok(false);
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, format!("{}\n\n", target.title()))?;
+    Ok(true)
+}
+
+// 時刻を付けた1行を末尾に足す
+pub fn append(notes_path: &Path, target: &NoteTarget, text: &str) -> Result<PathBuf, io::Error> {
+    ensure(notes_path, target)?;
+    let path = target.path(notes_path);
+    let mut file = fs::OpenOptions::new().append(true).open(&path)?;
+    writeln!(file, "- {} {}", Local::now().format("%Y-%m-%d %H:%M"), text)?;
+    Ok(path)
+}
+
+// notesの下にあるメモのファイルを、notesからの相対パスの順に返す
+pub fn list(notes_path: &Path) -> Result<Vec<PathBuf>, io::Error> {
+    let mut notes = vec![];
+    if !notes_path.exists() {
+        return Ok(notes);
+    }
+    let mut stack = vec![notes_path.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                stack.push(path);
+            } else if path.extension().is_some_and(|e| e == "md") {
+                notes.push(path);
+            }
+        }
+    }
+    notes.sort();
+    Ok(notes)
+}
@@ -0,0 +1,112 @@
+use std::{fs, path::PathBuf};
+
+use jsonschema::paths::LocationSegment;
+use serde_json::Value;
+
+use super::secret;
+use crate::{config::is_entry_exist, error::VarsSchemaError};
+
+// varsファイルの値をJSON Schemaで検査する
+// ワークスペースに .prail/vars.schema.json があるときだけ、varsファイルに書き込む前に検査する
+// 検査はvarsファイルの形式によらず、読み込んだあとのjsonに対して行う
+// vm --secretで暗号化した値と {{query}} を含む値は、書き込む時点では実際の値が分からないので、違反していても無視する
+// "vars schema init" で書き出すテンプレートでは、よく使う変数について次のことを確かめる
+//  ip      IPv4かIPv6のアドレスの文字列
+//  ports   整数の配列
+//  creds   userを持つオブジェクトの配列
+pub const SCHEMA_FILE_NAME: &str = "vars.schema.json";
+
+pub const TEMPLATE: &str = r#"{
+  "$schema": "https://json-schema.org/draft/2020-12/schema",
+  "type": "object",
+  "properties": {
+    "ip": {
+      "type": "string",
+      "anyOf": [{ "format": "ipv4" }, { "format": "ipv6" }]
+    },
+    "ports": {
+      "type": "array",
+      "items": { "type": "integer", "minimum": 0, "maximum": 65535 }
+    },
+    "creds": {
+      "type": "array",
+      "items": { "type": "object", "required": ["user"] }
+    }
+  }
+}
+"#;
+
+// スキーマに違反している箇所
+// pathはvrなどと同じクエリの形式、ruleは違反したキーワード (type, requiredなど)
+#[derive(Debug)]
+pub struct Violation {
+    pub path: String,
+    pub rule: String,
+    pub message: String,
+}
+
+// スキーマファイルがなければ何もしない
+pub fn validate(schema_path: &PathBuf, value: &Value) -> Result<(), VarsSchemaError> {
+    let schema = match is_entry_exist(schema_path) {
+        Ok(true) => read(schema_path)?,
+        Ok(false) => return Ok(()),
+        Err(e) => return Err(VarsSchemaError::ReadError(e)),
+    };
+    let validator = match jsonschema::options()
+        .should_validate_formats(true)
+        .build(&schema)
+    {
+        Ok(v) => v,
+        Err(e) => return Err(VarsSchemaError::InvalidSchema(e.to_string())),
+    };
+    // 暗号化された値などが長々と表示されないように、メッセージでは値を伏せる
+    let violations = validator
+        .iter_errors(value)
+        .filter(|e| !is_unresolved(e.instance()))
+        .map(|e| Violation {
+            path: to_query(&e.instance_path().iter().collect::<Vec<_>>()),
+            rule: e.kind().keyword().to_string(),
+            message: e.masked().to_string(),
+        })
+        .collect::<Vec<Violation>>();
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(VarsSchemaError::Violation(violations))
+    }
+}
+
+fn is_unresolved(value: &Value) -> bool {
+    match value {
+        Value::String(s) => secret::is_secret(value) || s.contains("{{"),
+        _ => false,
+    }
+}
+
+fn read(schema_path: &PathBuf) -> Result<Value, VarsSchemaError> {
+    let content = match fs::read_to_string(schema_path) {
+        Ok(c) => c,
+        Err(e) => return Err(VarsSchemaError::ReadError(e)),
+    };
+    match serde_json::from_str(&content) {
+        Ok(s) => Ok(s),
+        Err(e) => Err(VarsSchemaError::ParseError(e)),
+    }
+}
+
+// ["creds", 0, "user"] -> creds[0].user
+fn to_query(segments: &[LocationSegment]) -> String {
+    let mut query = String::new();
+    for segment in segments {
+        match segment {
+            LocationSegment::Index(i) => query.push_str(&format!("[{}]", i)),
+            LocationSegment::Property(p) if query.is_empty() => query.push_str(p),
+            LocationSegment::Property(p) => query.push_str(&format!(".{}", p)),
+        }
+    }
+    if query.is_empty() {
+        ".".to_string()
+    } else {
+        query
+    }
+}
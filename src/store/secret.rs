@@ -0,0 +1,268 @@
+use std::{fs, io::Write, path::Path};
+
+use argon2::Argon2;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chacha20poly1305::{
+    aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng},
+    XChaCha20Poly1305, XNonce,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use zeroize::Zeroizing;
+
+use super::lock;
+use crate::{config::is_entry_exist, error::SecretError};
+
+// varsファイルに暗号化して保存する値 (vm --secret)
+// 値は "pwnrail-secret:v1:<base64(nonce || ciphertext)>" という文字列としてjsonに入れる
+// 文字列のままなので、マージや変更履歴、ws exportではほかの値と同じように扱える
+//
+// 鍵はapp configディレクトリに置く次のどちらかから作る
+//  secret.key      32バイトの鍵を16進数で書いたファイル。あればパスフレーズを聞かずにこれを使う
+//  パスフレーズ    secret.tomlのsaltとargon2idで鍵を導出する
+// secret.tomlには正しい鍵かどうかを確かめるための値も入れておく。最初のunlockで作られる
+pub const SECRET_PREFIX: &str = "pwnrail-secret:v1:";
+// vrなどで、まだ復号していない・復号しない値の代わりに表示する文字列
+pub const MASKED_VALUE: &str = "<secret>";
+
+const KEY_FILE_NAME: &str = "secret.key";
+const SECRET_CONF_FILE_NAME: &str = "secret.toml";
+const CHECK_PLAINTEXT: &str = "pwnrail";
+const NONCE_LEN: usize = 24;
+
+// unlockしている間だけAppConfigに保持する鍵。dropされるとメモリ上の鍵はゼロで上書きされる
+pub struct SecretKey(Zeroizing<[u8; 32]>);
+
+#[derive(Deserialize, Serialize, Debug)]
+struct SecretConf {
+    salt: String,
+    check: String,
+}
+
+pub fn is_secret(value: &Value) -> bool {
+    matches!(value, Value::String(s) if s.starts_with(SECRET_PREFIX))
+}
+
+pub fn encrypt(key: &SecretKey, plaintext: &str) -> Result<String, SecretError> {
+    let cipher = XChaCha20Poly1305::new(key.0.as_ref().into());
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = match cipher.encrypt(&nonce, plaintext.as_bytes()) {
+        Ok(c) => c,
+        Err(_) => return Err(SecretError::EncryptError),
+    };
+    let mut blob = nonce.to_vec();
+    blob.extend(ciphertext);
+    Ok(format!("{}{}", SECRET_PREFIX, BASE64.encode(blob)))
+}
+
+pub fn decrypt(key: &SecretKey, value: &str) -> Result<String, SecretError> {
+    let encoded = match value.strip_prefix(SECRET_PREFIX) {
+        Some(e) => e,
+        None => return Err(SecretError::InvalidFormat),
+    };
+    let blob = match BASE64.decode(encoded) {
+        Ok(b) => b,
+        Err(_) => return Err(SecretError::InvalidFormat),
+    };
+    if blob.len() < NONCE_LEN {
+        return Err(SecretError::InvalidFormat);
+    }
+    let (nonce, ciphertext) = blob.split_at(NONCE_LEN);
+    let cipher = XChaCha20Poly1305::new(key.0.as_ref().into());
+    let plaintext = match cipher.decrypt(XNonce::from_slice(nonce), ciphertext) {
+        Ok(p) => Zeroizing::new(p),
+        Err(_) => return Err(SecretError::DecryptError),
+    };
+    match String::from_utf8(plaintext.to_vec()) {
+        Ok(s) => Ok(s),
+        Err(_) => Err(SecretError::DecryptError),
+    }
+}
+
+// 暗号化された値をすべて <secret> に置き換える
+pub fn mask(value: &mut Value) {
+    match value {
+        Value::Object(map) => map.values_mut().for_each(mask),
+        Value::Array(arr) => arr.iter_mut().for_each(mask),
+        v if is_secret(v) => *v = Value::String(MASKED_VALUE.to_string()),
+        _ => (),
+    }
+}
+
+// 秘密情報らしいキー名 (小文字にして部分一致で判定する)
+const SECRET_KEY_PATTERNS: [&str; 10] = [
+    "pass",
+    "pwd",
+    "secret",
+    "token",
+    "hash",
+    "ntlm",
+    "apikey",
+    "api_key",
+    "private_key",
+    "privkey",
+];
+
+pub fn is_secret_key(key: &str) -> bool {
+    let key = key.to_lowercase();
+    SECRET_KEY_PATTERNS.iter().any(|p| key.contains(p))
+}
+
+// 秘密情報らしいキーの値と暗号化された値を replacement に置き換える
+// 値がオブジェクトや配列の場合は、その中のすべての値を置き換える
+pub fn strip_secret_keys(value: &mut Value, replacement: &str) {
+    match value {
+        Value::Object(map) => {
+            for (k, v) in map.iter_mut() {
+                if is_secret_key(k) {
+                    replace_all(v, replacement);
+                } else {
+                    strip_secret_keys(v, replacement);
+                }
+            }
+        }
+        Value::Array(arr) => arr
+            .iter_mut()
+            .for_each(|v| strip_secret_keys(v, replacement)),
+        // vm --secretで暗号化された値はキー名に関係なく置き換える
+        v if is_secret(v) => *v = Value::String(replacement.to_string()),
+        _ => (),
+    }
+}
+
+// null以外のすべての値を replacement に置き換える
+pub fn replace_all(value: &mut Value, replacement: &str) {
+    match value {
+        Value::Object(map) => map.values_mut().for_each(|v| replace_all(v, replacement)),
+        Value::Array(arr) => arr.iter_mut().for_each(|v| replace_all(v, replacement)),
+        Value::Null => (),
+        _ => *value = Value::String(replacement.to_string()),
+    }
+}
+
+// テンプレート ({{query}}) に値を埋め込むときの文字列
+// 秘密の値はunlockされていれば復号し、そうでなければ <secret> にする。オブジェクトや配列の中の秘密の値は常に隠す
+pub fn to_text(key: Option<&SecretKey>, value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) if is_secret(value) => match key.map(|k| decrypt(k, s)) {
+            Some(Ok(plain)) => Some(plain),
+            _ => Some(MASKED_VALUE.to_string()),
+        },
+        Value::Object(_) | Value::Array(_) => {
+            let mut v = value.clone();
+            mask(&mut v);
+            Some(v.to_string())
+        }
+        _ => None,
+    }
+}
+
+// 鍵を読み込む。鍵ファイルがなければパスフレーズを聞く
+// 初めてのunlockではsecret.tomlを作る。パスフレーズの場合は確認のためにもう一度入力させる
+pub fn unlock(app_conf_path: &Path) -> Result<SecretKey, SecretError> {
+    let conf_path = app_conf_path.join(SECRET_CONF_FILE_NAME);
+    let conf = match is_entry_exist(&conf_path) {
+        Ok(true) => Some(read_conf(&conf_path)?),
+        Ok(false) => None,
+        Err(e) => return Err(SecretError::ReadConfError(e)),
+    };
+    let salt = match &conf {
+        Some(c) => match BASE64.decode(&c.salt) {
+            Ok(s) => s,
+            Err(_) => return Err(SecretError::InvalidConf),
+        },
+        None => {
+            let mut salt = vec![0u8; 16];
+            OsRng.fill_bytes(&mut salt);
+            salt
+        }
+    };
+
+    let key_path = app_conf_path.join(KEY_FILE_NAME);
+    let key = match is_entry_exist(&key_path) {
+        Ok(true) => read_key_file(&key_path)?,
+        Ok(false) => derive_key(&salt, conf.is_none())?,
+        Err(e) => return Err(SecretError::ReadKeyFileError(e)),
+    };
+
+    match conf {
+        Some(c) => verify(&key, &c.check)?,
+        None => {
+            let conf = SecretConf {
+                salt: BASE64.encode(&salt),
+                check: encrypt(&key, CHECK_PLAINTEXT)?,
+            };
+            write_conf(&conf_path, &conf)?;
+        }
+    }
+    Ok(key)
+}
+
+// secret.tomlの検証値を復号できれば正しい鍵
+pub(crate) fn verify(key: &SecretKey, check: &str) -> Result<(), SecretError> {
+    match decrypt(key, check) {
+        Ok(p) if p == CHECK_PLAINTEXT => Ok(()),
+        _ => Err(SecretError::WrongKey),
+    }
+}
+
+fn read_conf(path: &Path) -> Result<SecretConf, SecretError> {
+    let content = match fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) => return Err(SecretError::ReadConfError(e)),
+    };
+    match toml::from_str(&content) {
+        Ok(c) => Ok(c),
+        Err(_) => Err(SecretError::InvalidConf),
+    }
+}
+
+fn write_conf(path: &Path, conf: &SecretConf) -> Result<(), SecretError> {
+    let content = match toml::to_string(conf) {
+        Ok(c) => c,
+        Err(_) => return Err(SecretError::InvalidConf),
+    };
+    // 鍵の検証値とsaltなので、他のユーザーからは読めないようにする
+    match lock::create_private(path).and_then(|mut f| f.write_all(content.as_bytes())) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(SecretError::WriteConfError(e)),
+    }
+}
+
+fn read_key_file(path: &Path) -> Result<SecretKey, SecretError> {
+    let content = match fs::read_to_string(path) {
+        Ok(c) => Zeroizing::new(c),
+        Err(e) => return Err(SecretError::ReadKeyFileError(e)),
+    };
+    let mut key = Zeroizing::new([0u8; 32]);
+    match hex::decode_to_slice(content.trim(), key.as_mut()) {
+        Ok(_) => Ok(SecretKey(key)),
+        Err(_) => Err(SecretError::InvalidKeyFile),
+    }
+}
+
+fn derive_key(salt: &[u8], confirm: bool) -> Result<SecretKey, SecretError> {
+    let passphrase = read_passphrase("passphrase: ")?;
+    if confirm {
+        let again = read_passphrase("passphrase (again): ")?;
+        if *passphrase != *again {
+            return Err(SecretError::PassphraseMismatch);
+        }
+    }
+    key_from_passphrase(&passphrase, salt)
+}
+
+pub(crate) fn key_from_passphrase(passphrase: &str, salt: &[u8]) -> Result<SecretKey, SecretError> {
+    let mut key = Zeroizing::new([0u8; 32]);
+    match Argon2::default().hash_password_into(passphrase.as_bytes(), salt, key.as_mut()) {
+        Ok(_) => Ok(SecretKey(key)),
+        Err(e) => Err(SecretError::KeyDerivationError(e.to_string())),
+    }
+}
+
+fn read_passphrase(prompt: &str) -> Result<Zeroizing<String>, SecretError> {
+    match rpassword::prompt_password(prompt) {
+        Ok(p) => Ok(Zeroizing::new(p)),
+        Err(e) => Err(SecretError::ReadPassphraseError(e)),
+    }
+}
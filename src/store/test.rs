@@ -0,0 +1,658 @@
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use chrono::Utc;
+    use serde_json::json;
+
+    use super::super::{
+        creds::{CredKind, Credential, Try, TryResult, Vault},
+        findings::FindingBook,
+        flags,
+        format::VarsFormat,
+        history::{undo_summary, undo_target, Revision},
+        hosts::{Host, Hosts},
+        lock::create_private_temp_dir,
+        loot::{self, LootIndex},
+        merge::{deep_merge, merge3, MergeStrategy},
+        schema::{validate, TEMPLATE},
+        secret::{
+            decrypt, encrypt, is_secret, key_from_passphrase, mask, strip_secret_keys, verify,
+            MASKED_VALUE, SECRET_PREFIX,
+        },
+        timeline::redact_line,
+        view::attach_data,
+        watch::{Tracker, WatchEvent},
+    };
+    use crate::error::{FlagsError, SecretError, VarsFormatError};
+
+    // テストごとに別の一時ディレクトリを作る
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("pwnrail-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn merge_unchanged_by_us() {
+        let base = json!({"ip": "10.0.0.1"});
+        let theirs = json!({"ip": "10.0.0.2"});
+        assert_eq!(merge3(&base, &base, &theirs).unwrap(), theirs);
+    }
+
+    #[test]
+    fn merge_disjoint_keys() {
+        let base = json!({"ip": "10.0.0.1", "ports": {"http": 80}});
+        let ours = json!({"ip": "10.0.0.5", "ports": {"http": 80}});
+        let theirs = json!({"ip": "10.0.0.1", "ports": {"http": 80, "ssh": 22}});
+        assert_eq!(
+            merge3(&base, &ours, &theirs).unwrap(),
+            json!({"ip": "10.0.0.5", "ports": {"http": 80, "ssh": 22}})
+        );
+    }
+
+    #[test]
+    fn merge_deleted_key() {
+        let base = json!({"ip": "10.0.0.1", "old": 1});
+        let ours = json!({"ip": "10.0.0.1"});
+        let theirs = json!({"ip": "10.0.0.1", "old": 1, "new": 2});
+        assert_eq!(
+            merge3(&base, &ours, &theirs).unwrap(),
+            json!({"ip": "10.0.0.1", "new": 2})
+        );
+    }
+
+    #[test]
+    fn merge_conflict() {
+        let base = json!({"creds": {"user": "a"}});
+        let ours = json!({"creds": {"user": "b"}});
+        let theirs = json!({"creds": {"user": "c"}});
+        assert_eq!(merge3(&base, &ours, &theirs).unwrap_err(), "creds.user");
+    }
+
+    #[test]
+    fn merge_same_change() {
+        let base = json!({"ip": "10.0.0.1"});
+        let ours = json!({"ip": "10.0.0.2"});
+        assert_eq!(merge3(&base, &ours, &ours).unwrap(), ours);
+    }
+
+    #[test]
+    fn deep_merge_keep() {
+        let ours = json!({"ip": "10.0.0.5", "domain": {"name": "corp.local"}});
+        let theirs = json!({"ip": "10.0.0.1", "domain": {"dc": "10.0.0.10"}});
+        let mut conflicts = vec![];
+        let merged = deep_merge(&ours, &theirs, MergeStrategy::Keep, "", &mut conflicts);
+        assert_eq!(
+            merged,
+            json!({"ip": "10.0.0.5", "domain": {"name": "corp.local", "dc": "10.0.0.10"}})
+        );
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].path, "ip");
+    }
+
+    #[test]
+    fn deep_merge_append() {
+        let ours = json!({"ports": [22, 445]});
+        let theirs = json!({"ports": [445, 3389]});
+        let mut conflicts = vec![];
+        let merged = deep_merge(&ours, &theirs, MergeStrategy::Append, "", &mut conflicts);
+        assert_eq!(merged, json!({"ports": [22, 445, 3389]}));
+        assert!(conflicts.is_empty());
+    }
+
+    #[test]
+    fn cred_try_result_per_service() {
+        let mut cred = Credential {
+            id: 1,
+            user: "admin".to_string(),
+            domain: None,
+            kind: CredKind::Password,
+            secret: "Summer2023!".to_string(),
+            source: None,
+            tries: vec![],
+            added_at: Utc::now(),
+        };
+        let try_ = |service: &str, result| Try {
+            host: "dc01".to_string(),
+            service: Some(service.to_string()),
+            result,
+            tried_at: Utc::now(),
+        };
+        cred.record_try(try_("445/tcp", TryResult::Failure));
+        assert_eq!(cred.result_on("dc01"), Some(TryResult::Failure));
+        cred.record_try(try_("5985/tcp", TryResult::Success));
+        cred.record_try(try_("445/tcp", TryResult::Failure));
+        assert_eq!(cred.tries.len(), 2);
+        assert_eq!(cred.result_on("dc01"), Some(TryResult::Success));
+        assert_eq!(cred.result_on("web01"), None);
+    }
+
+    #[test]
+    fn redact_secret_lines() {
+        assert_eq!(
+            redact_line("vm --secret creds.admin S3cret! "),
+            "vm --secret creds.admin <redacted>"
+        );
+        assert_eq!(
+            redact_line("cred add --user admin --pass S3cret! --source smb"),
+            "cred add --user admin --pass <redacted> --source smb"
+        );
+        assert_eq!(
+            redact_line("cred add --user a --hash=abcd"),
+            "cred add --user a --hash=<redacted>"
+        );
+        // 引用符で囲んだ空白を含む値も、まとめて伏せる
+        assert_eq!(
+            redact_line(r#"cred add --user a --pass "S3 cret!" --source 'smb share'"#),
+            "cred add --user a --pass <redacted> --source 'smb share'"
+        );
+        assert_eq!(
+            redact_line("cred add --user a --pass='x y z'"),
+            "cred add --user a --pass=<redacted>"
+        );
+        assert_eq!(redact_line("vr ip"), "vr ip");
+    }
+
+    #[test]
+    fn strip_secret_key_values() {
+        let mut vars = json!({
+            "ip": "10.0.0.1",
+            "admin": {"user": "administrator", "password": "S3cret!", "NTLM": ["aad3b435"]},
+            "keys": {"ssh_private_key": {"path": "id_rsa"}},
+            "empty_pass": null
+        });
+        strip_secret_keys(&mut vars, "<x>");
+        assert_eq!(
+            vars,
+            json!({
+                "ip": "10.0.0.1",
+                "admin": {"user": "administrator", "password": "<x>", "NTLM": ["<x>"]},
+                "keys": {"ssh_private_key": {"path": "<x>"}},
+                "empty_pass": null
+            })
+        );
+    }
+
+    #[test]
+    fn view_attaches_vault_next_to_vars_creds() {
+        let creds = vec![Credential {
+            id: 1,
+            user: "svc_sql".to_string(),
+            domain: Some("CORP".to_string()),
+            kind: CredKind::Password,
+            secret: "Summer2023!".to_string(),
+            source: None,
+            tries: vec![],
+            added_at: Utc::now(),
+        }];
+        let mut data = json!({"creds": {"admin": {"user": "administrator"}}});
+        attach_data(&mut data, &Default::default(), &creds);
+        assert_eq!(data["creds"], json!({"admin": {"user": "administrator"}}));
+        assert_eq!(data["vault"][0]["account"], json!("CORP\\svc_sql"));
+
+        let mut data = json!({});
+        attach_data(&mut data, &Default::default(), &creds);
+        assert_eq!(data["creds"], data["vault"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn private_temp_dir_is_new_and_0700() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let a = create_private_temp_dir("pwnrail-test-private").unwrap();
+        let b = create_private_temp_dir("pwnrail-test-private").unwrap();
+        assert_ne!(a, b);
+        let mode = std::fs::metadata(&a).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+        let _ = std::fs::remove_dir_all(&a);
+        let _ = std::fs::remove_dir_all(&b);
+    }
+
+    #[test]
+    fn undo_walks_back_through_revisions() {
+        let revision = |rev: u64, summary: &str| Revision {
+            rev,
+            time: Utc::now(),
+            summary: summary.to_string(),
+            fingerprint: String::new(),
+        };
+        let mut revisions = vec![
+            revision(0, "initial state"),
+            revision(1, "vm ip 10.0.0.1"),
+            revision(2, "vm ip 10.0.0.2"),
+        ];
+        assert_eq!(undo_target(&revisions), Some(1));
+        revisions.push(revision(3, &undo_summary(1)));
+        assert_eq!(undo_target(&revisions), Some(0));
+        revisions.push(revision(4, &undo_summary(0)));
+        assert_eq!(undo_target(&revisions), None);
+
+        // undoのあとに変更すれば、その変更の前 (undoで戻した状態) に戻る
+        revisions.push(revision(5, "vm ip 10.0.0.3"));
+        assert_eq!(undo_target(&revisions), Some(4));
+        assert_eq!(undo_target(&revisions[..1]), None);
+    }
+
+    #[test]
+    fn view_merges_vars_hosts_with_hosts_json() {
+        let mut hosts = Hosts::new();
+        hosts.insert(
+            "dc01".to_string(),
+            Host {
+                addresses: vec!["10.0.0.5".to_string()],
+                ..Default::default()
+            },
+        );
+        hosts.insert(
+            "web01".to_string(),
+            Host {
+                addresses: vec!["10.0.0.8".to_string()],
+                ..Default::default()
+            },
+        );
+        let mut data = json!({"hosts": {"dc01": {"ip": "10.0.0.50", "note": "pivot"}}});
+        attach_data(&mut data, &hosts, &vec![]);
+        assert_eq!(data["hosts"]["dc01"]["ip"], json!("10.0.0.50"));
+        assert_eq!(data["hosts"]["dc01"]["note"], json!("pivot"));
+        assert_eq!(data["hosts"]["dc01"]["addresses"], json!(["10.0.0.5"]));
+        assert_eq!(data["hosts"]["web01"]["ip"], json!("10.0.0.8"));
+    }
+
+    #[test]
+    fn vault_ids_are_not_reused() {
+        // next_idがなかったころのcreds.jsonも読める
+        let old = r#"[{"id": 1, "user": "a", "kind": "password", "secret": "x", "added_at": "2024-01-01T00:00:00Z"},
+                      {"id": 2, "user": "b", "kind": "hash", "secret": "y", "added_at": "2024-01-01T00:00:00Z"}]"#;
+        let mut vault: Vault = serde_json::from_str(old).unwrap();
+        assert_eq!(vault.creds.len(), 2);
+        assert_eq!(vault.take_id(), 3);
+        vault.creds.retain(|c| c.id != 2);
+        assert_eq!(vault.take_id(), 4);
+
+        let saved = serde_json::to_string(&vault).unwrap();
+        let mut vault: Vault = serde_json::from_str(&saved).unwrap();
+        assert_eq!(vault.next_id, 5);
+        assert_eq!(vault.take_id(), 5);
+        assert_eq!(serde_json::from_str::<Vault>("{}").unwrap().take_id(), 1);
+    }
+
+    #[test]
+    fn finding_ids_are_not_reused() {
+        // next_idがなかったころのfindings.jsonも読める
+        let old = r#"[{"id": 1, "title": "a", "severity": "low", "status": "open", "created_at": "2024-01-01T00:00:00Z", "updated_at": "2024-01-01T00:00:00Z"},
+                      {"id": 2, "title": "b", "severity": "high", "status": "open", "created_at": "2024-01-01T00:00:00Z", "updated_at": "2024-01-01T00:00:00Z"}]"#;
+        let mut book: FindingBook = serde_json::from_str(old).unwrap();
+        assert_eq!(book.findings.len(), 2);
+        assert_eq!(book.take_id(), 3);
+        // 一番大きいidの指摘事項を消しても、そのidは使い回さない
+        book.findings.retain(|f| f.id != 2);
+        let saved = serde_json::to_string(&book).unwrap();
+        let mut book: FindingBook = serde_json::from_str(&saved).unwrap();
+        assert_eq!(book.next_id, 4);
+        assert_eq!(book.take_id(), 4);
+        assert_eq!(
+            serde_json::from_str::<FindingBook>("{}").unwrap().take_id(),
+            1
+        );
+    }
+
+    #[test]
+    fn schema_skips_secret_and_template_values() {
+        let dir = std::env::temp_dir().join(format!("pwnrail-test-schema-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("vars.schema.json");
+        std::fs::write(&path, TEMPLATE).unwrap();
+
+        let secret = format!("{}AAAA", SECRET_PREFIX);
+        let ok = json!({"ip": secret, "ports": ["{{web.port}}", 445], "creds": [{"user": "a"}]});
+        assert!(validate(&path, &ok).is_ok());
+        let ok = json!({"ip": "{{hosts.dc01.ip}}"});
+        assert!(validate(&path, &ok).is_ok());
+        let ng = json!({"ip": "10.0.0", "ports": ["445"]});
+        assert!(validate(&path, &ng).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn secret_round_trip() {
+        let salt = [7u8; 16];
+        let key = key_from_passphrase("correct horse", &salt).unwrap();
+        for plaintext in [
+            "P@ssw0rd",
+            "",
+            "josé 日本語",
+            "aad3b435b51404eeaad3b435b51404ee",
+        ] {
+            let encrypted = encrypt(&key, plaintext).unwrap();
+            assert!(encrypted.starts_with(SECRET_PREFIX));
+            assert!(plaintext.is_empty() || !encrypted.contains(plaintext));
+            assert_eq!(decrypt(&key, &encrypted).unwrap(), plaintext);
+        }
+        // 同じ値でもnonceが違うので、暗号文は毎回変わる
+        assert_ne!(encrypt(&key, "x").unwrap(), encrypt(&key, "x").unwrap());
+
+        let mut value = json!({"creds": [{"pass": encrypt(&key, "x").unwrap()}], "ip": "10.0.0.1"});
+        assert!(is_secret(&value["creds"][0]["pass"]));
+        mask(&mut value);
+        assert_eq!(
+            value,
+            json!({"creds": [{"pass": MASKED_VALUE}], "ip": "10.0.0.1"})
+        );
+    }
+
+    #[test]
+    fn secret_wrong_passphrase() {
+        let salt = [7u8; 16];
+        let key = key_from_passphrase("correct horse", &salt).unwrap();
+        let wrong = key_from_passphrase("battery staple", &salt).unwrap();
+        let encrypted = encrypt(&key, "P@ssw0rd").unwrap();
+        assert!(matches!(
+            decrypt(&wrong, &encrypted),
+            Err(SecretError::DecryptError)
+        ));
+        // unlockでは検証値で鍵を確かめる
+        let check = encrypt(&key, "pwnrail").unwrap();
+        assert!(verify(&key, &check).is_ok());
+        assert!(matches!(verify(&wrong, &check), Err(SecretError::WrongKey)));
+        // 壊れた値は鍵が正しくても復号できない
+        let broken = format!("{}{}", SECRET_PREFIX, "AAAA");
+        assert!(matches!(
+            decrypt(&key, &broken),
+            Err(SecretError::InvalidFormat)
+        ));
+    }
+
+    #[test]
+    fn watch_follow_behind_changed_events() {
+        let dir = temp_dir("watch");
+        let a = dir.join("a").join(".prail");
+        let b = dir.join("b").join(".prail");
+        fs::create_dir_all(&a).unwrap();
+        fs::create_dir_all(&b).unwrap();
+        let (a_vars, b_vars) = (a.join("vars.json"), b.join("vars.json"));
+        fs::write(&a_vars, r#"{"ip": "10.0.0.1"}"#).unwrap();
+        fs::write(&b_vars, r#"{"domain": "corp.local"}"#).unwrap();
+
+        let mut tracker = Tracker::default();
+        assert!(tracker
+            .handle(vec![WatchEvent::Follow(a_vars.clone())], None)
+            .is_empty());
+        // aが外で変更されたあと、通知を出す前にuseでbに切り替えた
+        fs::write(&a_vars, r#"{"ip": "10.0.0.2"}"#).unwrap();
+        let notices = tracker.handle(
+            vec![
+                WatchEvent::Changed(a_vars.clone()),
+                WatchEvent::Changed(a_vars.clone()),
+                WatchEvent::Follow(b_vars.clone()),
+                WatchEvent::Changed(a_vars.clone()),
+            ],
+            None,
+        );
+        assert_eq!(notices.len(), 1);
+        assert!(notices[0].contains("~ip"));
+
+        // 基準はbになっているので、bの変更だけが通知される
+        fs::write(&b_vars, r#"{"domain": "corp.local", "dc": "dc01"}"#).unwrap();
+        let notices = tracker.handle(vec![WatchEvent::Changed(b_vars.clone())], None);
+        assert_eq!(notices.len(), 1);
+        assert!(notices[0].contains("+dc"));
+        assert!(!notices[0].contains("ip"));
+        // 切り替える前のワークスペースのイベントは無視する
+        fs::write(&a_vars, r#"{"ip": "10.0.0.3"}"#).unwrap();
+        assert!(tracker
+            .handle(vec![WatchEvent::Changed(a_vars)], None)
+            .is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    // yamlやtomlで型が変わりやすい値
+    fn format_sample() -> serde_json::Value {
+        json!({
+            "ip": "10.0.0.1",
+            "port": 445,
+            "ratio": 0.5,
+            "admin": true,
+            "not_bool": "true",
+            "not_num": "0445",
+            "empty": "",
+            "note": "line1\nline2: \"quoted\" # not a comment",
+            "user": "josé",
+            "pass": "pwnrail-secret:v1:AAAA",
+            "url": "http://{{ip}}:{{port}}/",
+            "creds": [{"user": "admin", "hash": "aad3b435b51404ee"}, {"user": "guest"}],
+            "ports": [22, 80],
+            "hosts": {"dc01.corp.local": {"ip": "10.0.0.5", "tags": []}},
+        })
+    }
+
+    // ファイルから読んで書き込み、もう一度読んだ値が最初に読んだ値と同じになる
+    fn format_round_trip(format: VarsFormat, value: &serde_json::Value) {
+        let dir = temp_dir(&format!("format-{}", format.extension()));
+        let path = dir.join(format.file_name());
+        fs::write(&path, format.serialize(value).unwrap()).unwrap();
+        let read = format.parse(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(read, *value);
+        fs::write(&path, format.serialize(&read).unwrap()).unwrap();
+        let again = format.parse(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(again, read);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn yaml_round_trip() {
+        let mut value = format_sample();
+        value["nothing"] = serde_json::Value::Null;
+        format_round_trip(VarsFormat::Yaml, &value);
+        assert_eq!(VarsFormat::Yaml.parse("").unwrap(), json!({}));
+    }
+
+    #[test]
+    fn toml_round_trip() {
+        format_round_trip(VarsFormat::Toml, &format_sample());
+        // tomlにはnullがないので書き込めない
+        assert!(matches!(
+            VarsFormat::Toml.serialize(&json!({"a": [1, null]})),
+            Err(VarsFormatError::TomlNullError)
+        ));
+    }
+
+    #[test]
+    fn flag_builtin_formats() {
+        // flag_formats.tomlがなければ組み込みの形式だけになる
+        let dir = temp_dir("flag-builtin");
+        let formats = flags::formats(&dir).unwrap();
+        let cases = [
+            (
+                "htb",
+                vec!["HTB{s0m3_fl4g}", "0123456789abcdef0123456789abcdef"],
+                vec![
+                    "htb{lower}",
+                    "HTB{}",
+                    "0123456789ABCDEF0123456789ABCDEF",
+                    "0123456789abcdef0123456789abcde",
+                    "HTB{a} trailing",
+                ],
+            ),
+            (
+                "thm",
+                vec!["THM{flag_here}"],
+                vec!["HTB{flag}", "THM{}", "THM{a}b"],
+            ),
+            (
+                "picoctf",
+                vec!["picoCTF{b4by_st3ps}"],
+                vec!["PICOCTF{x}", "picoCTF{x", "xpicoCTF{x}"],
+            ),
+            (
+                "ctfd",
+                vec!["flag{x}", "CTF_2024{a b c}"],
+                vec!["fl-ag{x}", "{x}", "flag{}", "flag"],
+            ),
+            ("any", vec!["anything"], vec!["", "two words"]),
+        ];
+        for (platform, accepted, rejected) in cases {
+            assert!(!formats[platform].custom);
+            for value in accepted {
+                assert!(
+                    flags::validate(&formats, platform, value).is_ok(),
+                    "{} should accept {}",
+                    platform,
+                    value
+                );
+            }
+            for value in rejected {
+                assert!(
+                    matches!(
+                        flags::validate(&formats, platform, value),
+                        Err(FlagsError::FormatMismatch(..))
+                    ),
+                    "{} should reject {}",
+                    platform,
+                    value
+                );
+            }
+        }
+        assert!(matches!(
+            flags::validate(&formats, "nope", "HTB{x}"),
+            Err(FlagsError::UnknownPlatform(_))
+        ));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn flag_custom_formats() {
+        let dir = temp_dir("flag-custom");
+        fs::write(
+            dir.join("flag_formats.toml"),
+            "ductf = '^DUCTF\\{[^}]+\\}$'\nthm = '^THM\\{[a-z]+\\}$'\nbroken = '('\n",
+        )
+        .unwrap();
+        let formats = flags::formats(&dir).unwrap();
+        assert!(formats["ductf"].custom);
+        assert!(flags::validate(&formats, "ductf", "DUCTF{x}").is_ok());
+        assert!(flags::validate(&formats, "ductf", "HTB{x}").is_err());
+        // 同じ名前の組み込みの形式は上書きされる
+        assert!(formats["thm"].custom);
+        assert!(flags::validate(&formats, "thm", "THM{abc}").is_ok());
+        assert!(flags::validate(&formats, "thm", "THM{ABC}").is_err());
+        assert!(!formats["htb"].custom);
+        assert!(matches!(
+            flags::validate(&formats, "broken", "x"),
+            Err(FlagsError::InvalidFormat(..))
+        ));
+
+        fs::write(dir.join("flag_formats.toml"), "ductf = [").unwrap();
+        assert!(matches!(
+            flags::formats(&dir),
+            Err(FlagsError::ParseFormatsError(..))
+        ));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn loot_dedup_by_sha256() {
+        let dir = temp_dir("loot-dedup");
+        let loot_path = dir.join("loot");
+        fs::write(dir.join("id_rsa"), "key").unwrap();
+        fs::write(dir.join("id_rsa.bak"), "key").unwrap();
+        let mut index = LootIndex::default();
+        let web = Some("web01".to_string());
+
+        let (l, new) = loot::store(
+            &loot_path,
+            &mut index,
+            &dir.join("id_rsa"),
+            web.clone(),
+            None,
+        )
+        .unwrap();
+        assert!(new);
+        assert_eq!((l.id, l.file.as_str()), (1, "1-id_rsa"));
+        assert_eq!(l.sha256, loot::sha256(b"key"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(loot_path.join(&l.file))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        // 同じ場所からもう一度持ってきても、lootもsourcesも増えない
+        let (l, new) = loot::store(
+            &loot_path,
+            &mut index,
+            &dir.join("id_rsa"),
+            web.clone(),
+            None,
+        )
+        .unwrap();
+        assert!(!new);
+        assert_eq!(l.sources.len(), 1);
+
+        // 同じ内容のファイルは、コピーせずにsourcesに足す
+        let (l, new) = loot::store(
+            &loot_path,
+            &mut index,
+            &dir.join("id_rsa.bak"),
+            web.clone(),
+            None,
+        )
+        .unwrap();
+        assert!(!new);
+        assert_eq!(l.id, 1);
+        assert_eq!(l.sources.len(), 2);
+        assert!(l.sources[1].path.ends_with("id_rsa.bak"));
+        // 別のホストで見つけた場合も、同じパスでもsourcesに足す
+        let (l, _) = loot::store(
+            &loot_path,
+            &mut index,
+            &dir.join("id_rsa"),
+            Some("dc01".to_string()),
+            None,
+        )
+        .unwrap();
+        assert_eq!(l.sources.len(), 3);
+        assert_eq!(index.loot.len(), 1);
+        assert_eq!(fs::read_dir(&loot_path).unwrap().count(), 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn loot_ids_are_not_reused() {
+        let dir = temp_dir("loot-ids");
+        let loot_path = dir.join("loot");
+        fs::write(dir.join("a"), "a").unwrap();
+        fs::write(dir.join("b"), "b").unwrap();
+        // next_idがなかったころのloot.jsonも読める
+        let mut index: LootIndex = serde_json::from_str("[]").unwrap();
+        loot::store(&loot_path, &mut index, &dir.join("a"), None, None).unwrap();
+        let (b, _) = loot::store(&loot_path, &mut index, &dir.join("b"), None, None).unwrap();
+        assert_eq!(b.id, 2);
+
+        // 一番大きいidのlootをloot.jsonから消しても、そのidとファイルは使い回さない
+        index.loot.retain(|l| l.id != 2);
+        let mut index: LootIndex =
+            serde_json::from_str(&serde_json::to_string(&index).unwrap()).unwrap();
+        assert_eq!(index.next_id, 3);
+        fs::write(dir.join("b"), "b2").unwrap();
+        let (l, new) = loot::store(&loot_path, &mut index, &dir.join("b"), None, None).unwrap();
+        assert!(new);
+        assert_eq!(l.id, 3);
+        assert_eq!(fs::read(loot_path.join("2-b")).unwrap(), b"b");
+
+        // next_idがなくても、残っているファイルは上書きしない
+        index.next_id = 0;
+        index.loot.retain(|l| l.id != 3);
+        fs::write(dir.join("b"), "b3").unwrap();
+        let (l, _) = loot::store(&loot_path, &mut index, &dir.join("b"), None, None).unwrap();
+        assert_eq!(l.id, 4);
+        assert_eq!(fs::read(loot_path.join("3-b")).unwrap(), b"b2");
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,139 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{error::TimelineError, json::json::split_quoted_args};
+
+// ワークスペースでしたことの記録 (.prail/timeline.jsonl)
+// 1行に1つ、次のどれかを追記していく
+//  command     シェルで実行したpwnrailのコマンド
+//  external    シェルから !cmd で実行した外部コマンド
+//  vars        varsファイルの変更。revは変更履歴のリビジョン (store::historyを参照)
+// 報告書のために、いつ何をしたかを後から正確に出せるようにする
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Entry {
+    pub time: DateTime<Utc>,
+    pub kind: EntryKind,
+    pub command: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub args: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ok: Option<bool>,
+    // 外部コマンドの終了コード。シグナルで終了した場合などはない
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rev: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind {
+    Command,
+    External,
+    Vars,
+}
+
+impl std::fmt::Display for EntryKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EntryKind::Command => write!(f, "command"),
+            EntryKind::External => write!(f, "external"),
+            EntryKind::Vars => write!(f, "vars"),
+        }
+    }
+}
+
+// 1行ずつ追記するだけなので、ほかのプロセスと同時に書いても行が混ざることはない
+pub fn append(path: &Path, entry: &Entry) -> Result<(), TimelineError> {
+    let line = match serde_json::to_string(entry) {
+        Ok(l) => l,
+        Err(e) => return Err(TimelineError::SerializeError(e)),
+    };
+    let result = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut f| f.write_all(format!("{}\n", line).as_bytes()));
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => Err(TimelineError::WriteError(e)),
+    }
+}
+
+pub fn read(path: &Path) -> Result<Vec<Entry>, TimelineError> {
+    let content = match fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(TimelineError::ReadError(e)),
+    };
+    let mut entries = vec![];
+    for (i, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<Entry>(line) {
+            Ok(e) => entries.push(e),
+            Err(e) => return Err(TimelineError::ParseError(i + 1, e)),
+        }
+    }
+    Ok(entries)
+}
+
+// 秘密の値を記録に残さないように、引数の中の値を伏せる
+//  vm --secret <path> <value>      値を伏せる
+//  --pass, --hash, --key <value>   cred addなどの値を伏せる
+// 入力された1行をコマンドと引数に分けてから伏せる。シェルの履歴に残すときに使う
+pub fn redact_line(line: &str) -> String {
+    let line = line.trim();
+    match line.split_once(char::is_whitespace) {
+        Some((c, a)) => format!("{} {}", c, redact(c, a)),
+        None => line.to_string(),
+    }
+}
+
+pub fn redact(command: &str, args: &str) -> String {
+    const REDACTED: &str = "<redacted>";
+    // cred add --pass "a b" のような引用符で囲んだ値も1つの引数として伏せる
+    let words = split_quoted_args(args);
+    if command == "vm" && words.contains(&"--secret") {
+        // フラグと最初の引数 (パス) だけを残し、値は伏せる
+        let mut kept = vec![];
+        let mut path_seen = false;
+        for w in words {
+            if w.starts_with("--") {
+                kept.push(w);
+            } else if !path_seen {
+                kept.push(w);
+                path_seen = true;
+            }
+        }
+        return format!("{} {}", kept.join(" "), REDACTED);
+    }
+    let mut result = vec![];
+    let mut hide_next = false;
+    for w in words {
+        if hide_next {
+            result.push(REDACTED.to_string());
+            hide_next = false;
+            continue;
+        }
+        match w.split_once('=') {
+            Some((flag, _)) if matches!(flag, "--pass" | "--hash" | "--key") => {
+                result.push(format!("{}={}", flag, REDACTED));
+            }
+            _ => {
+                hide_next = matches!(w, "--pass" | "--hash" | "--key");
+                result.push(w.to_string());
+            }
+        }
+    }
+    result.join(" ")
+}
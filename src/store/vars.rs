@@ -0,0 +1,210 @@
+use std::{fs, path::PathBuf};
+
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use super::{format::VarsFormat, history, lock, merge, schema};
+use crate::{
+    config::{AppConfig, Workspace},
+    error::{VarsHistoryError, VarsStoreError},
+    json::json::Json,
+};
+
+// varsファイルの読み書きはすべてこのモジュールを通して行う
+// 読み込みは共有ロック、書き込みは排他ロックを取って行い、
+// 読み込んでから書き込むまでの間に他のプロセスがファイルを変更していた場合は、3-way mergeしてから書き込む
+
+// 読み込んだ時点のvarsファイルの内容
+// jsonを変更してからsaveに渡す
+pub struct VarsSnapshot {
+    pub json: Json,
+    base: Value,
+    fingerprint: String,
+}
+
+// saveの結果
+pub enum SaveOutcome {
+    Written,
+    // 他のプロセスの変更とマージしてから書き込んだ
+    Merged,
+}
+
+// varsファイルを書き込んだあとの結果
+// 変更履歴への記録に失敗しても変更自体は保存されているので、エラーにはせずに呼び出し側で知らせる
+pub struct Saved {
+    pub outcome: SaveOutcome,
+    pub rev: Result<u64, VarsHistoryError>,
+}
+
+pub fn workspace(app_conf: &AppConfig) -> Result<Workspace, VarsStoreError> {
+    if app_conf.dyn_conf.current_workspace.is_empty() {
+        return Err(VarsStoreError::NoCurrentWorkspace);
+    }
+    Ok(app_conf.dyn_conf.to_workspace())
+}
+
+pub fn fingerprint(content: &[u8]) -> String {
+    hex::encode(Sha256::digest(content))
+}
+
+pub fn load(app_conf: &mut AppConfig) -> Result<VarsSnapshot, VarsStoreError> {
+    let workspace = workspace(app_conf)?;
+    let _lock = match lock::lock_shared(&workspace.vars_path) {
+        Ok(l) => l,
+        Err(e) => return Err(VarsStoreError::LockError(e)),
+    };
+    let content = match fs::read_to_string(&workspace.vars_path) {
+        Ok(c) => c,
+        Err(e) => return Err(VarsStoreError::ReadError(e)),
+    };
+    let data = match workspace.vars_format.parse(&content) {
+        Ok(d) => d,
+        Err(e) => return Err(VarsStoreError::ParseError(e)),
+    };
+    let fingerprint = fingerprint(content.as_bytes());
+    *app_conf.vars_fingerprint.lock().unwrap() = Some(fingerprint.clone());
+    Ok(VarsSnapshot {
+        json: Json { data: data.clone() },
+        base: data,
+        fingerprint,
+    })
+}
+
+// summaryは変更履歴に記録される変更の説明 (実行したコマンドなど)
+// timelineへの記録は呼び出し側で行う (command::vars::modify::saveを参照)
+pub fn save(
+    app_conf: &mut AppConfig,
+    snapshot: VarsSnapshot,
+    summary: &str,
+) -> Result<Saved, VarsStoreError> {
+    let workspace = workspace(app_conf)?;
+    let _lock = match lock::lock_exclusive(&workspace.vars_path) {
+        Ok(l) => l,
+        Err(e) => return Err(VarsStoreError::LockError(e)),
+    };
+    let current = match fs::read(&workspace.vars_path) {
+        Ok(c) => c,
+        Err(e) => return Err(VarsStoreError::ReadError(e)),
+    };
+
+    // 読み込んだあとに他のプロセスが変更していたらマージする
+    // エディタなどで壊された場合は、手で直せるように上書きせずにエラーにする
+    let (before, data, outcome) = if fingerprint(&current) == snapshot.fingerprint {
+        (snapshot.base, snapshot.json.data, SaveOutcome::Written)
+    } else {
+        let theirs = match workspace
+            .vars_format
+            .parse(&String::from_utf8_lossy(&current))
+        {
+            Ok(t) => t,
+            Err(e) => return Err(VarsStoreError::ParseError(e)),
+        };
+        match merge::merge3(&snapshot.base, &snapshot.json.data, &theirs) {
+            Ok(merged) => (theirs, merged, SaveOutcome::Merged),
+            Err(path) => return Err(VarsStoreError::Conflict(path)),
+        }
+    };
+
+    // スキーマがあれば、マージしたあとの値が違反していないか確かめる
+    match schema::validate(&workspace.schema_path, &data) {
+        Ok(_) => (),
+        Err(e) => return Err(VarsStoreError::SchemaError(e)),
+    };
+    let content = match workspace.vars_format.serialize(&data) {
+        Ok(c) => c,
+        Err(e) => return Err(VarsStoreError::SerializeError(e)),
+    };
+    match lock::write_atomic(&workspace.vars_path, &content) {
+        Ok(_) => (),
+        Err(e) => return Err(VarsStoreError::WriteError(e)),
+    };
+    *app_conf.vars_fingerprint.lock().unwrap() = Some(fingerprint(&content));
+    // 変更履歴に記録する
+    let before = history::Content {
+        raw: &current,
+        value: &before,
+    };
+    let after = history::Content {
+        raw: &content,
+        value: &data,
+    };
+    let rev = history::record(&workspace.history_path, before, after, summary);
+    Ok(Saved { outcome, rev })
+}
+
+// 別のワークスペースのvarsファイルを読み込む関数 (vars mergeで使う)
+// 読むだけなので、指紋の記録などはしない
+pub fn read_workspace(workspace: &Workspace) -> Result<Value, VarsStoreError> {
+    let _lock = match lock::lock_shared(&workspace.vars_path) {
+        Ok(l) => l,
+        Err(e) => return Err(VarsStoreError::LockError(e)),
+    };
+    let content = match fs::read_to_string(&workspace.vars_path) {
+        Ok(c) => c,
+        Err(e) => return Err(VarsStoreError::ReadError(e)),
+    };
+    match workspace.vars_format.parse(&content) {
+        Ok(d) => Ok(d),
+        Err(e) => Err(VarsStoreError::ParseError(e)),
+    }
+}
+
+// varsファイルを別の形式に変換する関数。変換したあとは元のファイルを削除する
+// 変換も変更履歴に記録される。saveと同じく、記録に失敗しても変換したファイルのパスと一緒に返す
+pub fn convert(
+    app_conf: &mut AppConfig,
+    to: VarsFormat,
+) -> Result<(PathBuf, Result<u64, VarsHistoryError>), VarsStoreError> {
+    let workspace = workspace(app_conf)?;
+    if workspace.vars_format == to {
+        return Err(VarsStoreError::AlreadyInFormat(workspace.vars_path));
+    }
+    let new_path = workspace.mgr_path.join(to.file_name());
+    let _lock = match lock::lock_exclusive(&workspace.vars_path) {
+        Ok(l) => l,
+        Err(e) => return Err(VarsStoreError::LockError(e)),
+    };
+    let current = match fs::read(&workspace.vars_path) {
+        Ok(c) => c,
+        Err(e) => return Err(VarsStoreError::ReadError(e)),
+    };
+    let data = match workspace
+        .vars_format
+        .parse(&String::from_utf8_lossy(&current))
+    {
+        Ok(d) => d,
+        Err(e) => return Err(VarsStoreError::ParseError(e)),
+    };
+    let content = match to.serialize(&data) {
+        Ok(c) => c,
+        Err(e) => return Err(VarsStoreError::SerializeError(e)),
+    };
+    match lock::write_atomic(&new_path, &content) {
+        Ok(_) => (),
+        Err(e) => return Err(VarsStoreError::WriteError(e)),
+    };
+    match fs::remove_file(&workspace.vars_path) {
+        Ok(_) => (),
+        Err(e) => return Err(VarsStoreError::WriteError(e)),
+    };
+    *app_conf.vars_fingerprint.lock().unwrap() = Some(fingerprint(&content));
+    let before = history::Content {
+        raw: &current,
+        value: &data,
+    };
+    let after = history::Content {
+        raw: &content,
+        value: &data,
+    };
+    let summary = format!("vars convert --to {}", to.extension());
+    let rev = history::record(&workspace.history_path, before, after, &summary);
+    Ok((new_path, rev))
+}
+
+// プロンプトに表示する通知のために、現在のvarsファイルのハッシュを取得する関数
+// 読めない場合はNoneを返す
+pub fn current_fingerprint(app_conf: &AppConfig) -> Option<String> {
+    let path = workspace(app_conf).ok()?.vars_path;
+    let _lock = lock::lock_shared(&path).ok()?;
+    fs::read(&path).ok().map(|c| fingerprint(&c))
+}
@@ -0,0 +1,63 @@
+use serde_json::Value;
+
+use super::{
+    creds::{self, Creds},
+    hosts::{self, Hosts},
+    merge::{self, MergeStrategy},
+};
+use crate::{config::AppConfig, error::ViewError};
+
+// vrや {{query}} で、varsファイルの値に加えてワークスペースのほかのデータも参照できるようにする
+//  hosts   hosts.jsonのホストとサービス (store::hostsを参照)。varsファイルにもhostsがあれば、ホストごとにマージする
+//  vault   creds.jsonの認証情報 (store::credsを参照)
+//  creds   varsファイルにcredsがなければ、vaultと同じもの
+// varsファイルに同じ名前のキーがある場合はvarsファイルの値を優先する。空のデータは足さない
+pub fn attach(app_conf: &AppConfig, data: &mut Value) -> Result<(), ViewError> {
+    let hosts = match hosts::load(app_conf) {
+        Ok(h) => h,
+        Err(e) => return Err(ViewError::HostsError(e)),
+    };
+    let creds = match creds::load(app_conf) {
+        Ok(c) => c,
+        Err(e) => return Err(ViewError::CredsError(e)),
+    };
+    attach_data(data, &hosts, &creds);
+    Ok(())
+}
+
+pub fn attach_data(data: &mut Value, hosts: &Hosts, creds: &Creds) {
+    let map = match data {
+        Value::Object(m) => m,
+        _ => return,
+    };
+    if !hosts.is_empty() {
+        let attached = hosts::to_value(hosts);
+        let merged = match map.get("hosts") {
+            // 同じ値がどちらにもあればvarsファイルの値を使う
+            Some(ours @ Value::Object(_)) => Some(merge::deep_merge(
+                ours,
+                &attached,
+                MergeStrategy::Keep,
+                "hosts",
+                &mut vec![],
+            )),
+            // オブジェクト以外はマージできないので、varsファイルの値をそのまま使う
+            Some(_) => None,
+            None => Some(attached),
+        };
+        if let Some(m) = merged {
+            map.insert("hosts".to_string(), m);
+        }
+    }
+    if creds.is_empty() {
+        return;
+    }
+    let vault = creds::to_value(creds);
+    // varsのcredsを使っていても、creds.jsonの認証情報はvaultでいつでも参照できる
+    if !map.contains_key("creds") {
+        map.insert("creds".to_string(), vault.clone());
+    }
+    if !map.contains_key("vault") {
+        map.insert("vault".to_string(), vault);
+    }
+}
@@ -0,0 +1,248 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use notify::{RecursiveMode, Watcher};
+use serde_json::Value;
+use tokio::sync::mpsc;
+
+use super::{format::VarsFormat, lock, vars::fingerprint};
+use crate::{
+    config::AppConfig,
+    error::VarsFormatError,
+    json::diff,
+    output::{error_prefix, log_prefix},
+};
+
+// エディタやスクリプトがvarsファイルを書き換えたときに、シェルに通知するための監視
+// varsファイルはrenameで置き換えられるので、ファイルではなく管理ディレクトリ(.prail)を監視する
+// notifyのイベントはtokioのタスクに送り、そこでパースと差分の計算をして1行の通知を出す
+//
+// このプロセス自身の書き込み(vm, vdなど)は、AppConfig.vars_fingerprintと一致するので通知しない
+
+// 連続した書き込みをまとめるための待ち時間
+const DEBOUNCE: Duration = Duration::from_millis(100);
+// 通知に並べるキーの数
+const MAX_KEYS_IN_NOTICE: usize = 5;
+
+pub(crate) enum WatchEvent {
+    // 監視対象が変わったので、差分の基準を読み込み直す
+    Follow(PathBuf),
+    Changed(PathBuf),
+}
+
+pub struct VarsWatcher {
+    watcher: notify::RecommendedWatcher,
+    tx: mpsc::UnboundedSender<WatchEvent>,
+    // 監視している管理ディレクトリ
+    watching: Option<PathBuf>,
+}
+
+impl VarsWatcher {
+    // printは通知を表示する関数。プロンプトを崩さないように、rustylineのExternalPrinterを渡す
+    pub fn new(
+        app_conf: &AppConfig,
+        print: impl FnMut(String) + Send + 'static,
+    ) -> Result<Self, notify::Error> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let event_tx = tx.clone();
+        let watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            if let Ok(event) = res {
+                if event.kind.is_access() {
+                    return;
+                }
+                for path in event.paths {
+                    if is_vars_file(&path) {
+                        let _ = event_tx.send(WatchEvent::Changed(path));
+                    }
+                }
+            }
+        })?;
+        tokio::spawn(handle_events(rx, app_conf.vars_fingerprint.clone(), print));
+        let mut w = Self {
+            watcher,
+            tx,
+            watching: None,
+        };
+        w.follow(app_conf);
+        Ok(w)
+    }
+
+    // 現在のワークスペースの管理ディレクトリを監視する。useでワークスペースが変わったときのためにコマンドごとに呼ぶ
+    pub fn follow(&mut self, app_conf: &AppConfig) {
+        let target = if app_conf.dyn_conf.current_workspace.is_empty() {
+            None
+        } else {
+            Some(app_conf.dyn_conf.to_workspace())
+        };
+        let mgr_path = target.as_ref().map(|w| w.mgr_path.clone());
+        if mgr_path == self.watching {
+            return;
+        }
+        if let Some(old) = self.watching.take() {
+            let _ = self.watcher.unwatch(&old);
+        }
+        if let Some(workspace) = target {
+            if self
+                .watcher
+                .watch(&workspace.mgr_path, RecursiveMode::NonRecursive)
+                .is_ok()
+            {
+                self.watching = Some(workspace.mgr_path);
+                let _ = self.tx.send(WatchEvent::Follow(workspace.vars_path));
+            }
+        }
+    }
+}
+
+fn is_vars_file(path: &Path) -> bool {
+    VarsFormat::from_path(path).is_some()
+}
+
+async fn handle_events(
+    mut rx: mpsc::UnboundedReceiver<WatchEvent>,
+    seen: Arc<Mutex<Option<String>>>,
+    mut print: impl FnMut(String),
+) {
+    let mut tracker = Tracker::default();
+    while let Some(event) = rx.recv().await {
+        let mut events = vec![event];
+        if let Some(WatchEvent::Changed(_)) = events.first() {
+            // 待っている間に届いたイベントもまとめて処理する
+            // useでワークスペースが変わったときのFollowも含まれるので、捨てずに順番どおりに処理する
+            tokio::time::sleep(DEBOUNCE).await;
+            while let Ok(e) = rx.try_recv() {
+                events.push(e);
+            }
+        }
+        let own = seen.lock().unwrap().clone();
+        for notice in tracker.handle(events, own.as_deref()) {
+            print(notice);
+        }
+    }
+}
+
+// 監視している管理ディレクトリと、差分の基準にする最後に読めたvarsファイルの内容
+#[derive(Default)]
+pub(crate) struct Tracker {
+    // 監視している管理ディレクトリ。notifyはシンボリックリンクを解決したパスを返すことがあるので、解決しておく
+    mgr_path: Option<PathBuf>,
+    last: Option<Value>,
+    // 最後に確認したハッシュ
+    last_fingerprint: Option<String>,
+    // 壊れていたvarsファイルが直ったことを通知するため
+    broken: bool,
+}
+
+impl Tracker {
+    // イベントを順番に処理して、表示する通知を返す
+    // ownはこのプロセスが最後に読み書きしたときのハッシュで、それと同じ内容になった変更は通知しない
+    // 同じファイルのChangedが続いた場合、2回目以降はハッシュが変わらないので何もしない
+    pub(crate) fn handle(&mut self, events: Vec<WatchEvent>, own: Option<&str>) -> Vec<String> {
+        let mut notices = vec![];
+        for event in events {
+            match event {
+                WatchEvent::Follow(path) => {
+                    let (fp, value) = read(&path);
+                    self.last = value.and_then(|v| v.ok());
+                    self.last_fingerprint = fp;
+                    self.broken = false;
+                    self.mgr_path = path.parent().and_then(|p| fs::canonicalize(p).ok());
+                }
+                WatchEvent::Changed(path) => self.changed(&path, own, &mut notices),
+            }
+        }
+        notices
+    }
+
+    fn changed(&mut self, path: &Path, own: Option<&str>, notices: &mut Vec<String>) {
+        // 切り替える前のワークスペースのイベントが遅れて届いた場合は、今の基準と比べられないので無視する
+        // varsファイルの形式を変えた場合(vars convert)はファイル名が変わるので、ディレクトリで比べる
+        let dir = path.parent().and_then(|p| fs::canonicalize(p).ok());
+        if dir.is_none() || dir != self.mgr_path {
+            return;
+        }
+        let (fp, value) = read(path);
+        if fp.is_none() || fp == self.last_fingerprint {
+            return;
+        }
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        self.last_fingerprint = fp.clone();
+        let own = fp.as_deref() == own;
+        match value {
+            Some(Ok(new)) => {
+                if self.broken {
+                    notices.push(format!("{} {} can be read again", log_prefix(), name));
+                    self.broken = false;
+                }
+                if !own {
+                    if let Some(old) = &self.last {
+                        notices.extend(notice(&name, old, &new));
+                    }
+                }
+                self.last = Some(new);
+            }
+            Some(Err(e)) => {
+                self.broken = true;
+                notices.push(format!(
+                    "{} {} is broken ({}). vm and vd refuse to overwrite it until it is fixed",
+                    error_prefix(),
+                    name,
+                    e
+                ))
+            }
+            None => (),
+        }
+    }
+}
+
+// varsファイルを読んでハッシュとパース結果を返す。読めなかった場合はどちらもNone
+fn read(path: &Path) -> (Option<String>, Option<Result<Value, VarsFormatError>>) {
+    let format = match VarsFormat::from_path(path) {
+        Some(f) => f,
+        None => return (None, None),
+    };
+    let _lock = match lock::lock_shared(path) {
+        Ok(l) => l,
+        Err(_) => return (None, None),
+    };
+    match fs::read_to_string(path) {
+        Ok(content) => (
+            Some(fingerprint(content.as_bytes())),
+            Some(format.parse(&content)),
+        ),
+        Err(_) => (None, None),
+    }
+}
+
+fn notice(name: &str, old: &Value, new: &Value) -> Option<String> {
+    let changes = diff::diff(old, new);
+    if changes.is_empty() {
+        return None;
+    }
+    let mut keys = changes
+        .iter()
+        .take(MAX_KEYS_IN_NOTICE)
+        .map(|c| match c {
+            diff::Change::Added(p, _) => format!("+{}", p),
+            diff::Change::Removed(p, _) => format!("-{}", p),
+            diff::Change::Modified(p, _, _) => format!("~{}", p),
+        })
+        .collect::<Vec<String>>()
+        .join(", ");
+    if changes.len() > MAX_KEYS_IN_NOTICE {
+        keys = format!("{} and {} more", keys, changes.len() - MAX_KEYS_IN_NOTICE);
+    }
+    Some(format!(
+        "{} {} was changed outside this shell: {}",
+        log_prefix(),
+        name,
+        keys
+    ))
+}
@@ -0,0 +1,86 @@
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use crate::{
+        config::{Workspace, WorkspaceMeta},
+        error::MigrateWorkspaceError,
+        migration::{migrate, CURRENT_SCHEMA_VERSION},
+    };
+
+    // テストごとに別の一時ディレクトリを作る
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("pwnrail-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    // 管理ディレクトリの中の、元のワークスペース以外のもの (バックアップ) を返す
+    fn backups(dir: &PathBuf) -> Vec<PathBuf> {
+        fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .filter(|p| p.to_string_lossy().contains(".backup-v"))
+            .collect()
+    }
+
+    #[test]
+    fn migrate_v0_records_schema_version() {
+        // metaファイルが導入される前のワークスペースは、.prailにvarsファイルだけがある
+        let dir = temp_dir("migrate-v0");
+        let vars = include_str!("testdata/v0/vars.json");
+        fs::create_dir_all(dir.join(".prail")).unwrap();
+        fs::write(dir.join(".prail").join("vars.json"), vars).unwrap();
+        let workspace = Workspace::assemble_struct(&dir);
+        assert!(workspace.read_meta().unwrap().is_none());
+
+        migrate(&workspace, false).unwrap();
+        let meta = workspace.read_meta().unwrap().unwrap();
+        assert_eq!(meta.schema_version, CURRENT_SCHEMA_VERSION);
+        assert_eq!(meta.pwnrail_version, env!("CARGO_PKG_VERSION"));
+        // varsファイルはそのままで、元の管理ディレクトリはバックアップされている
+        assert_eq!(fs::read_to_string(&workspace.vars_path).unwrap(), vars);
+        let backups = backups(&dir);
+        assert_eq!(backups.len(), 1);
+        assert!(backups[0].to_string_lossy().contains(".prail.backup-v0-"));
+        assert_eq!(
+            fs::read_to_string(backups[0].join("vars.json")).unwrap(),
+            vars
+        );
+        assert!(!backups[0].join("meta.toml").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn migrate_leaves_current_workspace_alone() {
+        let dir = temp_dir("migrate-current");
+        fs::create_dir_all(dir.join(".prail")).unwrap();
+        fs::write(dir.join(".prail").join("vars.json"), "{}").unwrap();
+        let workspace = Workspace::assemble_struct(&dir);
+        let mut meta = WorkspaceMeta::new(chrono::Utc::now());
+        meta.pwnrail_version = "0.0.1".to_string();
+        workspace.write_meta(&meta).unwrap();
+        let before = fs::read_to_string(&workspace.meta_path).unwrap();
+
+        migrate(&workspace, false).unwrap();
+        migrate(&workspace, true).unwrap();
+        assert_eq!(fs::read_to_string(&workspace.meta_path).unwrap(), before);
+        assert!(backups(&dir).is_empty());
+
+        // このpwnrailより新しいワークスペースは変更しない
+        meta.schema_version = CURRENT_SCHEMA_VERSION + 1;
+        workspace.write_meta(&meta).unwrap();
+        let before = fs::read_to_string(&workspace.meta_path).unwrap();
+        match migrate(&workspace, false) {
+            Err(MigrateWorkspaceError::TooNewSchema(v)) => {
+                assert_eq!(v, CURRENT_SCHEMA_VERSION + 1)
+            }
+            r => panic!("unexpected result: {:?}", r),
+        }
+        assert_eq!(fs::read_to_string(&workspace.meta_path).unwrap(), before);
+        assert!(backups(&dir).is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}